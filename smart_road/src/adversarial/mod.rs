@@ -0,0 +1,8 @@
+//! Adversarial testing module: searches for spawn sequences that make the
+//! intersection control fail
+
+pub mod scenario;
+pub mod search;
+
+pub use scenario::Scenario;
+pub use search::Search;
@@ -0,0 +1,270 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use crate::events::InputHandler;
+use crate::simulation::conflict::{claims_conflict, vehicle_claim};
+use crate::simulation::physics::CONFLICT_ZONE_HALF_SIZE;
+use crate::simulation::{Direction, Intersection, Route, Simulation, Vehicle, VehicleType};
+
+/// First line of every scenario file, bumped when the format changes
+const SCENARIO_HEADER: &str = "smart_road_scenario 1";
+
+/// Distance from the centre at which scenario vehicles appear (in meters),
+/// the same as for vehicles spawned with the keyboard
+pub const SPAWN_DISTANCE: f32 = 100.0;
+
+/// How long a scenario keeps running after its last spawn, so every
+/// vehicle has crossed (in seconds)
+pub const CLEAR_TIME: f32 = 30.0;
+
+/// One vehicle spawned at a fixed moment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spawn {
+    pub time: f32,              // Simulation time of the spawn (in seconds)
+    pub direction: Direction,
+    pub route: Route,
+    pub speed: f32,             // Cruise velocity (in m/s), capped at the type's top speed
+    pub vehicle_type: VehicleType,
+}
+
+impl Spawn {
+    /// Creates the spawned vehicle with the given id
+    pub fn vehicle(&self, id: u32) -> Vehicle {
+        Vehicle::new(
+            id,
+            InputHandler::get_spawn_position(self.direction, SPAWN_DISTANCE),
+            self.speed,
+            self.route,
+            self.direction,
+            SPAWN_DISTANCE,
+        )
+        .with_type(self.vehicle_type)
+    }
+}
+
+/// Safety failures seen while running a scenario
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Outcome {
+    /// Pairs of vehicles that ran into each other
+    pub collisions: i32,
+    /// Near misses: pairs of vehicles with conflicting movements inside the
+    /// intersection at the same time
+    pub zone_conflicts: i32,
+    /// Times a vehicle had to brake harder than it comfortably can
+    pub emergency_brakings: i32,
+    /// Spawns refused because the vehicle could not safely enter
+    pub refused: usize,
+    /// Smallest clearance between two vehicles during the run (in meters):
+    /// the bumper gap in a lane, or how far the nearer of two vehicles on
+    /// conflicting movements was from being in the conflict zone together
+    pub closest_approach: f32,
+}
+
+impl Outcome {
+    /// Whether the scenario made the control produce a collision or a near miss
+    pub fn failed(&self) -> bool {
+        self.collisions > 0 || self.zone_conflicts > 0
+    }
+
+    /// How close the scenario came to failing, used to steer the search
+    pub fn score(&self) -> f32 {
+        100.0 * self.collisions as f32
+            + 10.0 * self.zone_conflicts as f32
+            + self.emergency_brakings as f32
+            + 10.0 / (1.0 + self.closest_approach.max(0.0))
+    }
+}
+
+/// Fixed sequence of vehicle spawns, replayed without any random traffic
+///
+/// The file format is plain text: a header line, then one
+/// `spawn <time> <direction> <lane index> <speed> <type>` record per line.
+/// How the intersection is controlled is not part of the scenario; it is
+/// set up from the command line like any other run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scenario {
+    pub spawns: Vec<Spawn>,
+}
+
+impl Scenario {
+    /// Simulation time at which the run ends
+    pub fn end_time(&self) -> f32 {
+        self.spawns.iter().map(|spawn| spawn.time).fold(0.0, f32::max) + CLEAR_TIME
+    }
+
+    /// Runs the scenario on `simulation` in steps of `tick` seconds until
+    /// every vehicle had time to cross, or the safety monitor halts it
+    ///
+    /// Vehicles are added through `Simulation::add_vehicle`, so spawns the
+    /// intersection considers unsafe are refused as usual.
+    pub fn run(&self, simulation: &mut Simulation, tick: f32) -> Outcome {
+        let mut spawns = self.spawns.clone();
+        spawns.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let mut spawns = spawns.into_iter().peekable();
+        let mut refused = 0;
+        let mut closest_approach = f32::MAX;
+        let mut next_id = 1;
+        let end_time = self.end_time();
+        while simulation.time < end_time && !simulation.halted() {
+            while let Some(spawn) = spawns.next_if(|spawn| spawn.time <= simulation.time) {
+                let total = simulation.total_vehicles;
+                simulation.add_vehicle(spawn.vehicle(next_id));
+                next_id += 1;
+                if simulation.total_vehicles == total {
+                    refused += 1;
+                }
+            }
+            simulation.update(tick);
+            closest_approach = closest_approach.min(Self::closest_approach(&simulation.intersection));
+        }
+
+        let stats = simulation.statistics();
+        Outcome {
+            collisions: stats.collisions,
+            zone_conflicts: stats.zone_conflicts,
+            emergency_brakings: stats.emergency_brakings,
+            refused,
+            closest_approach,
+        }
+    }
+
+    /// Smallest clearance between any two vehicles right now (see
+    /// `Outcome::closest_approach`)
+    fn closest_approach(intersection: &Intersection) -> f32 {
+        let vehicles: Vec<&Vehicle> = intersection.lanes.values().flatten().collect();
+        let outside_zone = |vehicle: &Vehicle| {
+            vehicle.distance_to_intersection.abs() - CONFLICT_ZONE_HALF_SIZE - vehicle.spec().length / 2.0
+        };
+        let mut closest = f32::MAX;
+        for vehicle in &vehicles {
+            if let Some(leader) = intersection.leader_of(vehicle) {
+                closest = closest.min(intersection.physics.bumper_gap(vehicle, leader));
+            }
+        }
+        for (index, a) in vehicles.iter().enumerate() {
+            for b in &vehicles[index + 1..] {
+                if a.direction != b.direction && claims_conflict(vehicle_claim(a), vehicle_claim(b)) {
+                    closest = closest.min(outside_zone(a).max(outside_zone(b)));
+                }
+            }
+        }
+        closest
+    }
+
+    /// Writes the scenario in the text format
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{}", SCENARIO_HEADER)?;
+        for spawn in &self.spawns {
+            writeln!(
+                writer,
+                "spawn {} {} {} {} {}",
+                spawn.time,
+                spawn.direction,
+                spawn.route.lane_index(),
+                spawn.speed,
+                spawn.vehicle_type,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Reads a scenario written by `write`
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, String> {
+        let mut lines = reader.lines();
+        match lines.next() {
+            Some(Ok(header)) if header.trim() == SCENARIO_HEADER => {}
+            _ => return Err("Not a smart_road scenario".to_string()),
+        }
+
+        let mut scenario = Scenario::default();
+        for (number, line) in lines.enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                continue;
+            }
+            let spawn = parse_spawn(&fields).map_err(|e| format!("line {}: {}", number + 2, e))?;
+            scenario.spawns.push(spawn);
+        }
+        Ok(scenario)
+    }
+
+    /// Writes the scenario to the file at `path`
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    /// Reads a scenario from the file at `path`
+    pub fn load(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::read(BufReader::new(file))
+    }
+}
+
+/// Parses a `spawn ...` record
+fn parse_spawn(fields: &[&str]) -> Result<Spawn, String> {
+    if fields[0] != "spawn" {
+        return Err(format!("unknown record: {}", fields[0]));
+    }
+    Ok(Spawn {
+        time: field(fields, 1)?,
+        direction: field(fields, 2)?,
+        route: Route::from_lane_index(field(fields, 3)?).ok_or("invalid lane index")?,
+        speed: field(fields, 4)?,
+        vehicle_type: field(fields, 5)?,
+    })
+}
+
+/// Parses field `index` of a record
+fn field<T: std::str::FromStr>(fields: &[&str], index: usize) -> Result<T, String> {
+    let value = fields.get(index).ok_or_else(|| format!("missing field {}", index))?;
+    value.parse().map_err(|_| format!("invalid value: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::stats::StatisticsManager;
+
+    fn spawn(time: f32, direction: Direction, route: Route, vehicle_type: VehicleType) -> Spawn {
+        Spawn { time, direction, route, speed: 10.0, vehicle_type }
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let scenario = Scenario {
+            spawns: vec![
+                spawn(0.0, Direction::North, Route::Left, VehicleType::Car),
+                spawn(2.75, Direction::West, Route::Right, VehicleType::Bicycle),
+            ],
+        };
+        let mut buffer = Vec::new();
+        scenario.write(&mut buffer).unwrap();
+        assert_eq!(Scenario::read(buffer.as_slice()).unwrap(), scenario);
+
+        assert!(Scenario::read("not a scenario\n".as_bytes()).is_err());
+        let bad = format!("{}\nspawn 1 Up 0 10 car\n", SCENARIO_HEADER);
+        assert!(Scenario::read(bad.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_run_spawns_at_scenario_times() {
+        let scenario = Scenario {
+            spawns: vec![
+                spawn(1.0, Direction::South, Route::Straight, VehicleType::Car),
+                spawn(0.0, Direction::East, Route::Straight, VehicleType::Car),
+                // Too close behind the first one, refused by `can_enter`
+                spawn(1.0, Direction::South, Route::Straight, VehicleType::Car),
+            ],
+        };
+        let mut simulation = Simulation::new(25.0, Rc::new(RefCell::new(StatisticsManager::new())));
+        let outcome = scenario.run(&mut simulation, 0.016);
+
+        assert!(simulation.time >= 1.0 + CLEAR_TIME);
+        assert_eq!(simulation.total_vehicles, 2);
+        assert_eq!(outcome.refused, 1);
+        assert!(!outcome.failed());
+        assert!(outcome.closest_approach > 0.0);
+    }
+}
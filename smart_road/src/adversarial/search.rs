@@ -0,0 +1,253 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use rand::Rng;
+use crate::events::InputHandler;
+use crate::simulation::rng::SimRng;
+use crate::simulation::{Direction, Intersection, Route, Simulation, VehicleType};
+use crate::stats::StatisticsManager;
+use super::scenario::{Outcome, Scenario, Spawn};
+
+/// Most vehicles a searched scenario spawns
+pub const MAX_SPAWNS: usize = 12;
+
+/// Spawn times are drawn from the first this many seconds of a run
+pub const SPAWN_WINDOW: f32 = 20.0;
+
+/// Largest shift of a spawn time in one mutation (in seconds)
+const TIME_STEP: f32 = 2.0;
+
+/// Slowest cruise velocity a spawn is given (in m/s)
+const MIN_SPEED: f32 = 2.0;
+
+/// A failing scenario found by the search, after minimisation
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub scenario: Scenario,
+    pub outcome: Outcome,
+    /// Scenario runs the search had done when it found the failure
+    pub found_after: usize,
+}
+
+/// Search over spawn sequences for ones that make the intersection control
+/// produce collisions or near misses
+///
+/// Starting from a few random spawns, each step mutates the current
+/// scenario (adding or removing a spawn, or changing the time, approach,
+/// route or speed of one) and keeps the mutation when it comes at least as
+/// close to failing. A failing scenario is minimised, recorded, and the
+/// search starts over from fresh random spawns.
+pub struct Search {
+    /// Intersection every scenario starts from, with the control under test
+    template: Intersection,
+    tick: f32,
+    rng: SimRng,
+    /// Scenario runs done so far
+    pub runs: usize,
+}
+
+impl Search {
+    pub fn new(template: Intersection, tick: f32, seed: u64) -> Self {
+        Search { template, tick, rng: SimRng::new(seed), runs: 0 }
+    }
+
+    /// Runs `iterations` search steps and returns the distinct failures found
+    pub fn run(&mut self, iterations: usize) -> Vec<Failure> {
+        let mut failures: Vec<Failure> = Vec::new();
+        let mut current = self.random_scenario();
+        let mut current_outcome = self.evaluate(&current);
+        for _ in 0..iterations {
+            if current_outcome.failed() {
+                let (scenario, outcome) = self.minimise(current, current_outcome);
+                if !failures.iter().any(|failure| failure.scenario == scenario) {
+                    failures.push(Failure { scenario, outcome, found_after: self.runs });
+                }
+                current = self.random_scenario();
+                current_outcome = self.evaluate(&current);
+                continue;
+            }
+            let candidate = self.mutate(&current);
+            let outcome = self.evaluate(&candidate);
+            if outcome.score() >= current_outcome.score() {
+                current = candidate;
+                current_outcome = outcome;
+            }
+        }
+        failures
+    }
+
+    /// Runs a scenario on a fresh copy of the intersection
+    pub fn evaluate(&mut self, scenario: &Scenario) -> Outcome {
+        self.runs += 1;
+        let stats = Rc::new(RefCell::new(StatisticsManager::new()));
+        let mut simulation = Simulation::new(self.template.safe_distance, stats);
+        simulation.intersection = self.template.clone();
+        scenario.run(&mut simulation, self.tick)
+    }
+
+    /// Shrinks a failing scenario to one where removing any spawn makes it
+    /// pass, with the first spawn moved to the start of the run
+    pub fn minimise(&mut self, scenario: Scenario, outcome: Outcome) -> (Scenario, Outcome) {
+        minimise_by(scenario, outcome, |candidate| self.evaluate(candidate))
+    }
+
+    /// A scenario of two to four random spawns
+    fn random_scenario(&mut self) -> Scenario {
+        let count = self.rng.random_range(2..=4);
+        Scenario { spawns: (0..count).map(|_| self.random_spawn()).collect() }
+    }
+
+    fn random_spawn(&mut self) -> Spawn {
+        let vehicle_type = VehicleType::ALL[self.rng.random_range(0..VehicleType::ALL.len())];
+        let route = InputHandler::route_for(vehicle_type, self.random_route());
+        Spawn {
+            time: self.rng.random_range(0.0..SPAWN_WINDOW),
+            direction: self.random_direction(),
+            route,
+            speed: self.random_speed(vehicle_type),
+            vehicle_type,
+        }
+    }
+
+    /// Applies one random change to a copy of `scenario`
+    fn mutate(&mut self, scenario: &Scenario) -> Scenario {
+        let mut mutated = scenario.clone();
+        let count = mutated.spawns.len();
+        let operation = self.rng.random_range(0..6);
+        if count == 0 || (operation == 0 && count < MAX_SPAWNS) {
+            let spawn = self.random_spawn();
+            mutated.spawns.push(spawn);
+            return mutated;
+        }
+        let index = self.rng.random_range(0..count);
+        match operation {
+            1 if count > 1 => {
+                mutated.spawns.remove(index);
+            }
+            2 => {
+                let shift = self.rng.random_range(-TIME_STEP..TIME_STEP);
+                let spawn = &mut mutated.spawns[index];
+                spawn.time = (spawn.time + shift).clamp(0.0, SPAWN_WINDOW);
+            }
+            3 => mutated.spawns[index].direction = self.random_direction(),
+            4 => {
+                let route = self.random_route();
+                let spawn = &mut mutated.spawns[index];
+                spawn.route = InputHandler::route_for(spawn.vehicle_type, route);
+            }
+            _ => {
+                let vehicle_type = mutated.spawns[index].vehicle_type;
+                mutated.spawns[index].speed = self.random_speed(vehicle_type);
+            }
+        }
+        mutated
+    }
+
+    fn random_speed(&mut self, vehicle_type: VehicleType) -> f32 {
+        self.rng.random_range(MIN_SPEED..=vehicle_type.spec().max_speed)
+    }
+
+    fn random_route(&mut self) -> Route {
+        match self.rng.random_range(0..3) {
+            0 => Route::Straight,
+            1 => Route::Left,
+            _ => Route::Right,
+        }
+    }
+
+    fn random_direction(&mut self) -> Direction {
+        match self.rng.random_range(0..4) {
+            0 => Direction::North,
+            1 => Direction::South,
+            2 => Direction::East,
+            _ => Direction::West,
+        }
+    }
+}
+
+/// Shrinks a failing scenario as `Search::minimise` does, judging every
+/// candidate with `evaluate`
+fn minimise_by(
+    mut scenario: Scenario,
+    mut outcome: Outcome,
+    mut evaluate: impl FnMut(&Scenario) -> Outcome,
+) -> (Scenario, Outcome) {
+    loop {
+        let mut shrunk = false;
+        for index in (0..scenario.spawns.len()).rev() {
+            let mut candidate = scenario.clone();
+            candidate.spawns.remove(index);
+            let candidate_outcome = evaluate(&candidate);
+            if candidate_outcome.failed() {
+                scenario = candidate;
+                outcome = candidate_outcome;
+                shrunk = true;
+            }
+        }
+        if !shrunk {
+            break;
+        }
+    }
+
+    let start = scenario.spawns.iter().map(|spawn| spawn.time).fold(f32::MAX, f32::min);
+    if start > 0.0 && start < f32::MAX {
+        let mut candidate = scenario.clone();
+        for spawn in &mut candidate.spawns {
+            spawn.time -= start;
+        }
+        let candidate_outcome = evaluate(&candidate);
+        if candidate_outcome.failed() {
+            return (candidate, candidate_outcome);
+        }
+    }
+    (scenario, outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn(time: f32, direction: Direction, speed: f32, vehicle_type: VehicleType) -> Spawn {
+        Spawn { time, direction, route: Route::Right, speed, vehicle_type }
+    }
+
+    #[test]
+    fn test_minimise_keeps_only_failing_spawns() {
+        // Stand-in for a control defect: any two vehicles on the West approach collide
+        let evaluate = |scenario: &Scenario| Outcome {
+            collisions: (scenario.spawns.iter().filter(|spawn| spawn.direction == Direction::West).count() >= 2) as i32,
+            ..Outcome::default()
+        };
+        let scenario = Scenario {
+            spawns: vec![
+                spawn(2.0, Direction::West, 8.0, VehicleType::Bus),
+                spawn(4.0, Direction::North, 10.0, VehicleType::Car),
+                spawn(7.5, Direction::West, 14.0, VehicleType::Ambulance),
+                spawn(9.0, Direction::East, 10.0, VehicleType::Car),
+            ],
+        };
+        let outcome = evaluate(&scenario);
+        assert!(outcome.failed());
+
+        let (minimal, minimal_outcome) = minimise_by(scenario, outcome, evaluate);
+        assert!(minimal_outcome.failed());
+        assert_eq!(minimal.spawns.len(), 2);
+        assert!(minimal.spawns.iter().all(|spawn| spawn.direction == Direction::West));
+        assert_eq!(minimal.spawns[0].time, 0.0);
+        assert_eq!(minimal.spawns[1].time, 5.5);
+    }
+
+    #[test]
+    fn test_mutation_keeps_spawns_valid() {
+        let mut search = Search::new(Intersection::new(25.0), 0.016, 7);
+        let mut scenario = search.random_scenario();
+        for _ in 0..500 {
+            scenario = search.mutate(&scenario);
+            assert!((1..=MAX_SPAWNS).contains(&scenario.spawns.len()));
+            for spawn in &scenario.spawns {
+                assert!((0.0..=SPAWN_WINDOW).contains(&spawn.time));
+                assert!(spawn.speed >= MIN_SPEED && spawn.speed <= spawn.vehicle_type.spec().max_speed);
+                assert!(!(spawn.vehicle_type.is_bicycle() && spawn.route == Route::Left));
+            }
+        }
+    }
+}
@@ -2,7 +2,25 @@ use sdl2::keyboard::Keycode;
 use rand::Rng;
 use std::time::{Duration, Instant};
 use crate::simulation::vehicle::{Direction, Route, Vehicle};
-use crate::simulation::physics::velocities;
+use crate::simulation::rng::SimRng;
+use crate::simulation::pedestrian::WALKING_SPEED;
+use crate::simulation::behaviour::BehaviourProfile;
+use crate::simulation::driver::HumanDriver;
+use crate::simulation::rogue::Misbehaviour;
+use crate::simulation::{Pedestrian, VehicleMix, VehicleType};
+
+/// Vehicle generator state needed to resume a run with the same spawns
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorState {
+    pub next_vehicle_id: u32,
+    pub random_generation_enabled: bool,
+    /// Simulation time of the last random spawn (in seconds)
+    pub last_random_spawn: Option<f32>,
+    pub rng_state: u64,
+    pub next_pedestrian_id: u32,
+    /// Simulation time at which the next random pedestrian arrives (in seconds)
+    pub next_pedestrian_spawn: Option<f32>,
+}
 
 /// Handles keyboard input for vehicle generation
 pub struct InputHandler {
@@ -15,8 +33,8 @@ pub struct InputHandler {
     last_spawn_east: Option<Instant>,
     last_spawn_west: Option<Instant>,
 
-    /// Last time a random vehicle was spawned (for R key)
-    last_random_spawn: Option<Instant>,
+    /// Simulation time of the last random spawn (for R key), in seconds
+    last_random_spawn: Option<f32>,
 
     /// Whether continuous random generation is enabled (R key)
     pub random_generation_enabled: bool,
@@ -26,6 +44,39 @@ pub struct InputHandler {
 
     /// Initial distance from intersection for spawned vehicles
     spawn_distance: f32,
+
+    /// Source of all random choices (route, velocity, direction, type)
+    rng: SimRng,
+
+    /// Share of each vehicle type among spawned vehicles
+    pub vehicle_mix: VehicleMix,
+
+    /// Vehicle type to spawn with the next arrow key (E / F / B keys)
+    pub armed_vehicle: Option<VehicleType>,
+
+    /// Pedestrian demand over all crosswalks (in pedestrians per minute, 0 = none)
+    pub pedestrian_rate: f32,
+
+    /// Share of spawned vehicles driven by humans who ignore the manager (0–1)
+    pub human_share: f32,
+
+    /// Share of spawned automated vehicles that misbehave (0–1)
+    pub rogue_share: f32,
+
+    /// Kinds of misbehaviour rogue vehicles are drawn from
+    pub rogue_kinds: Vec<Misbehaviour>,
+
+    /// Whether the next arrow key spawns a rogue vehicle (X key)
+    pub armed_rogue: bool,
+
+    /// Distributions desired speeds and driver behaviour are drawn from
+    pub behaviour: BehaviourProfile,
+
+    /// Counter for pedestrian IDs
+    next_pedestrian_id: u32,
+
+    /// Simulation time at which the next random pedestrian arrives (in seconds)
+    next_pedestrian_spawn: Option<f32>,
 }
 
 impl InputHandler {
@@ -40,9 +91,52 @@ impl InputHandler {
             random_generation_enabled: false,
             next_vehicle_id: 1,
             spawn_distance,
+            rng: SimRng::from_entropy(),
+            vehicle_mix: VehicleMix::default(),
+            armed_vehicle: None,
+            pedestrian_rate: 0.0,
+            human_share: 0.0,
+            rogue_share: 0.0,
+            rogue_kinds: Misbehaviour::ALL.to_vec(),
+            armed_rogue: false,
+            behaviour: BehaviourProfile::default(),
+            next_pedestrian_id: 1,
+            next_pedestrian_spawn: None,
         }
     }
 
+    /// Distance from the centre at which vehicles are spawned (in meters)
+    pub fn spawn_distance(&self) -> f32 {
+        self.spawn_distance
+    }
+
+    /// Reseeds the random generator so runs can be reproduced
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = SimRng::new(seed);
+    }
+
+    /// Captures the generator state for a snapshot
+    pub fn generator_state(&self) -> GeneratorState {
+        GeneratorState {
+            next_vehicle_id: self.next_vehicle_id,
+            random_generation_enabled: self.random_generation_enabled,
+            last_random_spawn: self.last_random_spawn,
+            rng_state: self.rng.state(),
+            next_pedestrian_id: self.next_pedestrian_id,
+            next_pedestrian_spawn: self.next_pedestrian_spawn,
+        }
+    }
+
+    /// Restores a generator state captured by `generator_state`
+    pub fn restore_generator_state(&mut self, state: &GeneratorState) {
+        self.next_vehicle_id = state.next_vehicle_id;
+        self.random_generation_enabled = state.random_generation_enabled;
+        self.last_random_spawn = state.last_random_spawn;
+        self.rng = SimRng::new(state.rng_state);
+        self.next_pedestrian_id = state.next_pedestrian_id;
+        self.next_pedestrian_spawn = state.next_pedestrian_spawn;
+    }
+
     /// Handles a keypress event and returns vehicles to spawn (if any)
     pub fn handle_keypress(&mut self, keycode: Keycode) -> Vec<Vehicle> {
         match keycode {
@@ -54,6 +148,22 @@ impl InputHandler {
                 self.random_generation_enabled = !self.random_generation_enabled;
                 Vec::new()
             }
+            Keycode::E => {
+                self.armed_vehicle = Some(VehicleType::Ambulance);
+                Vec::new()
+            }
+            Keycode::F => {
+                self.armed_vehicle = Some(VehicleType::FireTruck);
+                Vec::new()
+            }
+            Keycode::B => {
+                self.armed_vehicle = Some(VehicleType::Bicycle);
+                Vec::new()
+            }
+            Keycode::X => {
+                self.armed_rogue = true;
+                Vec::new()
+            }
             _ => Vec::new(),
         }
     }
@@ -80,43 +190,98 @@ impl InputHandler {
         // Update last spawn time
         *last_spawn = Some(now);
 
-        // Create and return the vehicle
-        vec![self.create_vehicle(direction)]
+        // Create and return the vehicle, using up an armed vehicle type
+        let vehicle = match self.armed_vehicle.take() {
+            Some(vehicle_type) => self.create_armed_vehicle(direction, vehicle_type),
+            None => self.create_vehicle(direction),
+        };
+        if std::mem::take(&mut self.armed_rogue) {
+            let kind = self.random_misbehaviour();
+            vec![vehicle.with_rogue(kind)]
+        } else {
+            vec![vehicle]
+        }
     }
 
     /// Updates random vehicle generation (called each frame)
-    pub fn update_random_generation(&mut self, random_spawn_rate_ms: u64) -> Option<Vehicle> {
+    ///
+    /// Spawning is paced by simulation time (in seconds) rather than wall-clock
+    /// time, so the same seed always produces the same traffic.
+    pub fn update_random_generation(&mut self, random_spawn_rate_ms: u64, time: f32) -> Option<Vehicle> {
         if !self.random_generation_enabled {
             return None;
         }
 
-        let now = Instant::now();
-        let spawn_interval = Duration::from_millis(random_spawn_rate_ms);
+        let spawn_interval = random_spawn_rate_ms as f32 / 1000.0;
 
         // Check if it's time to spawn a random vehicle
         if let Some(last) = self.last_random_spawn {
-            if now.duration_since(last) < spawn_interval {
+            if time - last < spawn_interval {
                 return None;
             }
         }
 
-        self.last_random_spawn = Some(now);
+        self.last_random_spawn = Some(time);
 
         // Generate a random direction
-        let direction = Self::random_direction();
+        let direction = self.random_direction();
         Some(self.create_vehicle(direction))
     }
 
-    /// Creates a new vehicle with random route and velocity
+    /// Updates random pedestrian generation (called each frame)
+    ///
+    /// Pedestrians arrive at random (Poisson) times at `pedestrian_rate` per
+    /// minute, paced by simulation time like vehicle generation.
+    pub fn update_pedestrian_generation(&mut self, time: f32) -> Option<Pedestrian> {
+        if self.pedestrian_rate <= 0.0 {
+            return None;
+        }
+
+        let next = match self.next_pedestrian_spawn {
+            Some(next) => next,
+            None => {
+                let next = time + self.random_pedestrian_interval();
+                self.next_pedestrian_spawn = Some(next);
+                next
+            }
+        };
+        if time < next {
+            return None;
+        }
+
+        self.next_pedestrian_spawn = Some(time + self.random_pedestrian_interval());
+        Some(self.create_pedestrian())
+    }
+
+    /// Creates a pedestrian at the curb of a random crosswalk
+    pub fn create_pedestrian(&mut self) -> Pedestrian {
+        let id = self.next_pedestrian_id;
+        self.next_pedestrian_id += 1;
+
+        let arm = self.random_direction();
+        let forward = self.rng.random_bool(0.5);
+        let speed = WALKING_SPEED * self.rng.random_range(0.8..1.2);
+        Pedestrian::new(id, arm, forward, speed)
+    }
+
+    /// Exponentially distributed time until the next pedestrian arrives (in seconds)
+    fn random_pedestrian_interval(&mut self) -> f32 {
+        let uniform: f32 = self.rng.random_range(0.0..1.0);
+        -(1.0 - uniform).ln() * 60.0 / self.pedestrian_rate
+    }
+
+    /// Creates a new vehicle with random route, velocity and type
     fn create_vehicle(&mut self, direction: Direction) -> Vehicle {
         let id = self.next_vehicle_id;
         self.next_vehicle_id += 1;
 
-        let route = Self::random_route();
-        let velocity = Self::random_velocity();
+        let route = self.random_route();
+        let velocity = self.behaviour.sample_desired_speed(&mut self.rng);
+        let vehicle_type = self.vehicle_mix.sample(&mut self.rng);
+        let route = Self::route_for(vehicle_type, route);
         let position = Self::get_spawn_position(direction, self.spawn_distance);
 
-        Vehicle::new(
+        let vehicle = Vehicle::new(
             id,
             position,
             velocity,
@@ -124,29 +289,70 @@ impl InputHandler {
             direction,
             self.spawn_distance,
         )
+        .with_type(vehicle_type);
+
+        // Only draw when humans are enabled, so all-automated runs keep
+        // their random sequence
+        let human = self.human_share > 0.0 && self.rng.random_bool(self.human_share.min(1.0) as f64);
+        let vehicle = vehicle.with_behaviour(self.behaviour.sample(&mut self.rng, human));
+        if human {
+            return vehicle.with_driver(HumanDriver::new());
+        }
+        // Likewise only draw when rogue vehicles are enabled
+        if self.rogue_share > 0.0 && self.rng.random_bool(self.rogue_share.min(1.0) as f64) {
+            let kind = self.random_misbehaviour();
+            vehicle.with_rogue(kind)
+        } else {
+            vehicle
+        }
     }
 
-    fn random_route() -> Route {
-        let mut rng = rand::thread_rng();
-        match rng.gen_range(0..3) {
-            0 => Route::Straight,
-            1 => Route::Left,
-            _ => Route::Right,
+    /// Picks one of the enabled kinds of misbehaviour
+    fn random_misbehaviour(&mut self) -> Misbehaviour {
+        let index = self.rng.random_range(0..self.rogue_kinds.len());
+        self.rogue_kinds[index]
+    }
+
+    /// Creates a vehicle of the armed type driving at its top speed on a random route
+    fn create_armed_vehicle(&mut self, direction: Direction, vehicle_type: VehicleType) -> Vehicle {
+        let id = self.next_vehicle_id;
+        self.next_vehicle_id += 1;
+
+        let route = Self::route_for(vehicle_type, self.random_route());
+        let position = Self::get_spawn_position(direction, self.spawn_distance);
+
+        Vehicle::new(
+            id,
+            position,
+            vehicle_type.spec().max_speed,
+            route,
+            direction,
+            self.spawn_distance,
+        )
+        .with_type(vehicle_type)
+        .with_behaviour(self.behaviour.sample(&mut self.rng, false))
+    }
+
+    /// Bicycles do not turn left across the traffic from the bike lane, they
+    /// ride on straight instead
+    pub fn route_for(vehicle_type: VehicleType, route: Route) -> Route {
+        if vehicle_type.is_bicycle() && route == Route::Left {
+            Route::Straight
+        } else {
+            route
         }
     }
 
-    fn random_velocity() -> f32 {
-        let mut rng = rand::thread_rng();
-        match rng.gen_range(0..3) {
-            0 => velocities::SLOW,
-            1 => velocities::MEDIUM,
-            _ => velocities::FAST,
+    fn random_route(&mut self) -> Route {
+        match self.rng.random_range(0..3) {
+            0 => Route::Straight,
+            1 => Route::Left,
+            _ => Route::Right,
         }
     }
 
-    fn random_direction() -> Direction {
-        let mut rng = rand::thread_rng();
-        match rng.gen_range(0..4) {
+    fn random_direction(&mut self) -> Direction {
+        match self.rng.random_range(0..4) {
             0 => Direction::North,
             1 => Direction::South,
             2 => Direction::East,
@@ -155,7 +361,7 @@ impl InputHandler {
     }
 
     /// Calculates spawn position based on direction
-    fn get_spawn_position(direction: Direction, distance: f32) -> (f32, f32) {
+    pub fn get_spawn_position(direction: Direction, distance: f32) -> (f32, f32) {
         match direction {
             Direction::North => (0.0, -distance),  // Spawns south of center, moving north
             Direction::South => (0.0, distance),   // Spawns north of center, moving south
@@ -172,6 +378,9 @@ impl InputHandler {
         self.last_spawn_west = None;
         self.last_random_spawn = None;
         self.random_generation_enabled = false;
+        self.armed_vehicle = None;
+        self.armed_rogue = false;
+        self.next_pedestrian_spawn = None;
     }
 }
 
@@ -244,4 +453,138 @@ mod tests {
         assert_eq!(vehicles1[0].id, 1);
         assert_eq!(vehicles2[0].id, 2);
     }
+
+    #[test]
+    fn test_random_generation_uses_simulation_time() {
+        let mut handler = InputHandler::new(500, 100.0);
+        handler.handle_keypress(Keycode::R);
+
+        assert!(handler.update_random_generation(800, 0.0).is_some());
+        assert!(handler.update_random_generation(800, 0.5).is_none());
+        assert!(handler.update_random_generation(800, 0.8).is_some());
+    }
+
+    #[test]
+    fn test_restore_generator_state_repeats_spawns() {
+        let mut handler = InputHandler::new(500, 100.0);
+        handler.set_seed(3);
+        handler.handle_keypress(Keycode::R);
+        let state = handler.generator_state();
+
+        let first: Vec<Vehicle> = (0..5)
+            .filter_map(|i| handler.update_random_generation(800, i as f32))
+            .collect();
+
+        let mut resumed = InputHandler::new(500, 100.0);
+        resumed.restore_generator_state(&state);
+        let second: Vec<Vehicle> = (0..5)
+            .filter_map(|i| resumed.update_random_generation(800, i as f32))
+            .collect();
+
+        assert_eq!(first.len(), 5);
+        for (a, b) in first.iter().zip(&second) {
+            assert_eq!(a.id, b.id);
+            assert_eq!(a.direction, b.direction);
+            assert_eq!(a.route, b.route);
+            assert_eq!(a.velocity, b.velocity);
+            assert_eq!(a.vehicle_type, b.vehicle_type);
+        }
+    }
+
+    #[test]
+    fn test_spawn_emergency_vehicle() {
+        let mut handler = InputHandler::new(0, 100.0);
+
+        assert!(handler.handle_keypress(Keycode::F).is_empty());
+        let vehicle = &handler.handle_keypress(Keycode::Left)[0];
+        assert_eq!(vehicle.vehicle_type, VehicleType::FireTruck);
+        assert_eq!(vehicle.direction, Direction::East);
+        assert!(vehicle.priority);
+
+        // Only the next vehicle is an emergency vehicle
+        let vehicle = &handler.handle_keypress(Keycode::Left)[0];
+        assert!(!vehicle.priority);
+    }
+
+    #[test]
+    fn test_bicycles_never_turn_left() {
+        let mut handler = InputHandler::new(0, 100.0);
+        handler.vehicle_mix = "bicycle=1".parse().unwrap();
+        for _ in 0..50 {
+            let vehicle = &handler.handle_keypress(Keycode::Up)[0];
+            assert_eq!(vehicle.vehicle_type, VehicleType::Bicycle);
+            assert_ne!(vehicle.route, Route::Left);
+        }
+    }
+
+    #[test]
+    fn test_pedestrian_demand_rate() {
+        let mut handler = InputHandler::new(500, 100.0);
+        handler.set_seed(7);
+        assert!(handler.update_pedestrian_generation(0.0).is_none());
+
+        handler.pedestrian_rate = 6.0;
+        let spawned = (0..60_000)
+            .filter_map(|tick| handler.update_pedestrian_generation(tick as f32 * 0.01))
+            .count();
+        // 6 per minute over 10 minutes
+        assert!((40..80).contains(&spawned));
+    }
+
+    #[test]
+    fn test_human_share() {
+        let mut handler = InputHandler::new(0, 100.0);
+        handler.set_seed(5);
+        let humans = |handler: &mut InputHandler| {
+            (0..200)
+                .filter(|_| handler.handle_keypress(Keycode::Up)[0].is_human())
+                .count()
+        };
+        assert_eq!(humans(&mut handler), 0);
+
+        handler.human_share = 0.25;
+        assert!((30..70).contains(&humans(&mut handler)));
+
+        // Emergency vehicles are always automated
+        handler.human_share = 1.0;
+        handler.handle_keypress(Keycode::E);
+        assert!(!handler.handle_keypress(Keycode::Up)[0].is_human());
+    }
+
+    #[test]
+    fn test_behaviour_profile() {
+        let mut handler = InputHandler::new(0, 100.0);
+        handler.vehicle_mix = "car=1".parse().unwrap();
+        handler.behaviour = "speed=uniform:7:8,headway=1.5".parse().unwrap();
+        for _ in 0..20 {
+            let vehicle = &handler.handle_keypress(Keycode::Up)[0];
+            assert!((7.0..8.0).contains(&vehicle.cruise_velocity));
+            assert_eq!(vehicle.behaviour.headway, 1.5);
+        }
+    }
+
+    #[test]
+    fn test_vehicle_mix() {
+        let mut handler = InputHandler::new(0, 100.0);
+        handler.vehicle_mix = "truck=1".parse().unwrap();
+
+        for keycode in [Keycode::Up, Keycode::Down, Keycode::Left, Keycode::Right] {
+            let vehicle = &handler.handle_keypress(keycode)[0];
+            assert_eq!(vehicle.vehicle_type, VehicleType::Truck);
+            assert!(vehicle.velocity <= VehicleType::Truck.spec().max_speed);
+        }
+    }
+
+    #[test]
+    fn test_spawn_rogue_vehicle() {
+        let mut handler = InputHandler::new(0, 100.0);
+        handler.rogue_kinds = vec![Misbehaviour::SuddenStop];
+
+        assert!(handler.handle_keypress(Keycode::X).is_empty());
+        let vehicle = &handler.handle_keypress(Keycode::Down)[0];
+        assert_eq!(vehicle.rogue.map(|rogue| rogue.kind), Some(Misbehaviour::SuddenStop));
+
+        // Only the next vehicle is a rogue one
+        assert!(handler.handle_keypress(Keycode::Down)[0].rogue.is_none());
+    }
 }
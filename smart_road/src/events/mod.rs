@@ -2,7 +2,7 @@
 
 pub mod input;
 
-pub use input::InputHandler;
+pub use input::{GeneratorState, InputHandler};
 use crate::simulation::Direction;
 
 // Events that can occur in the simulation
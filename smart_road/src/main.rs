@@ -1,22 +1,64 @@
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
 use sdl2::pixels::Color;
 
 mod simulation;
 mod events;
 mod render;
 mod stats;
+mod recording;
+mod adversarial;
 
+use adversarial::{Scenario, Search};
 use events::InputHandler;
 use simulation::Simulation;
+use simulation::auction;
+use simulation::detector::{self, Detectors};
+use simulation::fallback::AllWayStop;
+use simulation::monitor::{MonitorMode, SafetyMonitor};
+use simulation::policy::Policy;
+use simulation::rng::SimRng;
+use simulation::signal::{self, Phase, Signal};
+use simulation::v2i::V2iLink;
+use simulation::v2v::{Negotiation, DEFAULT_COMMUNICATION_RANGE};
 use render::{AssetManager, Renderer};
 use stats::StatisticsManager;
+use recording::{History, Replay, Snapshot, TrajectoryRecorder};
 use std::time::Duration;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::EventPump;
 
 use std::rc::Rc;
 use std::cell::RefCell;
 
+/// Snapshot file written with F5 and read with F9 in the GUI
+const SNAPSHOT_FILE: &str = "snapshot.txt";
+
+/// Time step of one simulation tick (in seconds)
+const TICK: f32 = 0.016;
+
 fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().collect();
+
+    // `--headless <seconds>` runs the simulation without opening a window
+    if let Some(duration) = arg_value(&args, "--headless") {
+        let duration: f32 = duration.parse().map_err(|_| "--headless requires a duration in seconds")?;
+        return run_headless(&args, duration);
+    }
+
+    // `--search <iterations>` looks for spawn sequences that lead to collisions
+    if let Some(iterations) = arg_value(&args, "--search") {
+        let iterations: usize = iterations.parse().map_err(|_| "--search requires a number of iterations")?;
+        return run_search(&args, iterations);
+    }
+
+    // `--scenario <file>` runs a saved scenario without a window
+    if let Some(path) = arg_value(&args, "--scenario") {
+        return run_scenario(&args, path);
+    }
+
     let sdl_context = sdl2::init()?;
     let video_subsystem = sdl_context.video()?;
     let _image_context = sdl2::image::init(sdl2::image::InitFlag::PNG)?;
@@ -39,11 +81,24 @@ fn main() -> Result<(), String> {
 
     let mut event_pump = sdl_context.event_pump()?;
 
+    // `--replay <file>` plays back a recorded trajectory instead of simulating
+    if args.iter().any(|arg| arg == "--replay") {
+        let path = arg_value(&args, "--replay").ok_or("--replay requires a trajectory file")?;
+        let samples = recording::trajectory::load_csv(path)?;
+        return run_replay(&mut canvas, &mut event_pump, &renderer, Replay::new(&samples));
+    }
+
         let stats_manager = Rc::new(RefCell::new(StatisticsManager::new()));
 
 
     let mut simulation = Simulation::new(25.0, Rc::clone(&stats_manager));
     let mut input_handler = InputHandler::new(500, 100.0);
+    setup_run(&args, &mut simulation, &mut input_handler)?;
+    let mut recorder = trajectory_recorder(&args)?;
+
+    // Two minutes of rewind history at 10 snapshots per second
+    let mut history = History::new(1200, 0.1);
+    let mut paused = false;
     
     let mut show_stats = false;
 
@@ -58,7 +113,70 @@ fn main() -> Result<(), String> {
                 Event::KeyDown { keycode: Some(Keycode::ESCAPE), .. } => {
                     show_stats = !show_stats;
                 }
-                Event::KeyDown { keycode: Some(keycode), .. } => {
+                Event::KeyDown { keycode: Some(Keycode::F5), .. } => {
+                    Snapshot::capture(&simulation, &input_handler)
+                        .save(SNAPSHOT_FILE)
+                        .map_err(|e| e.to_string())?;
+                    println!("Snapshot saved to {}", SNAPSHOT_FILE);
+                }
+                Event::KeyDown { keycode: Some(Keycode::F9), .. } => {
+                    match Snapshot::load(SNAPSHOT_FILE) {
+                        Ok(snapshot) => {
+                            snapshot.restore(&mut simulation, &mut input_handler);
+                            history.clear();
+                            if let Some(recorder) = recorder.as_mut() {
+                                recorder.clear();
+                            }
+                        }
+                        Err(e) => println!("Could not load snapshot: {}", e),
+                    }
+                }
+                Event::KeyDown { keycode: Some(Keycode::P), .. } => {
+                    // Resuming after scrubbing continues from the shown state
+                    if paused {
+                        if let (true, Some(recorder)) = (history.is_scrubbing(), recorder.as_mut()) {
+                            recorder.truncate(simulation.time);
+                        }
+                        history.resume();
+                    }
+                    paused = !paused;
+                }
+                Event::KeyDown { keycode: Some(Keycode::COMMA), .. } => {
+                    paused = true;
+                    if let Some(snapshot) = history.step(-history.steps_for(1.0)) {
+                        snapshot.restore(&mut simulation, &mut input_handler);
+                    }
+                }
+                Event::KeyDown { keycode: Some(Keycode::PERIOD), .. } => {
+                    paused = true;
+                    if let Some(snapshot) = history.step(history.steps_for(1.0)) {
+                        snapshot.restore(&mut simulation, &mut input_handler);
+                    }
+                }
+                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
+                    if let Some(fraction) = renderer.timeline.fraction_at(x, y) {
+                        paused = true;
+                        if let Some(snapshot) = history.seek(fraction) {
+                            snapshot.restore(&mut simulation, &mut input_handler);
+                        }
+                    }
+                }
+                Event::MouseMotion { mousestate, x, y, .. } if mousestate.left() && paused => {
+                    if let Some(fraction) = renderer.timeline.fraction_at(x, y) {
+                        if let Some(snapshot) = history.seek(fraction) {
+                            snapshot.restore(&mut simulation, &mut input_handler);
+                        }
+                    }
+                }
+                Event::KeyDown { keycode: Some(Keycode::M), .. } => {
+                    let fallback = &mut simulation.intersection.fallback;
+                    fallback.manual_outage = !fallback.manual_outage;
+                    println!("Manager {}", if fallback.manual_outage { "failed" } else { "restored" });
+                }
+                Event::KeyDown { keycode: Some(Keycode::W), .. } if !paused => {
+                    simulation.add_pedestrian(input_handler.create_pedestrian());
+                }
+                Event::KeyDown { keycode: Some(keycode), .. } if !paused => {
                     let vehicles = input_handler.handle_keypress(keycode);
                     for vehicle in vehicles {
                         simulation.add_vehicle(vehicle);
@@ -68,14 +186,46 @@ fn main() -> Result<(), String> {
             }
         }
 
-        if let Some(vehicle) = input_handler.update_random_generation(800) {
-            simulation.add_vehicle(vehicle);
-        }
+        if !paused {
+            history.record(&simulation, &input_handler);
+
+            if let Some(vehicle) = input_handler.update_random_generation(800, simulation.time) {
+                simulation.add_vehicle(vehicle);
+            }
+            if let Some(pedestrian) = input_handler.update_pedestrian_generation(simulation.time) {
+                simulation.add_pedestrian(pedestrian);
+            }
 
-        simulation.update(0.016);
+            simulation.update(TICK);
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.sample(simulation.time, &simulation.intersection);
+            }
+            if let Some(monitor) = simulation.monitor.as_ref().filter(|monitor| monitor.halted) {
+                paused = true;
+                println!("Safety monitor halted the run at {:.3} s:", simulation.time);
+                for violation in monitor.latest() {
+                    println!("{}", violation);
+                }
+            }
+        }
         //stats_manager.update_car_count(simulation.vehicles.len() as i32);
 
         renderer.render(&mut canvas, &simulation)?;
+        renderer.render_timeline(&mut canvas, &history)?;
+
+        let title = if paused {
+            format!(
+                "Traffic Intersection - Paused at {:.1} s (history {:.1} - {:.1} s)",
+                simulation.time,
+                history.start_time(),
+                history.end_time(),
+            )
+        } else {
+            "Traffic Intersection".to_string()
+        };
+        if canvas.window().title() != title {
+            canvas.window_mut().set_title(&title).map_err(|e| e.to_string())?;
+        }
         
         if show_stats {
             stats_manager.borrow().render_stats(&mut canvas, &font, &texture_creator)?;
@@ -86,5 +236,441 @@ fn main() -> Result<(), String> {
         std::thread::sleep(Duration::from_millis(16));
     }
 
+    if let Some(recorder) = recorder.as_mut() {
+        // The trajectory leads up to the state shown, even when rewound
+        if history.is_scrubbing() {
+            recorder.truncate(simulation.time);
+        }
+        if let Some(path) = arg_value(&args, "--record") {
+            recorder.save_csv(path).map_err(|e| e.to_string())?;
+            println!("Trajectory of {} samples saved to {}", recorder.samples().len(), path);
+        }
+        if let Some(path) = arg_value(&args, "--record-fcd") {
+            recorder.save_fcd(path).map_err(|e| e.to_string())?;
+            println!("Floating car data saved to {}", path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Gets the value following `flag` on the command line, if any
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .map(|value| value.as_str())
+}
+
+/// Applies the `--seed <n>`, `--vehicle-mix <mix>`, `--pedestrian-rate <per minute>`,
+/// `--human-share <0-1>`, `--rogue-share <0-1>`, `--rogue <kind,...>`,
+/// `--behaviour <profile>`, `--no-platoons`,
+/// `--policy <policy>`, `--v2v`, `--v2v-range <meters>`,
+/// `--v2i <latency:jitter:drop rate>`, `--manager-outage <start:duration,...>`,
+/// `--signal <controller>`, `--signal-phases <phase,...>`, `--all-way-stop`,
+/// `--detectors <lane@distance[:length],...>`, `--detector-interval <seconds>`,
+/// `--monitor <record|halt>` and `--load-snapshot <file>` options
+///
+/// Debug builds always run the safety monitor, recording violations unless
+/// told to halt.
+fn setup_run(
+    args: &[String],
+    simulation: &mut Simulation,
+    input_handler: &mut InputHandler,
+) -> Result<(), String> {
+    simulation.intersection.queues.spawn_distance = input_handler.spawn_distance();
+    if let Some(seed) = arg_value(args, "--seed") {
+        input_handler.set_seed(seed.parse().map_err(|_| "--seed requires an integer")?);
+    }
+    if let Some(mix) = arg_value(args, "--vehicle-mix") {
+        input_handler.vehicle_mix = mix.parse()?;
+    }
+    if let Some(rate) = arg_value(args, "--pedestrian-rate") {
+        input_handler.pedestrian_rate = rate.parse().map_err(|_| "--pedestrian-rate requires a number")?;
+    }
+    if let Some(share) = arg_value(args, "--human-share") {
+        let share: f32 = share.parse().map_err(|_| "--human-share requires a number")?;
+        if !(0.0..=1.0).contains(&share) {
+            return Err("--human-share must be between 0 and 1".to_string());
+        }
+        input_handler.human_share = share;
+    }
+    if let Some(share) = arg_value(args, "--rogue-share") {
+        let share: f32 = share.parse().map_err(|_| "--rogue-share requires a number")?;
+        if !(0.0..=1.0).contains(&share) {
+            return Err("--rogue-share must be between 0 and 1".to_string());
+        }
+        input_handler.rogue_share = share;
+    }
+    if let Some(kinds) = arg_value(args, "--rogue") {
+        input_handler.rogue_kinds = kinds
+            .split(',')
+            .map(|kind| kind.parse())
+            .collect::<Result<_, _>>()?;
+    }
+    if let Some(profile) = arg_value(args, "--behaviour") {
+        input_handler.behaviour = profile.parse()?;
+    }
+    if args.iter().any(|arg| arg == "--no-platoons") {
+        simulation.intersection.platooning = false;
+    }
+    if let Some(policy) = arg_value(args, "--policy") {
+        simulation.intersection.manager.policy = policy.parse()?;
+    }
+    if args.iter().any(|arg| arg == "--v2v") {
+        simulation.intersection.negotiation = Some(Negotiation::new(v2v_range(args)?));
+    }
+    if let Some(config) = arg_value(args, "--v2i") {
+        // The channel draws from its own generator so losses do not shift the traffic
+        let seed = match arg_value(args, "--seed") {
+            Some(seed) => seed.parse().map_err(|_| "--seed requires an integer")?,
+            None => SimRng::from_entropy().state(),
+        };
+        simulation.intersection.v2i = Some(V2iLink::new(config.parse()?, seed));
+    }
+    if let Some(outages) = arg_value(args, "--manager-outage") {
+        simulation.intersection.fallback.outages = outages
+            .split(',')
+            .map(|outage| outage.parse())
+            .collect::<Result<_, _>>()?;
+    }
+    if let Some(controller) = arg_value(args, "--signal") {
+        simulation.intersection.signal = Some(Signal::new(controller.parse()?, signal_phases(args)?));
+    }
+    if args.iter().any(|arg| arg == "--all-way-stop") {
+        simulation.intersection.all_way_stop = Some(AllWayStop::with_stop_signs());
+    }
+    if let Some(loops) = arg_value(args, "--detectors") {
+        let interval = match arg_value(args, "--detector-interval") {
+            Some(interval) => interval.parse().map_err(|_| "--detector-interval requires a number of seconds")?,
+            None => detector::DEFAULT_AGGREGATION_INTERVAL,
+        };
+        if interval <= 0.0 {
+            return Err("--detector-interval must be positive".to_string());
+        }
+        let loops = loops.split(',').map(|detector| detector.parse()).collect::<Result<_, _>>()?;
+        simulation.intersection.detectors = Some(Detectors::new(loops, interval));
+    }
+    match arg_value(args, "--monitor") {
+        Some(mode) => simulation.monitor = Some(SafetyMonitor::new(mode.parse()?)),
+        None if cfg!(debug_assertions) => simulation.monitor = Some(SafetyMonitor::new(MonitorMode::Record)),
+        None => {}
+    }
+    if let Some(path) = arg_value(args, "--load-snapshot") {
+        Snapshot::load(path)?.restore(simulation, input_handler);
+    }
+    Ok(())
+}
+
+/// Runs the simulation without a window for `duration` simulated seconds
+///
+/// Random generation is always on. `--record <file>` writes the trajectory
+/// sampled every `--trajectory-interval` as CSV and `--record-fcd <file>`
+/// as SUMO floating car data, `--save-snapshot <file>` writes a snapshot
+/// when the run ends, `--auction-log <file>` writes the bids and outcomes
+/// of the auction policy as CSV, `--detector-log <file>` writes the
+/// measurements of the loop detectors per interval as CSV,
+/// `--monitor-report <file>` writes the safety monitor's violations, and
+/// statistics are printed to stdout.
+/// `--compare-platoons` repeats the run with platooning switched the other
+/// way and prints the difference in throughput, `--compare-policies
+/// <policy,...>` repeats it under each listed policy, and `--compare-control`
+/// repeats it with the central manager, with V2V negotiation and under
+/// fixed-time, actuated and max-pressure signals with their default timings
+/// and at all-way stop signs.
+fn run_headless(args: &[String], duration: f32) -> Result<(), String> {
+    let mut recorder = trajectory_recorder(args)?;
+    let (simulation, input_handler) = simulate_headless(args, duration, |_| {}, recorder.as_mut())?;
+    simulation.print_statistics();
+
+    if args.iter().any(|arg| arg == "--compare-platoons") {
+        let platooning = simulation.intersection.platooning;
+        let (other, _) = simulate_headless(args, duration, |simulation| {
+            simulation.intersection.platooning = !platooning;
+        }, None)?;
+        let (with, without) = if platooning {
+            (simulation.throughput(), other.throughput())
+        } else {
+            (other.throughput(), simulation.throughput())
+        };
+        println!(
+            "Throughput with platoons {:.2}, without {:.2} vehicles per minute ({:+.1}%)",
+            with,
+            without,
+            if without > 0.0 { (with / without - 1.0) * 100.0 } else { 0.0 },
+        );
+    }
+
+    if let Some(policies) = arg_value(args, "--compare-policies") {
+        print_comparison_header("Policy");
+        for policy in policies.split(',') {
+            let policy: Policy = policy.parse()?;
+            let (run, _) = simulate_headless(args, duration, |simulation| {
+                simulation.intersection.manager.policy = policy;
+            }, None)?;
+            print_comparison_row(&policy.to_string(), &run);
+        }
+    }
+
+    if args.iter().any(|arg| arg == "--compare-control") {
+        let range = v2v_range(args)?;
+        let phases = signal_phases(args)?;
+        let controls = [
+            ("manager", None, None, None),
+            ("v2v", Some(Negotiation::new(range)), None, None),
+            ("fixed-time", None, Some(Signal::new("fixed".parse()?, phases.clone())), None),
+            ("actuated", None, Some(Signal::new("actuated".parse()?, phases.clone())), None),
+            ("max-pressure", None, Some(Signal::new("max-pressure".parse()?, phases)), None),
+            ("all-way-stop", None, None, Some(AllWayStop::with_stop_signs())),
+        ];
+        print_comparison_header("Control");
+        for (label, negotiation, signal, all_way_stop) in controls {
+            let (run, _) = simulate_headless(args, duration, |simulation| {
+                simulation.intersection.negotiation = negotiation;
+                simulation.intersection.signal = signal;
+                simulation.intersection.all_way_stop = all_way_stop;
+            }, None)?;
+            print_comparison_row(label, &run);
+        }
+    }
+
+    if let (Some(path), Some(recorder)) = (arg_value(args, "--record"), recorder.as_ref()) {
+        recorder.save_csv(path).map_err(|e| e.to_string())?;
+        println!("Trajectory of {} samples saved to {}", recorder.samples().len(), path);
+    }
+
+    if let (Some(path), Some(recorder)) = (arg_value(args, "--record-fcd"), recorder.as_ref()) {
+        recorder.save_fcd(path).map_err(|e| e.to_string())?;
+        println!("Floating car data saved to {}", path);
+    }
+
+    if let Some(path) = arg_value(args, "--save-snapshot") {
+        Snapshot::capture(&simulation, &input_handler)
+            .save(path)
+            .map_err(|e| e.to_string())?;
+        println!("Snapshot saved to {}", path);
+    }
+
+    if let Some(path) = arg_value(args, "--monitor-report") {
+        write_monitor_report(&simulation, path)?;
+    }
+
+    if let Some(path) = arg_value(args, "--auction-log") {
+        let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+        auction::write_csv(&simulation.statistics().auction_log, std::io::BufWriter::new(file))
+            .map_err(|e| e.to_string())?;
+        println!("Auction log saved to {}", path);
+    }
+
+    if let Some(path) = arg_value(args, "--detector-log") {
+        let detectors = simulation
+            .intersection
+            .detectors
+            .as_ref()
+            .ok_or("--detector-log requires --detectors")?;
+        let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+        detector::write_csv(&detectors.all_records(), std::io::BufWriter::new(file))
+            .map_err(|e| e.to_string())?;
+        println!("Detector log saved to {}", path);
+    }
+
+    Ok(())
+}
+
+/// Sets up the control under test from the command line, without any
+/// random traffic
+fn setup_scenario_run(args: &[String]) -> Result<Simulation, String> {
+    let mut simulation = Simulation::new(25.0, Rc::new(RefCell::new(StatisticsManager::new())));
+    let mut input_handler = InputHandler::new(500, 100.0);
+    setup_run(args, &mut simulation, &mut input_handler)?;
+    Ok(simulation)
+}
+
+/// Searches for spawn sequences that make the control set up on the command
+/// line produce collisions or near misses
+///
+/// The search is seeded with `--seed` (1 by default). Every failure is
+/// minimised and saved as a scenario file in `--scenario-dir` (default
+/// `scenarios`).
+fn run_search(args: &[String], iterations: usize) -> Result<(), String> {
+    let template = setup_scenario_run(args)?.intersection;
+    let seed = match arg_value(args, "--seed") {
+        Some(seed) => seed.parse().map_err(|_| "--seed requires an integer")?,
+        None => 1,
+    };
+    let mut search = Search::new(template, TICK, seed);
+    let failures = search.run(iterations);
+
+    let dir = arg_value(args, "--scenario-dir").unwrap_or("scenarios");
+    if !failures.is_empty() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    println!("{} scenario runs, {} failing scenarios found", search.runs, failures.len());
+    for (index, failure) in failures.iter().enumerate() {
+        let path = format!("{}/failure_{:03}.txt", dir, index + 1);
+        failure.scenario.save(&path).map_err(|e| e.to_string())?;
+        println!(
+            "{}: {} vehicles, {} collisions, {} near misses (after {} runs)",
+            path,
+            failure.scenario.spawns.len(),
+            failure.outcome.collisions,
+            failure.outcome.zone_conflicts,
+            failure.found_after,
+        );
+    }
+    Ok(())
+}
+
+/// Runs a saved scenario without a window and prints statistics, writing
+/// the safety monitor's violations to `--monitor-report <file>` if given
+fn run_scenario(args: &[String], path: &str) -> Result<(), String> {
+    let scenario = Scenario::load(path)?;
+    let mut simulation = setup_scenario_run(args)?;
+    let outcome = scenario.run(&mut simulation, TICK);
+    simulation.print_statistics();
+    println!(
+        "Scenario {}: {}, {} spawns refused",
+        path,
+        if outcome.failed() { "FAILED" } else { "passed" },
+        outcome.refused,
+    );
+    if let Some(path) = arg_value(args, "--monitor-report") {
+        write_monitor_report(&simulation, path)?;
+    }
+    Ok(())
+}
+
+/// Writes the violations found by the safety monitor to `path`
+fn write_monitor_report(simulation: &Simulation, path: &str) -> Result<(), String> {
+    let monitor = simulation.monitor.as_ref().ok_or("--monitor-report requires --monitor")?;
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    monitor.write_report(std::io::BufWriter::new(file)).map_err(|e| e.to_string())?;
+    println!("Safety monitor report saved to {}", path);
+    Ok(())
+}
+
+/// Gets the V2V communication range given with `--v2v-range`, or the default
+fn v2v_range(args: &[String]) -> Result<f32, String> {
+    match arg_value(args, "--v2v-range") {
+        Some(range) => range.parse().map_err(|_| "--v2v-range requires a distance in meters".to_string()),
+        None => Ok(DEFAULT_COMMUNICATION_RANGE),
+    }
+}
+
+/// Gets a trajectory recorder sampling every `--trajectory-interval <seconds>`
+/// if `--record <file>` or `--record-fcd <file>` asks for the trajectory
+fn trajectory_recorder(args: &[String]) -> Result<Option<TrajectoryRecorder>, String> {
+    if arg_value(args, "--record").is_none() && arg_value(args, "--record-fcd").is_none() {
+        return Ok(None);
+    }
+    let interval = match arg_value(args, "--trajectory-interval") {
+        Some(interval) => interval
+            .parse()
+            .map_err(|_| "--trajectory-interval requires a time in seconds".to_string())?,
+        None => recording::trajectory::DEFAULT_SAMPLE_INTERVAL,
+    };
+    Ok(Some(TrajectoryRecorder::new(interval)))
+}
+
+/// Gets the signal phases given with `--signal-phases`, or the default ones
+fn signal_phases(args: &[String]) -> Result<Vec<Phase>, String> {
+    signal::parse_phases(arg_value(args, "--signal-phases").unwrap_or(signal::DEFAULT_PHASES))
+}
+
+/// Prints the column headers of a comparison between runs
+fn print_comparison_header(label: &str) {
+    println!(
+        "\n{:<16} {:>12} {:>10} {:>10} {:>9} {:>10}",
+        label, "Veh/minute", "Avg delay", "Max delay", "Fairness", "Max queue",
+    );
+}
+
+/// Prints the throughput, delay, fairness and longest queue of one compared run
+fn print_comparison_row(label: &str, run: &Simulation) {
+    let delay = run.statistics().regular_delay;
+    println!(
+        "{:<16} {:>12.2} {:>9.2}s {:>9.2}s {:>9.3} {:>9.1}m",
+        label,
+        run.throughput(),
+        delay.average(),
+        delay.max,
+        delay.fairness(),
+        run.intersection.queues.longest_overall().length,
+    );
+}
+
+/// Sets up a run from the command line, lets `configure` override parts of
+/// it and simulates it for `duration` seconds, sampling the trajectory into
+/// `recorder` after every step when given
+fn simulate_headless(
+    args: &[String],
+    duration: f32,
+    configure: impl FnOnce(&mut Simulation),
+    mut recorder: Option<&mut TrajectoryRecorder>,
+) -> Result<(Simulation, InputHandler), String> {
+    let stats_manager = Rc::new(RefCell::new(StatisticsManager::new()));
+    let mut simulation = Simulation::new(25.0, Rc::clone(&stats_manager));
+    let mut input_handler = InputHandler::new(500, 100.0);
+    input_handler.random_generation_enabled = true;
+    setup_run(args, &mut simulation, &mut input_handler)?;
+    configure(&mut simulation);
+
+    let end_time = simulation.time + duration;
+    while simulation.time < end_time && !simulation.halted() {
+        if let Some(vehicle) = input_handler.update_random_generation(800, simulation.time) {
+            simulation.add_vehicle(vehicle);
+        }
+        if let Some(pedestrian) = input_handler.update_pedestrian_generation(simulation.time) {
+            simulation.add_pedestrian(pedestrian);
+        }
+        simulation.update(TICK);
+        if let Some(recorder) = recorder.as_deref_mut() {
+            recorder.sample(simulation.time, &simulation.intersection);
+        }
+    }
+    Ok((simulation, input_handler))
+}
+
+/// Plays back a recorded trajectory
+///
+/// Space pauses/resumes, Left/Right seek 5 s, Up/Down change the playback
+/// speed, Home restarts and Q quits.
+fn run_replay(
+    canvas: &mut Canvas<Window>,
+    event_pump: &mut EventPump,
+    renderer: &Renderer,
+    mut replay: Replay,
+) -> Result<(), String> {
+    'running: loop {
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Q), .. } => break 'running,
+                Event::KeyDown { keycode: Some(Keycode::Space), .. } => replay.toggle_pause(),
+                Event::KeyDown { keycode: Some(Keycode::Left), .. } => replay.seek_by(-5.0),
+                Event::KeyDown { keycode: Some(Keycode::Right), .. } => replay.seek_by(5.0),
+                Event::KeyDown { keycode: Some(Keycode::Up), .. } => replay.faster(),
+                Event::KeyDown { keycode: Some(Keycode::Down), .. } => replay.slower(),
+                Event::KeyDown { keycode: Some(Keycode::Home), .. } => replay.seek(replay.start_time()),
+                _ => {}
+            }
+        }
+
+        replay.update(TICK);
+
+        let title = format!(
+            "Traffic Intersection - Replay {:.1} / {:.1} s x{}{}",
+            replay.time,
+            replay.end_time(),
+            replay.speed,
+            if replay.playing { "" } else { " (paused)" },
+        );
+        canvas.window_mut().set_title(&title).map_err(|e| e.to_string())?;
+
+        renderer.render_replay(canvas, &replay)?;
+        canvas.present();
+
+        std::thread::sleep(Duration::from_millis(16));
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,142 @@
+use std::io::{self, Write};
+use crate::simulation::physics::lane_position;
+use super::trajectory::TrajectorySample;
+
+/// Writes trajectory samples in SUMO's floating car data (FCD) XML format
+///
+/// Samples must be ordered by time (as recorded by `TrajectoryRecorder`); each
+/// distinct time becomes one `<timestep>` element. Positions are the middle
+/// of the vehicle's lane, where it is drawn, and angles are in degrees
+/// clockwise from north, as SUMO has them. Lane ids follow SUMO's
+/// `<edge>_<index>` convention, with the approach direction as the edge id,
+/// and the vehicle type name is used as the SUMO type id.
+pub fn write_fcd<W: Write>(mut writer: W, samples: &[TrajectorySample]) -> io::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<fcd-export xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:noNamespaceSchemaLocation="http://sumo.dlr.de/xsd/fcd_file.xsd">"#
+    )?;
+
+    let mut current_time: Option<f32> = None;
+    for sample in samples {
+        if current_time != Some(sample.time) {
+            if current_time.is_some() {
+                writeln!(writer, "    </timestep>")?;
+            }
+            writeln!(writer, r#"    <timestep time="{:.2}">"#, sample.time)?;
+            current_time = Some(sample.time);
+        }
+
+        let (x, y) = lane_position((sample.x, sample.y), sample.direction, sample.route(), sample.vehicle_type);
+        writeln!(
+            writer,
+            r#"        <vehicle id="{}" x="{:.2}" y="{:.2}" angle="{:.2}" type="{}" speed="{:.2}" lane="{}_{}"/>"#,
+            sample.id,
+            x,
+            y,
+            sample.heading.rem_euclid(360.0),
+            sample.vehicle_type,
+            sample.speed,
+            sample.direction,
+            sample.lane,
+        )?;
+    }
+
+    if current_time.is_some() {
+        writeln!(writer, "    </timestep>")?;
+    }
+    writeln!(writer, "</fcd-export>")?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::vehicle::BIKE_LANE;
+    use crate::simulation::{Direction, Route, VehicleState, VehicleType};
+
+    fn sample(time: f32, id: u32) -> TrajectorySample {
+        TrajectorySample {
+            time,
+            id,
+            x: 0.0,
+            y: -100.0,
+            heading: 0.0,
+            speed: 10.0,
+            acceleration: 0.0,
+            direction: Direction::North,
+            lane: Route::Straight.lane_index(),
+            state: VehicleState::Approaching,
+            vehicle_type: VehicleType::Car,
+        }
+    }
+
+    #[test]
+    fn test_empty_export() {
+        let mut buffer = Vec::new();
+        write_fcd(&mut buffer, &[]).unwrap();
+
+        let xml = String::from_utf8(buffer).unwrap();
+        assert!(xml.contains("<fcd-export"));
+        assert!(xml.trim_end().ends_with("</fcd-export>"));
+        assert!(!xml.contains("<timestep"));
+    }
+
+    #[test]
+    fn test_groups_samples_by_timestep() {
+        let samples = vec![sample(0.0, 1), sample(0.0, 2), sample(0.1, 1)];
+        let mut buffer = Vec::new();
+        write_fcd(&mut buffer, &samples).unwrap();
+
+        let xml = String::from_utf8(buffer).unwrap();
+        assert_eq!(xml.matches("<timestep ").count(), 2);
+        assert_eq!(xml.matches("</timestep>").count(), 2);
+        assert_eq!(xml.matches("<vehicle ").count(), 3);
+        assert!(xml.contains(r#"<timestep time="0.10">"#));
+        assert!(xml.contains(
+            r#"<vehicle id="2" x="5.25" y="-100.00" angle="0.00" type="car" speed="10.00" lane="North_1"/>"#
+        ));
+    }
+
+    #[test]
+    fn test_positions_and_angles_follow_the_lane() {
+        let west = TrajectorySample {
+            x: 100.0,
+            y: 0.0,
+            heading: Direction::West.heading(),
+            direction: Direction::West,
+            lane: Route::Left.lane_index(),
+            ..sample(0.0, 1)
+        };
+        let east = TrajectorySample {
+            x: -100.0,
+            y: 0.0,
+            heading: Direction::East.heading(),
+            direction: Direction::East,
+            lane: Route::Right.lane_index(),
+            ..sample(0.0, 2)
+        };
+        let mut buffer = Vec::new();
+        write_fcd(&mut buffer, &[west, east]).unwrap();
+
+        // Westbound traffic drives on the north side of the road, eastbound on the south
+        let xml = String::from_utf8(buffer).unwrap();
+        assert!(xml.contains(r#"<vehicle id="1" x="100.00" y="8.75" angle="270.00""#));
+        assert!(xml.contains(r#"<vehicle id="2" x="-100.00" y="-1.75" angle="90.00""#));
+    }
+
+    #[test]
+    fn test_bicycles_exported_in_bike_lane() {
+        let bicycle = TrajectorySample {
+            lane: BIKE_LANE,
+            vehicle_type: VehicleType::Bicycle,
+            ..sample(0.0, 1)
+        };
+        let mut buffer = Vec::new();
+        write_fcd(&mut buffer, &[bicycle]).unwrap();
+
+        let xml = String::from_utf8(buffer).unwrap();
+        assert!(xml.contains(r#"<vehicle id="1" x="9.90" y="-100.00""#));
+        assert!(xml.contains(r#"type="bicycle" speed="10.00" lane="North_3"/>"#));
+    }
+}
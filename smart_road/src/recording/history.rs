@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+use crate::events::InputHandler;
+use crate::simulation::Simulation;
+use super::snapshot::Snapshot;
+
+/// Bounded in-memory history of snapshots for rewinding a live run
+///
+/// Snapshots are taken at a fixed simulation-time interval and the oldest
+/// ones are dropped once `capacity` is reached. While scrubbing, a cursor
+/// points at the snapshot currently shown; resuming discards everything
+/// after it so the run continues from that point.
+pub struct History {
+    snapshots: VecDeque<Snapshot>,
+
+    /// Maximum number of snapshots kept
+    capacity: usize,
+
+    /// Simulation time between two snapshots (in seconds)
+    interval: f32,
+
+    /// Index of the snapshot being shown while scrubbing
+    cursor: Option<usize>,
+}
+
+impl History {
+    pub fn new(capacity: usize, interval: f32) -> Self {
+        History {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            interval,
+            cursor: None,
+        }
+    }
+
+    /// Takes a snapshot if one is due; does nothing while scrubbing
+    pub fn record(&mut self, simulation: &Simulation, input_handler: &InputHandler) {
+        if self.is_scrubbing() {
+            return;
+        }
+        if let Some(last) = self.snapshots.back() {
+            if simulation.time < last.time + self.interval {
+                return;
+            }
+        }
+
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(Snapshot::capture(simulation, input_handler));
+    }
+
+    /// Forgets all snapshots, e.g. after loading an unrelated state
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.cursor = None;
+    }
+
+    /// Whether a past snapshot is currently selected
+    pub fn is_scrubbing(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// Moves the cursor to the snapshot closest to `fraction` (0.0-1.0) of the timeline
+    pub fn seek(&mut self, fraction: f32) -> Option<&Snapshot> {
+        if self.snapshots.is_empty() {
+            return None;
+        }
+        let last = self.snapshots.len() - 1;
+        let index = (fraction.clamp(0.0, 1.0) * last as f32).round() as usize;
+        self.cursor = Some(index);
+        self.snapshots.get(index)
+    }
+
+    /// Moves the cursor by `steps` snapshots (negative steps rewind)
+    pub fn step(&mut self, steps: isize) -> Option<&Snapshot> {
+        if self.snapshots.is_empty() {
+            return None;
+        }
+        let last = self.snapshots.len() - 1;
+        let current = self.cursor.unwrap_or(last) as isize;
+        let index = (current + steps).clamp(0, last as isize) as usize;
+        self.cursor = Some(index);
+        self.snapshots.get(index)
+    }
+
+    /// Number of snapshots covering `seconds` of simulation time
+    pub fn steps_for(&self, seconds: f32) -> isize {
+        (seconds / self.interval).round() as isize
+    }
+
+    /// Leaves scrubbing mode, dropping the snapshots after the cursor
+    pub fn resume(&mut self) {
+        if let Some(index) = self.cursor.take() {
+            self.snapshots.truncate(index + 1);
+        }
+    }
+
+    /// Time of the oldest snapshot kept
+    pub fn start_time(&self) -> f32 {
+        self.snapshots.front().map_or(0.0, |snapshot| snapshot.time)
+    }
+
+    /// Time of the newest snapshot kept
+    pub fn end_time(&self) -> f32 {
+        self.snapshots.back().map_or(0.0, |snapshot| snapshot.time)
+    }
+
+    /// Position of the cursor (or the live end) as a fraction of the timeline
+    pub fn position(&self) -> f32 {
+        match (self.cursor, self.snapshots.len()) {
+            (Some(index), len) if len > 1 => index as f32 / (len - 1) as f32,
+            _ => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::stats::StatisticsManager;
+
+    fn run_with_history(history: &mut History, seconds: f32) -> (Simulation, InputHandler) {
+        let mut simulation = Simulation::new(25.0, Rc::new(RefCell::new(StatisticsManager::new())));
+        let input_handler = InputHandler::new(500, 100.0);
+        while simulation.time < seconds {
+            history.record(&simulation, &input_handler);
+            simulation.update(0.05);
+        }
+        (simulation, input_handler)
+    }
+
+    #[test]
+    fn test_interval_and_capacity() {
+        let mut history = History::new(10, 0.5);
+        run_with_history(&mut history, 10.0);
+
+        assert_eq!(history.snapshots.len(), 10);
+        assert!(history.end_time() - history.start_time() >= 4.5);
+        assert!(history.start_time() > 4.0);
+    }
+
+    #[test]
+    fn test_step_and_seek() {
+        let mut history = History::new(100, 1.0);
+        run_with_history(&mut history, 10.0);
+        let newest = history.end_time();
+
+        let snapshot = history.step(-2).unwrap();
+        assert!((newest - snapshot.time - 2.0).abs() < 0.1);
+        assert!(history.is_scrubbing());
+
+        assert_eq!(history.seek(0.0).unwrap().time, 0.0);
+        assert_eq!(history.position(), 0.0);
+        assert_eq!(history.step(-5).unwrap().time, 0.0);
+    }
+
+    #[test]
+    fn test_resume_discards_future() {
+        let mut history = History::new(100, 1.0);
+        let (simulation, input_handler) = run_with_history(&mut history, 10.0);
+        let count = history.snapshots.len();
+
+        history.step(-3);
+        history.resume();
+        assert!(!history.is_scrubbing());
+        assert_eq!(history.snapshots.len(), count - 3);
+
+        // Recording continues once the run moves past the kept end
+        history.record(&simulation, &input_handler);
+        assert_eq!(history.snapshots.len(), count - 2);
+    }
+
+    #[test]
+    fn test_no_recording_while_scrubbing() {
+        let mut history = History::new(100, 1.0);
+        let (simulation, input_handler) = run_with_history(&mut history, 5.0);
+        let count = history.snapshots.len();
+
+        history.step(-1);
+        history.record(&simulation, &input_handler);
+        assert_eq!(history.snapshots.len(), count);
+    }
+}
@@ -0,0 +1,12 @@
+//! Recording module for capturing simulation runs for offline analysis
+
+pub mod trajectory;
+pub mod fcd;
+pub mod replay;
+pub mod snapshot;
+pub mod history;
+
+pub use trajectory::TrajectoryRecorder;
+pub use replay::Replay;
+pub use snapshot::Snapshot;
+pub use history::History;
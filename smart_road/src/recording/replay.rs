@@ -0,0 +1,221 @@
+use crate::simulation::{Direction, Vehicle};
+use super::trajectory::TrajectorySample;
+
+/// Slowest and fastest playback speed multipliers
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 16.0;
+
+/// Plays back a recorded trajectory instead of running the simulation
+///
+/// Samples are grouped into frames by time; the frame shown is the latest one
+/// recorded at or before the current playback time.
+pub struct Replay {
+    /// Recorded frames in time order, each holding the vehicles of one sample
+    frames: Vec<(f32, Vec<Vehicle>)>,
+
+    /// Current playback position (in simulation seconds)
+    pub time: f32,
+
+    /// Playback speed multiplier (1.0 = real time)
+    pub speed: f32,
+
+    /// Whether playback is running or paused
+    pub playing: bool,
+}
+
+impl Replay {
+    /// Creates a replay from samples ordered by time (as written by the recorder)
+    pub fn new(samples: &[TrajectorySample]) -> Self {
+        let mut frames: Vec<(f32, Vec<Vehicle>)> = Vec::new();
+        for sample in samples {
+            let vehicle = Self::vehicle_from_sample(sample);
+            match frames.last_mut() {
+                Some((time, vehicles)) if *time == sample.time => vehicles.push(vehicle),
+                _ => frames.push((sample.time, vec![vehicle])),
+            }
+        }
+
+        let start = frames.first().map_or(0.0, |(time, _)| *time);
+        Replay {
+            frames,
+            time: start,
+            speed: 1.0,
+            playing: true,
+        }
+    }
+
+    /// Rebuilds a drawable vehicle from a recorded sample
+    fn vehicle_from_sample(sample: &TrajectorySample) -> Vehicle {
+        // Vehicles drive along one axis towards the centre, so the distance
+        // left to the intersection follows from the position
+        let distance = match sample.direction {
+            Direction::North => -sample.y,
+            Direction::South => sample.y,
+            Direction::East => -sample.x,
+            Direction::West => sample.x,
+        };
+
+        Vehicle::new(
+            sample.id,
+            (sample.x, sample.y),
+            sample.speed,
+            sample.route(),
+            sample.direction,
+            distance,
+        )
+        .with_type(sample.vehicle_type)
+    }
+
+    /// Time of the first recorded frame
+    pub fn start_time(&self) -> f32 {
+        self.frames.first().map_or(0.0, |(time, _)| *time)
+    }
+
+    /// Time of the last recorded frame
+    pub fn end_time(&self) -> f32 {
+        self.frames.last().map_or(0.0, |(time, _)| *time)
+    }
+
+    /// Advances playback by `delta_time` real seconds, scaled by the speed
+    ///
+    /// Playback pauses automatically at the end of the recording.
+    pub fn update(&mut self, delta_time: f32) {
+        if !self.playing {
+            return;
+        }
+        self.time += delta_time * self.speed;
+        if self.time >= self.end_time() {
+            self.time = self.end_time();
+            self.playing = false;
+        }
+    }
+
+    /// Toggles between playing and paused
+    pub fn toggle_pause(&mut self) {
+        if !self.playing && self.time >= self.end_time() {
+            // Restart from the beginning when resuming at the end
+            self.time = self.start_time();
+        }
+        self.playing = !self.playing;
+    }
+
+    /// Moves the playback position to `time`, clamped to the recording
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.clamp(self.start_time(), self.end_time());
+    }
+
+    /// Moves the playback position by `offset` seconds
+    pub fn seek_by(&mut self, offset: f32) {
+        self.seek(self.time + offset);
+    }
+
+    /// Doubles the playback speed, up to `MAX_SPEED`
+    pub fn faster(&mut self) {
+        self.speed = (self.speed * 2.0).min(MAX_SPEED);
+    }
+
+    /// Halves the playback speed, down to `MIN_SPEED`
+    pub fn slower(&mut self) {
+        self.speed = (self.speed / 2.0).max(MIN_SPEED);
+    }
+
+    /// Vehicles of the frame shown at the current playback position
+    pub fn current_vehicles(&self) -> &[Vehicle] {
+        let index = self.frames.partition_point(|(time, _)| *time <= self.time);
+        if index == 0 {
+            return &[];
+        }
+        &self.frames[index - 1].1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{Route, VehicleState, VehicleType};
+
+    fn sample(time: f32, id: u32, y: f32) -> TrajectorySample {
+        TrajectorySample {
+            time,
+            id,
+            x: 0.0,
+            y,
+            heading: 0.0,
+            speed: 10.0,
+            acceleration: 0.0,
+            direction: Direction::North,
+            lane: Route::Straight.lane_index(),
+            state: VehicleState::Approaching,
+            vehicle_type: VehicleType::Car,
+        }
+    }
+
+    fn replay() -> Replay {
+        Replay::new(&[
+            sample(0.0, 1, -100.0),
+            sample(0.0, 2, -80.0),
+            sample(1.0, 1, -90.0),
+            sample(2.0, 1, -80.0),
+        ])
+    }
+
+    #[test]
+    fn test_frames_grouped_by_time() {
+        let replay = replay();
+        assert_eq!(replay.start_time(), 0.0);
+        assert_eq!(replay.end_time(), 2.0);
+        assert_eq!(replay.current_vehicles().len(), 2);
+        assert_eq!(replay.current_vehicles()[1].distance_to_intersection, 80.0);
+    }
+
+    #[test]
+    fn test_playback_speed() {
+        let mut replay = replay();
+        replay.faster();
+        replay.update(0.5);
+
+        assert_eq!(replay.time, 1.0);
+        assert_eq!(replay.current_vehicles().len(), 1);
+        assert_eq!(replay.current_vehicles()[0].position.1, -90.0);
+    }
+
+    #[test]
+    fn test_pauses_at_end() {
+        let mut replay = replay();
+        replay.update(10.0);
+
+        assert_eq!(replay.time, 2.0);
+        assert!(!replay.playing);
+
+        // Resuming at the end restarts playback
+        replay.toggle_pause();
+        assert!(replay.playing);
+        assert_eq!(replay.time, 0.0);
+    }
+
+    #[test]
+    fn test_seek_is_clamped() {
+        let mut replay = replay();
+        replay.seek_by(1.5);
+        assert_eq!(replay.time, 1.5);
+        assert_eq!(replay.current_vehicles()[0].position.1, -90.0);
+
+        replay.seek_by(-10.0);
+        assert_eq!(replay.time, 0.0);
+        replay.seek(10.0);
+        assert_eq!(replay.time, 2.0);
+    }
+
+    #[test]
+    fn test_speed_limits() {
+        let mut replay = replay();
+        for _ in 0..10 {
+            replay.faster();
+        }
+        assert_eq!(replay.speed, MAX_SPEED);
+        for _ in 0..20 {
+            replay.slower();
+        }
+        assert_eq!(replay.speed, MIN_SPEED);
+    }
+}
@@ -0,0 +1,1077 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use crate::events::{GeneratorState, InputHandler};
+use crate::simulation::auction::AuctionOutcome;
+use crate::simulation::conflict::Claim;
+use crate::simulation::detector::{DetectorRecord, Detectors, LoopDetector};
+use crate::simulation::driver::HumanDriver;
+use crate::simulation::fallback::{AllWayStop, Outage};
+use crate::simulation::manager::{Agent, Request};
+use crate::simulation::queue::{Queue, QueueMonitor, APPROACHES};
+use crate::simulation::rng::SimRng;
+use crate::simulation::rogue::Rogue;
+use crate::simulation::signal::Signal;
+use crate::simulation::v2i::{ChannelConfig, InFlight, Message, V2iLink};
+use crate::simulation::v2v::Negotiation;
+use crate::simulation::vehicle::BIKE_LANE;
+use crate::simulation::{Intersection, Pedestrian, Route, Simulation, Vehicle};
+use crate::stats::StatisticsManager;
+
+/// First line of every snapshot file, bumped when the format changes
+const SNAPSHOT_HEADER: &str = "smart_road_snapshot 20";
+
+/// Complete state of a run, from which it can be resumed exactly
+///
+/// The file format is plain text with one `key value...` record per line.
+/// Floats are written with Rust's shortest round-trip formatting so a
+/// loaded snapshot continues bit-for-bit where it was saved.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub time: f32,
+    pub total_vehicles: u32,
+    pub intersection: Intersection,
+    pub stats: StatisticsManager,
+    pub generator: GeneratorState,
+}
+
+impl Snapshot {
+    /// Captures the current state of the simulation and vehicle generator
+    pub fn capture(simulation: &Simulation, input_handler: &InputHandler) -> Self {
+        Snapshot {
+            time: simulation.time,
+            total_vehicles: simulation.total_vehicles,
+            intersection: simulation.intersection.clone(),
+            stats: simulation.statistics(),
+            generator: input_handler.generator_state(),
+        }
+    }
+
+    /// Puts the simulation and vehicle generator back into the captured state
+    pub fn restore(&self, simulation: &mut Simulation, input_handler: &mut InputHandler) {
+        simulation.time = self.time;
+        simulation.total_vehicles = self.total_vehicles;
+        simulation.intersection = self.intersection.clone();
+        simulation.restore_statistics(self.stats.clone());
+        input_handler.restore_generator_state(&self.generator);
+    }
+
+    /// Writes the snapshot in the text format
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{}", SNAPSHOT_HEADER)?;
+        writeln!(writer, "time {}", self.time)?;
+        writeln!(writer, "total_vehicles {}", self.total_vehicles)?;
+        writeln!(writer, "safe_distance {}", self.intersection.safe_distance)?;
+        writeln!(
+            writer,
+            "physics {} {}",
+            self.intersection.physics.safe_distance,
+            self.intersection.physics.boundary_limit,
+        )?;
+        writeln!(
+            writer,
+            "stats {} {} {} {}",
+            self.stats.num_cars, self.stats.num_close, self.stats.max_velo, self.stats.min_velo,
+        )?;
+        for (key, delay) in [
+            ("regular_delay", &self.stats.regular_delay),
+            ("emergency_delay", &self.stats.emergency_delay),
+            ("cyclist_delay", &self.stats.cyclist_delay),
+            ("pedestrian_wait", &self.stats.pedestrian_wait),
+            ("platoon_size", &self.stats.platoon_size),
+        ] {
+            writeln!(
+                writer,
+                "{} {} {} {} {}",
+                key, delay.count, delay.total, delay.max, delay.sum_squares,
+            )?;
+        }
+        writeln!(writer, "pedestrian_close_calls {}", self.stats.pedestrian_close_calls)?;
+        writeln!(writer, "zone_conflicts {}", self.stats.zone_conflicts)?;
+        let mut pairs: Vec<&(u32, u32)> = self.intersection.conflicting_pairs.iter().collect();
+        pairs.sort();
+        for (a, b) in pairs {
+            writeln!(writer, "conflicting_pair {} {}", a, b)?;
+        }
+        writeln!(writer, "collisions {} {}", self.stats.collisions, self.stats.emergency_brakings)?;
+        let mut pairs: Vec<&(u32, u32)> = self.intersection.colliding_pairs.iter().collect();
+        pairs.sort();
+        for (a, b) in pairs {
+            writeln!(writer, "colliding_pair {} {}", a, b)?;
+        }
+        let mut braking: Vec<&u32> = self.intersection.emergency_braking.iter().collect();
+        braking.sort();
+        for id in braking {
+            writeln!(writer, "emergency_braking {}", id)?;
+        }
+        writeln!(writer, "platooning {}", self.intersection.platooning)?;
+        for outcome in &self.stats.auction_log {
+            writeln!(writer, "{}", format_outcome("auction", outcome))?;
+        }
+        writeln!(
+            writer,
+            "generator {} {} {} {} {} {}",
+            self.generator.next_vehicle_id,
+            self.generator.random_generation_enabled,
+            self.generator.last_random_spawn.map_or("-".to_string(), |t| t.to_string()),
+            self.generator.rng_state,
+            self.generator.next_pedestrian_id,
+            self.generator.next_pedestrian_spawn.map_or("-".to_string(), |t| t.to_string()),
+        )?;
+
+        let manager = &self.intersection.manager;
+        writeln!(writer, "manager {}", manager.next_sequence)?;
+        writeln!(writer, "policy {} {}", manager.policy, manager.batch.collecting)?;
+        for agent in &manager.batch.members {
+            writeln!(writer, "batch_member {}", format_agent(*agent))?;
+        }
+        for request in &manager.requests {
+            let agent = format_agent(request.agent);
+            let claim = match request.claim {
+                Claim::Movement((direction, route)) => format!("movement {} {}", direction, route.lane_index()),
+                Claim::Cycling((direction, route)) => format!("cycling {} {}", direction, route.lane_index()),
+                Claim::Crosswalk(arm) => format!("crosswalk {} -", arm),
+            };
+            writeln!(
+                writer,
+                "request {} {} {} {} {} {} {} {} {}",
+                agent,
+                claim,
+                request.priority,
+                request.sequence,
+                request.granted,
+                request.human,
+                request.waited,
+                request.losses,
+                request.intruder,
+            )?;
+            if let Some(bid) = &request.bid {
+                writeln!(writer, "{}", format_outcome("bid", bid))?;
+            }
+        }
+
+        if let Some(negotiation) = &self.intersection.negotiation {
+            writeln!(
+                writer,
+                "negotiation {} {} {}",
+                negotiation.range, negotiation.time, negotiation.messages,
+            )?;
+            let mut arrivals: Vec<(&Agent, &f32)> = negotiation.arrivals.iter().collect();
+            arrivals.sort_by_key(|(agent, _)| format_agent(**agent));
+            for (agent, arrival) in arrivals {
+                let going = negotiation.going.contains(agent);
+                writeln!(writer, "intention {} {} {}", format_agent(*agent), arrival, going)?;
+            }
+        }
+
+        if let Some(link) = &self.intersection.v2i {
+            writeln!(
+                writer,
+                "v2i {} {} {} {} {} {} {} {} {}",
+                link.config.latency,
+                link.config.jitter,
+                link.config.drop_rate,
+                link.rng.state(),
+                link.time,
+                link.listening,
+                link.sent,
+                link.dropped,
+                link.unauthorized_entries,
+            )?;
+            let mut ids: Vec<&u32> = link.requested.iter().chain(&link.granted).chain(&link.entered).collect();
+            ids.sort();
+            ids.dedup();
+            for id in ids {
+                writeln!(
+                    writer,
+                    "v2i_vehicle {} {} {} {}",
+                    id,
+                    link.requested.contains(id),
+                    link.granted.contains(id),
+                    link.entered.contains(id),
+                )?;
+            }
+            let mut view: Vec<&(Vehicle, f32)> = link.view.values().collect();
+            view.sort_by_key(|(vehicle, _)| vehicle.id);
+            for (vehicle, heard) in view {
+                writeln!(writer, "v2i_view {} {}", heard, format_vehicle(vehicle))?;
+            }
+            for (channel, messages) in [("uplink", &link.uplink), ("downlink", &link.downlink)] {
+                for in_flight in messages {
+                    let message = match &in_flight.message {
+                        Message::Request(vehicle) => format!("request {}", format_vehicle(vehicle)),
+                        Message::PositionUpdate(vehicle) => format!("update {}", format_vehicle(vehicle)),
+                        Message::Cancel(id) => format!("cancel {}", id),
+                        Message::Grant(id) => format!("grant {}", id),
+                        Message::Reject(id) => format!("reject {}", id),
+                    };
+                    writeln!(writer, "v2i_message {} {} {}", channel, in_flight.delivery, message)?;
+                }
+            }
+        }
+
+        if let Some(signal) = &self.intersection.signal {
+            writeln!(
+                writer,
+                "signal {} {} {} {} {} {} {} {}",
+                signal.controller,
+                signal.current,
+                signal.interval,
+                signal.elapsed,
+                signal.since_detection,
+                signal.phase_changes,
+                signal.gap_outs,
+                signal.max_outs,
+            )?;
+            for phase in &signal.phases {
+                writeln!(writer, "signal_phase {}", phase)?;
+            }
+            let mut going: Vec<String> = signal.going.iter().map(|agent| format_agent(*agent)).collect();
+            going.sort();
+            for agent in going {
+                writeln!(writer, "signal_going {}", agent)?;
+            }
+        }
+
+        let fallback = &self.intersection.fallback;
+        writeln!(
+            writer,
+            "fallback {} {} {} {} {} {} {} {}",
+            fallback.time,
+            fallback.manual_outage,
+            fallback.manager_down,
+            fallback.silence,
+            fallback.active,
+            fallback.activations,
+            fallback.time_active,
+            fallback.rule.time,
+        )?;
+        for outage in &fallback.outages {
+            writeln!(writer, "outage {} {}", outage.start, outage.duration)?;
+        }
+        write_rule_agents(&mut writer, "fallback_agent", &fallback.rule)?;
+
+        if let Some(rule) = &self.intersection.all_way_stop {
+            writeln!(writer, "all_way_stop {}", rule.time)?;
+            write_rule_agents(&mut writer, "all_way_stop_agent", rule)?;
+        }
+
+        let queues = &self.intersection.queues;
+        writeln!(writer, "queues {}", queues.spawn_distance)?;
+        for direction in APPROACHES {
+            for lane in 0..=BIKE_LANE {
+                if let Some(longest) = queues.longest.get(&(direction, lane)) {
+                    let current = queues.lane(direction, lane);
+                    writeln!(
+                        writer,
+                        "queue {} {} {} {} {} {}",
+                        direction, lane, current.vehicles, current.length, longest.vehicles, longest.length,
+                    )?;
+                }
+            }
+            if let Some(longest) = queues.longest_approach.get(&direction) {
+                writeln!(
+                    writer,
+                    "queue_approach {} {} {} {} {} {}",
+                    direction,
+                    longest.vehicles,
+                    longest.length,
+                    queues.spilled.contains(&direction),
+                    queues.spillbacks.get(&direction).copied().unwrap_or(0),
+                    queues.spillback_time.get(&direction).copied().unwrap_or(0.0),
+                )?;
+            }
+        }
+
+        if let Some(detectors) = &self.intersection.detectors {
+            writeln!(writer, "detectors {} {} {}", detectors.interval, detectors.time, detectors.interval_start)?;
+            for (index, detector) in detectors.loops.iter().enumerate() {
+                writeln!(
+                    writer,
+                    "detector {} {} {} {} {} {} {}",
+                    detector.direction,
+                    detector.lane,
+                    detector.distance,
+                    detector.length,
+                    detector.count,
+                    detector.occupied_time,
+                    detector.speed_sum,
+                )?;
+                let mut over: Vec<&u32> = detector.over.iter().collect();
+                over.sort();
+                for id in over {
+                    writeln!(writer, "detector_over {} {}", index, id)?;
+                }
+            }
+            for record in &detectors.records {
+                writeln!(
+                    writer,
+                    "detector_record {} {} {} {} {} {}",
+                    record.start,
+                    record.end,
+                    record.detector,
+                    record.count,
+                    record.occupied_time,
+                    record.speed_sum,
+                )?;
+            }
+        }
+
+        for pedestrian in &self.intersection.pedestrians {
+            writeln!(
+                writer,
+                "pedestrian {} {} {} {} {} {} {} {} {}",
+                pedestrian.id,
+                pedestrian.arm,
+                pedestrian.position,
+                pedestrian.forward,
+                pedestrian.speed,
+                pedestrian.waiting_time,
+                pedestrian.crossing,
+                pedestrian.active,
+                pedestrian.close_call,
+            )?;
+        }
+
+        // Lanes are written in a fixed order so equal snapshots give equal files
+        let mut vehicles: Vec<&Vehicle> = self.intersection.lanes
+            .values()
+            .flat_map(|lane| lane.iter())
+            .collect();
+        vehicles.sort_by_key(|vehicle| vehicle.id);
+        for vehicle in vehicles {
+            writeln!(writer, "{}", format_vehicle(vehicle))?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a snapshot written by `write`
+    pub fn read<R: BufRead>(reader: R) -> Result<Self, String> {
+        let mut lines = reader.lines();
+        match lines.next() {
+            Some(Ok(header)) if header.trim() == SNAPSHOT_HEADER => {}
+            _ => return Err("Not a smart_road snapshot".to_string()),
+        }
+
+        let mut snapshot = Snapshot {
+            time: 0.0,
+            total_vehicles: 0,
+            intersection: Intersection::new(0.0),
+            stats: StatisticsManager::new(),
+            generator: GeneratorState {
+                next_vehicle_id: 1,
+                random_generation_enabled: false,
+                last_random_spawn: None,
+                rng_state: 0,
+                next_pedestrian_id: 1,
+                next_pedestrian_spawn: None,
+            },
+        };
+
+        for (index, line) in lines.enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                continue;
+            }
+            snapshot
+                .read_record(&fields)
+                .map_err(|e| format!("line {}: {}", index + 2, e))?;
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Applies one `key value...` record to the snapshot being read
+    fn read_record(&mut self, fields: &[&str]) -> Result<(), String> {
+        match fields[0] {
+            "time" => self.time = field(fields, 1)?,
+            "total_vehicles" => self.total_vehicles = field(fields, 1)?,
+            "safe_distance" => self.intersection.safe_distance = field(fields, 1)?,
+            "physics" => {
+                self.intersection.physics.safe_distance = field(fields, 1)?;
+                self.intersection.physics.boundary_limit = field(fields, 2)?;
+            }
+            "stats" => {
+                self.stats.num_cars = field(fields, 1)?;
+                self.stats.num_close = field(fields, 2)?;
+                self.stats.max_velo = field(fields, 3)?;
+                self.stats.min_velo = field(fields, 4)?;
+            }
+            "regular_delay" | "emergency_delay" | "cyclist_delay" | "pedestrian_wait" | "platoon_size" => {
+                let delay = match fields[0] {
+                    "regular_delay" => &mut self.stats.regular_delay,
+                    "emergency_delay" => &mut self.stats.emergency_delay,
+                    "cyclist_delay" => &mut self.stats.cyclist_delay,
+                    "platoon_size" => &mut self.stats.platoon_size,
+                    _ => &mut self.stats.pedestrian_wait,
+                };
+                delay.count = field(fields, 1)?;
+                delay.total = field(fields, 2)?;
+                delay.max = field(fields, 3)?;
+                delay.sum_squares = field(fields, 4)?;
+            }
+            "pedestrian_close_calls" => self.stats.pedestrian_close_calls = field(fields, 1)?,
+            "zone_conflicts" => self.stats.zone_conflicts = field(fields, 1)?,
+            "conflicting_pair" => {
+                self.intersection.conflicting_pairs.insert((field(fields, 1)?, field(fields, 2)?));
+            }
+            "collisions" => {
+                self.stats.collisions = field(fields, 1)?;
+                self.stats.emergency_brakings = field(fields, 2)?;
+            }
+            "colliding_pair" => {
+                self.intersection.colliding_pairs.insert((field(fields, 1)?, field(fields, 2)?));
+            }
+            "emergency_braking" => {
+                self.intersection.emergency_braking.insert(field(fields, 1)?);
+            }
+            "fallback" => {
+                let fallback = &mut self.intersection.fallback;
+                fallback.time = field(fields, 1)?;
+                fallback.manual_outage = field(fields, 2)?;
+                fallback.manager_down = field(fields, 3)?;
+                fallback.silence = field(fields, 4)?;
+                fallback.active = field(fields, 5)?;
+                fallback.activations = field(fields, 6)?;
+                fallback.time_active = field(fields, 7)?;
+                fallback.rule.time = field(fields, 8)?;
+            }
+            "signal" => {
+                let mut signal = Signal::new(field(fields, 1)?, Vec::new());
+                signal.current = field(fields, 2)?;
+                signal.interval = field(fields, 3)?;
+                signal.elapsed = field(fields, 4)?;
+                signal.since_detection = field(fields, 5)?;
+                signal.phase_changes = field(fields, 6)?;
+                signal.gap_outs = field(fields, 7)?;
+                signal.max_outs = field(fields, 8)?;
+                self.intersection.signal = Some(signal);
+            }
+            "signal_phase" => {
+                let signal = self.intersection.signal.as_mut().ok_or("signal_phase before signal")?;
+                signal.phases.push(field(fields, 1)?);
+            }
+            "signal_going" => {
+                let agent = parse_agent(fields)?;
+                let signal = self.intersection.signal.as_mut().ok_or("signal_going before signal")?;
+                signal.going.insert(agent);
+            }
+            "outage" => self.intersection.fallback.outages.push(Outage {
+                start: field(fields, 1)?,
+                duration: field(fields, 2)?,
+            }),
+            "fallback_agent" => read_rule_agent(&mut self.intersection.fallback.rule, fields)?,
+            "all_way_stop" => {
+                let mut rule = AllWayStop::with_stop_signs();
+                rule.time = field(fields, 1)?;
+                self.intersection.all_way_stop = Some(rule);
+            }
+            "all_way_stop_agent" => {
+                let rule = self.intersection.all_way_stop.as_mut().ok_or("all_way_stop_agent before all_way_stop")?;
+                read_rule_agent(rule, fields)?;
+            }
+            "queues" => self.intersection.queues = QueueMonitor::new(field(fields, 1)?),
+            "queue" => {
+                let key = (field(fields, 1)?, field(fields, 2)?);
+                let queues = &mut self.intersection.queues;
+                queues.current.insert(key, Queue { vehicles: field(fields, 3)?, length: field(fields, 4)? });
+                queues.longest.insert(key, Queue { vehicles: field(fields, 5)?, length: field(fields, 6)? });
+            }
+            "queue_approach" => {
+                let direction = field(fields, 1)?;
+                let queues = &mut self.intersection.queues;
+                queues
+                    .longest_approach
+                    .insert(direction, Queue { vehicles: field(fields, 2)?, length: field(fields, 3)? });
+                if field(fields, 4)? {
+                    queues.spilled.insert(direction);
+                }
+                let spillbacks: u32 = field(fields, 5)?;
+                if spillbacks > 0 {
+                    queues.spillbacks.insert(direction, spillbacks);
+                    queues.spillback_time.insert(direction, field(fields, 6)?);
+                }
+            }
+            "detectors" => {
+                let mut detectors = Detectors::new(Vec::new(), field(fields, 1)?);
+                detectors.time = field(fields, 2)?;
+                detectors.interval_start = field(fields, 3)?;
+                self.intersection.detectors = Some(detectors);
+            }
+            "detector" => {
+                let detectors = self.intersection.detectors.as_mut().ok_or("detector before detectors")?;
+                let mut detector = LoopDetector::new(field(fields, 1)?, field(fields, 2)?, field(fields, 3)?, field(fields, 4)?);
+                detector.count = field(fields, 5)?;
+                detector.occupied_time = field(fields, 6)?;
+                detector.speed_sum = field(fields, 7)?;
+                detectors.loops.push(detector);
+            }
+            "detector_over" => {
+                let detectors = self.intersection.detectors.as_mut().ok_or("detector_over before detectors")?;
+                let index: usize = field(fields, 1)?;
+                let detector = detectors.loops.get_mut(index).ok_or("detector_over before its detector")?;
+                detector.over.insert(field(fields, 2)?);
+            }
+            "detector_record" => {
+                let detectors = self.intersection.detectors.as_mut().ok_or("detector_record before detectors")?;
+                detectors.records.push(DetectorRecord {
+                    start: field(fields, 1)?,
+                    end: field(fields, 2)?,
+                    detector: field(fields, 3)?,
+                    count: field(fields, 4)?,
+                    occupied_time: field(fields, 5)?,
+                    speed_sum: field(fields, 6)?,
+                });
+            }
+            "platooning" => self.intersection.platooning = field(fields, 1)?,
+            "auction" => self.stats.auction_log.push(parse_outcome(fields)?),
+            "manager" => self.intersection.manager.next_sequence = field(fields, 1)?,
+            "policy" => {
+                self.intersection.manager.policy = field(fields, 1)?;
+                self.intersection.manager.batch.collecting = field(fields, 2)?;
+            }
+            "negotiation" => {
+                let mut negotiation = Negotiation::new(field(fields, 1)?);
+                negotiation.time = field(fields, 2)?;
+                negotiation.messages = field(fields, 3)?;
+                self.intersection.negotiation = Some(negotiation);
+            }
+            "intention" => {
+                let agent = parse_agent(fields)?;
+                let negotiation = self.intersection.negotiation
+                    .as_mut()
+                    .ok_or("intention before negotiation")?;
+                negotiation.arrivals.insert(agent, field(fields, 3)?);
+                if field(fields, 4)? {
+                    negotiation.going.insert(agent);
+                }
+            }
+            "v2i" => {
+                let config = ChannelConfig {
+                    latency: field(fields, 1)?,
+                    jitter: field(fields, 2)?,
+                    drop_rate: field(fields, 3)?,
+                };
+                let mut link = V2iLink::new(config, 0);
+                link.rng = SimRng::new(field(fields, 4)?);
+                link.time = field(fields, 5)?;
+                link.listening = field(fields, 6)?;
+                link.sent = field(fields, 7)?;
+                link.dropped = field(fields, 8)?;
+                link.unauthorized_entries = field(fields, 9)?;
+                self.intersection.v2i = Some(link);
+            }
+            "v2i_vehicle" | "v2i_view" | "v2i_message" => {
+                let link = self.intersection.v2i
+                    .as_mut()
+                    .ok_or_else(|| format!("{} before v2i", fields[0]))?;
+                match fields[0] {
+                    "v2i_vehicle" => {
+                        let id: u32 = field(fields, 1)?;
+                        for (index, set) in [&mut link.requested, &mut link.granted, &mut link.entered]
+                            .into_iter()
+                            .enumerate()
+                        {
+                            if field(fields, index + 2)? {
+                                set.insert(id);
+                            }
+                        }
+                    }
+                    "v2i_view" => {
+                        let vehicle = parse_vehicle(&fields[2..])?;
+                        link.view.insert(vehicle.id, (vehicle, field(fields, 1)?));
+                    }
+                    _ => {
+                        let message = match fields.get(3) {
+                            Some(&"request") => Message::Request(parse_vehicle(&fields[4..])?),
+                            Some(&"update") => Message::PositionUpdate(parse_vehicle(&fields[4..])?),
+                            Some(&"cancel") => Message::Cancel(field(fields, 4)?),
+                            Some(&"grant") => Message::Grant(field(fields, 4)?),
+                            Some(&"reject") => Message::Reject(field(fields, 4)?),
+                            _ => return Err("invalid v2i message".to_string()),
+                        };
+                        let in_flight = InFlight { delivery: field(fields, 2)?, message };
+                        match fields[1] {
+                            "uplink" => link.uplink.push(in_flight),
+                            "downlink" => link.downlink.push(in_flight),
+                            _ => return Err("invalid v2i channel".to_string()),
+                        }
+                    }
+                }
+            }
+            "batch_member" => {
+                let agent = parse_agent(fields)?;
+                self.intersection.manager.batch.members.push(agent);
+            }
+            "request" => {
+                let agent = parse_agent(fields)?;
+                let claim = match fields.get(3) {
+                    Some(kind @ (&"movement" | &"cycling")) => {
+                        let route = Route::from_lane_index(field(fields, 5)?)
+                            .ok_or("invalid lane index")?;
+                        let movement = (field(fields, 4)?, route);
+                        if *kind == "cycling" {
+                            Claim::Cycling(movement)
+                        } else {
+                            Claim::Movement(movement)
+                        }
+                    }
+                    Some(&"crosswalk") => Claim::Crosswalk(field(fields, 4)?),
+                    _ => return Err("invalid request claim".to_string()),
+                };
+                self.intersection.manager.requests.push(Request {
+                    agent,
+                    claim,
+                    priority: field(fields, 6)?,
+                    sequence: field(fields, 7)?,
+                    granted: field(fields, 8)?,
+                    human: field(fields, 9)?,
+                    waited: field(fields, 10)?,
+                    losses: field(fields, 11)?,
+                    intruder: field(fields, 12)?,
+                    bid: None,
+                });
+            }
+            // The latest bid of the request written just before
+            "bid" => {
+                let request = self.intersection.manager.requests.last_mut().ok_or("bid without a request")?;
+                request.bid = Some(parse_outcome(fields)?);
+            }
+            "generator" => {
+                self.generator.next_vehicle_id = field(fields, 1)?;
+                self.generator.random_generation_enabled = field(fields, 2)?;
+                self.generator.last_random_spawn = match fields.get(3) {
+                    Some(&"-") => None,
+                    _ => Some(field(fields, 3)?),
+                };
+                self.generator.rng_state = field(fields, 4)?;
+                self.generator.next_pedestrian_id = field(fields, 5)?;
+                self.generator.next_pedestrian_spawn = match fields.get(6) {
+                    Some(&"-") => None,
+                    _ => Some(field(fields, 6)?),
+                };
+            }
+            "pedestrian" => {
+                let mut pedestrian = Pedestrian::new(
+                    field(fields, 1)?,
+                    field(fields, 2)?,
+                    field(fields, 4)?,
+                    field(fields, 5)?,
+                );
+                pedestrian.position = field(fields, 3)?;
+                pedestrian.waiting_time = field(fields, 6)?;
+                pedestrian.crossing = field(fields, 7)?;
+                pedestrian.active = field(fields, 8)?;
+                pedestrian.close_call = field(fields, 9)?;
+                self.intersection.pedestrians.push(pedestrian);
+            }
+            "vehicle" => {
+                let vehicle = parse_vehicle(fields)?;
+                self.intersection.lanes
+                    .entry(vehicle.direction)
+                    .or_default()
+                    .push(vehicle);
+            }
+            other => return Err(format!("unknown record: {}", other)),
+        }
+        Ok(())
+    }
+
+    /// Writes the snapshot to the file at `path`
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    /// Reads a snapshot from the file at `path`
+    pub fn load(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::read(BufReader::new(file))
+    }
+}
+
+/// Parses field `index` of a record
+fn field<T: std::str::FromStr>(fields: &[&str], index: usize) -> Result<T, String> {
+    let value = fields.get(index).ok_or_else(|| format!("missing field {}", index))?;
+    value.parse().map_err(|_| format!("invalid value: {}", value))
+}
+
+/// Formats a vehicle as a `vehicle ...` record
+fn format_vehicle(vehicle: &Vehicle) -> String {
+    let driver = match vehicle.driver {
+        Some(driver) => format!("{} {}", driver.gap_timer, driver.decided),
+        None => "-".to_string(),
+    };
+    let rogue = match vehicle.rogue {
+        Some(rogue) => format!("{} {}", rogue.kind, rogue.stopped_for),
+        None => "- 0".to_string(),
+    };
+    let behaviour = &vehicle.behaviour;
+    format!(
+        "vehicle {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+        vehicle.id,
+        vehicle.position.0,
+        vehicle.position.1,
+        vehicle.velocity,
+        vehicle.route.lane_index(),
+        vehicle.direction,
+        vehicle.distance_to_intersection,
+        vehicle.time_elapsed,
+        vehicle.active,
+        vehicle.vehicle_type,
+        vehicle.cruise_velocity,
+        vehicle.acceleration,
+        vehicle.priority,
+        vehicle.distance_travelled,
+        behaviour.reaction_time,
+        behaviour.headway,
+        behaviour.critical_gap,
+        behaviour.politeness,
+        behaviour.urgency,
+        behaviour.budget,
+        vehicle.platoon.map_or("-".to_string(), |id| id.to_string()),
+        rogue,
+        driver,
+    )
+}
+
+/// Parses a vehicle written by `format_vehicle`, starting at `fields[0]`
+fn parse_vehicle(fields: &[&str]) -> Result<Vehicle, String> {
+    let route = Route::from_lane_index(field(fields, 5)?)
+        .ok_or("invalid lane index")?;
+    let mut vehicle = Vehicle::new(
+        field(fields, 1)?,
+        (field(fields, 2)?, field(fields, 3)?),
+        field(fields, 4)?,
+        route,
+        field(fields, 6)?,
+        field(fields, 7)?,
+    );
+    vehicle.time_elapsed = field(fields, 8)?;
+    vehicle.active = field(fields, 9)?;
+    vehicle.vehicle_type = field(fields, 10)?;
+    vehicle.cruise_velocity = field(fields, 11)?;
+    vehicle.acceleration = field(fields, 12)?;
+    vehicle.priority = field(fields, 13)?;
+    vehicle.distance_travelled = field(fields, 14)?;
+    vehicle.behaviour.reaction_time = field(fields, 15)?;
+    vehicle.behaviour.headway = field(fields, 16)?;
+    vehicle.behaviour.critical_gap = field(fields, 17)?;
+    vehicle.behaviour.politeness = field(fields, 18)?;
+    vehicle.behaviour.urgency = field(fields, 19)?;
+    vehicle.behaviour.budget = field(fields, 20)?;
+    vehicle.platoon = match fields.get(21) {
+        Some(&"-") => None,
+        _ => Some(field(fields, 21)?),
+    };
+    if fields.get(22) != Some(&"-") {
+        vehicle.rogue = Some(Rogue {
+            kind: field(fields, 22)?,
+            stopped_for: field(fields, 23)?,
+        });
+    }
+    if fields.get(24) != Some(&"-") {
+        vehicle.driver = Some(HumanDriver {
+            gap_timer: field(fields, 24)?,
+            decided: field(fields, 25)?,
+        });
+    }
+    Ok(vehicle)
+}
+
+/// Formats an auction bid as a `<key> ...` record
+fn format_outcome(key: &str, outcome: &AuctionOutcome) -> String {
+    format!(
+        "{} {} {} {} {} {} {} {} {}",
+        key,
+        outcome.id,
+        outcome.approach,
+        outcome.urgency,
+        outcome.budget,
+        outcome.bid,
+        outcome.won,
+        outcome.losses,
+        outcome.waited,
+    )
+}
+
+/// Parses an auction bid written by `format_outcome`, starting at `fields[0]`
+fn parse_outcome(fields: &[&str]) -> Result<AuctionOutcome, String> {
+    Ok(AuctionOutcome {
+        id: field(fields, 1)?,
+        approach: field(fields, 2)?,
+        urgency: field(fields, 3)?,
+        budget: field(fields, 4)?,
+        bid: field(fields, 5)?,
+        won: field(fields, 6)?,
+        losses: field(fields, 7)?,
+        waited: field(fields, 8)?,
+    })
+}
+
+/// Formats an agent as `vehicle <id>` or `pedestrian <id>`
+fn format_agent(agent: Agent) -> String {
+    match agent {
+        Agent::Vehicle(id) => format!("vehicle {}", id),
+        Agent::Pedestrian(id) => format!("pedestrian {}", id),
+    }
+}
+
+/// Writes the agents an all-way-stop rule knows as `<key> <agent> <arrival> <going>` records
+fn write_rule_agents<W: Write>(writer: &mut W, key: &str, rule: &AllWayStop) -> io::Result<()> {
+    let mut agents: Vec<&Agent> = rule.arrivals.keys().chain(&rule.going).collect();
+    agents.sort_by_key(|agent| format_agent(**agent));
+    agents.dedup();
+    for agent in agents {
+        writeln!(
+            writer,
+            "{} {} {} {}",
+            key,
+            format_agent(*agent),
+            rule.arrivals.get(agent).map_or("-".to_string(), |arrival| arrival.to_string()),
+            rule.going.contains(agent),
+        )?;
+    }
+    Ok(())
+}
+
+/// Applies a record written by `write_rule_agents` to an all-way-stop rule
+fn read_rule_agent(rule: &mut AllWayStop, fields: &[&str]) -> Result<(), String> {
+    let agent = parse_agent(fields)?;
+    if fields.get(3) != Some(&"-") {
+        rule.arrivals.insert(agent, field(fields, 3)?);
+    }
+    if field(fields, 4)? {
+        rule.going.insert(agent);
+    }
+    Ok(())
+}
+
+/// Parses an agent written by `format_agent` from fields 1 and 2 of a record
+fn parse_agent(fields: &[&str]) -> Result<Agent, String> {
+    let id = fields
+        .get(2)
+        .and_then(|id| id.parse().ok())
+        .ok_or("invalid agent id")?;
+    match fields.get(1) {
+        Some(&"vehicle") => Ok(Agent::Vehicle(id)),
+        Some(&"pedestrian") => Ok(Agent::Pedestrian(id)),
+        _ => Err("invalid agent".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use sdl2::keyboard::Keycode;
+    use crate::simulation::signal::parse_phases;
+
+    fn run(simulation: &mut Simulation, input_handler: &mut InputHandler, ticks: usize) {
+        for _ in 0..ticks {
+            if let Some(vehicle) = input_handler.update_random_generation(800, simulation.time) {
+                simulation.add_vehicle(vehicle);
+            }
+            if let Some(pedestrian) = input_handler.update_pedestrian_generation(simulation.time) {
+                simulation.add_pedestrian(pedestrian);
+            }
+            simulation.update(0.016);
+        }
+    }
+
+    fn positions(simulation: &Simulation) -> Vec<(u32, (f32, f32))> {
+        let mut positions: Vec<_> = simulation.intersection.lanes
+            .values()
+            .flat_map(|lane| lane.iter())
+            .map(|vehicle| (vehicle.id, vehicle.position))
+            .collect();
+        positions.sort_by_key(|(id, _)| *id);
+        positions
+    }
+
+    fn new_run() -> (Simulation, InputHandler) {
+        let mut simulation = Simulation::new(25.0, Rc::new(RefCell::new(StatisticsManager::new())));
+        simulation.intersection.manager.policy = "auction".parse().unwrap();
+        let mut input_handler = InputHandler::new(500, 100.0);
+        input_handler.set_seed(11);
+        input_handler.handle_keypress(Keycode::R);
+        input_handler.pedestrian_rate = 20.0;
+        input_handler.human_share = 0.3;
+        input_handler.behaviour = "headway=uniform:0:1,urgency=uniform:0:1".parse().unwrap();
+        (simulation, input_handler)
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let (mut simulation, mut input_handler) = new_run();
+        run(&mut simulation, &mut input_handler, 300);
+
+        let snapshot = Snapshot::capture(&simulation, &input_handler);
+        let mut buffer = Vec::new();
+        snapshot.write(&mut buffer).unwrap();
+        let loaded = Snapshot::read(buffer.as_slice()).unwrap();
+
+        let mut rewritten = Vec::new();
+        loaded.write(&mut rewritten).unwrap();
+        assert_eq!(buffer, rewritten);
+        assert_eq!(loaded.generator, snapshot.generator);
+    }
+
+    #[test]
+    fn test_resumed_run_matches_original() {
+        let (mut simulation, mut input_handler) = new_run();
+        run(&mut simulation, &mut input_handler, 300);
+
+        let mut buffer = Vec::new();
+        Snapshot::capture(&simulation, &input_handler).write(&mut buffer).unwrap();
+
+        run(&mut simulation, &mut input_handler, 300);
+
+        let (mut resumed, mut resumed_input) = new_run();
+        Snapshot::read(buffer.as_slice()).unwrap().restore(&mut resumed, &mut resumed_input);
+        run(&mut resumed, &mut resumed_input, 300);
+
+        assert_eq!(resumed.time, simulation.time);
+        assert_eq!(resumed.total_vehicles, simulation.total_vehicles);
+        assert_eq!(positions(&resumed), positions(&simulation));
+        assert_eq!(resumed.intersection.queues, simulation.intersection.queues);
+    }
+
+    #[test]
+    fn test_resumed_v2v_run_matches_original() {
+        let (mut simulation, mut input_handler) = new_run();
+        simulation.intersection.negotiation = Some(Negotiation::new(60.0));
+        run(&mut simulation, &mut input_handler, 300);
+
+        let mut buffer = Vec::new();
+        Snapshot::capture(&simulation, &input_handler).write(&mut buffer).unwrap();
+        run(&mut simulation, &mut input_handler, 300);
+
+        let (mut resumed, mut resumed_input) = new_run();
+        Snapshot::read(buffer.as_slice()).unwrap().restore(&mut resumed, &mut resumed_input);
+        assert!(resumed.intersection.negotiation.is_some());
+        run(&mut resumed, &mut resumed_input, 300);
+        assert_eq!(positions(&resumed), positions(&simulation));
+    }
+
+    #[test]
+    fn test_resumed_v2i_run_matches_original() {
+        let (mut simulation, mut input_handler) = new_run();
+        let config = "0.2:0.1:0.05".parse().unwrap();
+        simulation.intersection.v2i = Some(V2iLink::new(config, 3));
+        run(&mut simulation, &mut input_handler, 300);
+
+        let mut buffer = Vec::new();
+        Snapshot::capture(&simulation, &input_handler).write(&mut buffer).unwrap();
+        run(&mut simulation, &mut input_handler, 300);
+
+        let (mut resumed, mut resumed_input) = new_run();
+        Snapshot::read(buffer.as_slice()).unwrap().restore(&mut resumed, &mut resumed_input);
+        run(&mut resumed, &mut resumed_input, 300);
+        assert_eq!(positions(&resumed), positions(&simulation));
+        let (link, resumed_link) = (simulation.intersection.v2i.unwrap(), resumed.intersection.v2i.unwrap());
+        assert_eq!(resumed_link.sent, link.sent);
+        assert_eq!(resumed_link.dropped, link.dropped);
+    }
+
+    #[test]
+    fn test_resumed_run_during_manager_outage_matches_original() {
+        let (mut simulation, mut input_handler) = new_run();
+        simulation.intersection.fallback.outages = vec!["2:10".parse().unwrap()];
+        run(&mut simulation, &mut input_handler, 500);
+        assert!(simulation.intersection.fallback.active);
+
+        let mut buffer = Vec::new();
+        Snapshot::capture(&simulation, &input_handler).write(&mut buffer).unwrap();
+        run(&mut simulation, &mut input_handler, 600);
+
+        let (mut resumed, mut resumed_input) = new_run();
+        Snapshot::read(buffer.as_slice()).unwrap().restore(&mut resumed, &mut resumed_input);
+        run(&mut resumed, &mut resumed_input, 600);
+        assert_eq!(positions(&resumed), positions(&simulation));
+        assert_eq!(resumed.intersection.fallback, simulation.intersection.fallback);
+    }
+
+    #[test]
+    fn test_resumed_run_with_rogue_vehicles_matches_original() {
+        let (mut simulation, mut input_handler) = new_run();
+        input_handler.rogue_share = 0.5;
+        run(&mut simulation, &mut input_handler, 500);
+        assert!(simulation.intersection.lanes.values().flatten().any(|vehicle| vehicle.rogue.is_some()));
+
+        let mut buffer = Vec::new();
+        Snapshot::capture(&simulation, &input_handler).write(&mut buffer).unwrap();
+        run(&mut simulation, &mut input_handler, 600);
+
+        let (mut resumed, mut resumed_input) = new_run();
+        resumed_input.rogue_share = 0.5;
+        Snapshot::read(buffer.as_slice()).unwrap().restore(&mut resumed, &mut resumed_input);
+        run(&mut resumed, &mut resumed_input, 600);
+        assert_eq!(positions(&resumed), positions(&simulation));
+        let stats = Snapshot::capture(&simulation, &input_handler).stats;
+        let resumed_stats = Snapshot::capture(&resumed, &resumed_input).stats;
+        assert_eq!(resumed_stats.collisions, stats.collisions);
+        assert_eq!(resumed_stats.emergency_brakings, stats.emergency_brakings);
+    }
+
+    #[test]
+    fn test_resumed_signalized_run_matches_original() {
+        let (mut simulation, mut input_handler) = new_run();
+        let phases = parse_phases("NS/SR:12,NS/L,EW/SR,EW/L").unwrap();
+        simulation.intersection.signal = Some(Signal::new("actuated".parse().unwrap(), phases));
+        run(&mut simulation, &mut input_handler, 1200);
+
+        let mut buffer = Vec::new();
+        Snapshot::capture(&simulation, &input_handler).write(&mut buffer).unwrap();
+        run(&mut simulation, &mut input_handler, 600);
+
+        let (mut resumed, mut resumed_input) = new_run();
+        Snapshot::read(buffer.as_slice()).unwrap().restore(&mut resumed, &mut resumed_input);
+        run(&mut resumed, &mut resumed_input, 600);
+        assert_eq!(positions(&resumed), positions(&simulation));
+        assert_eq!(resumed.intersection.signal, simulation.intersection.signal);
+    }
+
+    #[test]
+    fn test_resumed_all_way_stop_run_matches_original() {
+        let (mut simulation, mut input_handler) = new_run();
+        simulation.intersection.all_way_stop = Some(AllWayStop::with_stop_signs());
+        run(&mut simulation, &mut input_handler, 900);
+
+        let mut buffer = Vec::new();
+        Snapshot::capture(&simulation, &input_handler).write(&mut buffer).unwrap();
+        run(&mut simulation, &mut input_handler, 600);
+
+        let (mut resumed, mut resumed_input) = new_run();
+        Snapshot::read(buffer.as_slice()).unwrap().restore(&mut resumed, &mut resumed_input);
+        run(&mut resumed, &mut resumed_input, 600);
+        assert_eq!(positions(&resumed), positions(&simulation));
+        assert_eq!(resumed.intersection.all_way_stop, simulation.intersection.all_way_stop);
+    }
+
+    #[test]
+    fn test_resumed_run_with_detectors_matches_original() {
+        let (mut simulation, mut input_handler) = new_run();
+        let loops = vec!["North_1@40".parse().unwrap(), "East_0@20:4".parse().unwrap()];
+        simulation.intersection.detectors = Some(Detectors::new(loops, 30.0));
+        run(&mut simulation, &mut input_handler, 1000);
+
+        let mut buffer = Vec::new();
+        Snapshot::capture(&simulation, &input_handler).write(&mut buffer).unwrap();
+        run(&mut simulation, &mut input_handler, 600);
+
+        let (mut resumed, mut resumed_input) = new_run();
+        Snapshot::read(buffer.as_slice()).unwrap().restore(&mut resumed, &mut resumed_input);
+        run(&mut resumed, &mut resumed_input, 600);
+        assert_eq!(positions(&resumed), positions(&simulation));
+        assert_eq!(resumed.intersection.detectors, simulation.intersection.detectors);
+    }
+
+    #[test]
+    fn test_rejects_unknown_files() {
+        assert!(Snapshot::read("not a snapshot\n".as_bytes()).is_err());
+
+        let bad = format!("{}\nwarp 9\n", SNAPSHOT_HEADER);
+        assert!(Snapshot::read(bad.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,336 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use crate::simulation::vehicle::BIKE_LANE;
+use crate::simulation::{Direction, Intersection, Route, VehicleState, VehicleType};
+use super::fcd;
+
+/// Column header of the trajectory CSV file
+const CSV_HEADER: &str = "time,id,x,y,heading,speed,acceleration,lane,state,type";
+
+/// Simulation time between two samples unless told otherwise (in seconds)
+pub const DEFAULT_SAMPLE_INTERVAL: f32 = 0.1;
+
+/// State of a single vehicle at one sampled instant
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrajectorySample {
+    pub time: f32,                  // Simulation time of the sample (in seconds)
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    pub heading: f32,               // Compass heading in degrees
+    pub speed: f32,                 // in m/s
+    pub acceleration: f32,          // in m/s²
+    pub direction: Direction,
+    pub lane: usize,                // Lane index on the approach, `BIKE_LANE` for bicycles
+    pub state: VehicleState,
+    pub vehicle_type: VehicleType,
+}
+
+impl TrajectorySample {
+    /// Route served by the sample's lane; bicycles in the bike lane are
+    /// taken to ride straight on, as the trajectory does not tell
+    pub fn route(&self) -> Route {
+        Route::from_lane_index(self.lane).unwrap_or(Route::Straight)
+    }
+
+    /// Formats the sample as one CSV row (without trailing newline)
+    fn to_csv_row(&self) -> String {
+        format!(
+            "{:.3},{},{:.3},{:.3},{:.1},{:.3},{:.3},{}_{},{},{}",
+            self.time,
+            self.id,
+            self.x,
+            self.y,
+            self.heading,
+            self.speed,
+            self.acceleration,
+            self.direction,
+            self.lane,
+            self.state,
+            self.vehicle_type,
+        )
+    }
+
+    /// Parses one CSV row written by `to_csv_row`
+    fn from_csv_row(row: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = row.trim().split(',').collect();
+        if fields.len() != 10 {
+            return Err(format!("Expected 10 columns, found {}: {}", fields.len(), row));
+        }
+
+        let number = |value: &str| value.parse::<f32>().map_err(|e| format!("{}: {}", value, e));
+
+        let (direction, lane) = fields[7]
+            .split_once('_')
+            .ok_or_else(|| format!("Invalid lane: {}", fields[7]))?;
+        let lane = lane
+            .parse::<usize>()
+            .ok()
+            .filter(|lane| *lane <= BIKE_LANE)
+            .ok_or_else(|| format!("Invalid lane: {}", fields[7]))?;
+
+        Ok(TrajectorySample {
+            time: number(fields[0])?,
+            id: fields[1].parse().map_err(|e| format!("{}: {}", fields[1], e))?,
+            x: number(fields[2])?,
+            y: number(fields[3])?,
+            heading: number(fields[4])?,
+            speed: number(fields[5])?,
+            acceleration: number(fields[6])?,
+            direction: direction.parse()?,
+            lane,
+            state: fields[8].parse()?,
+            vehicle_type: fields[9].parse()?,
+        })
+    }
+}
+
+/// Records the state of every vehicle in the intersection at a fixed sample rate
+///
+/// Samples are kept in memory for the whole run and written out once at the end.
+pub struct TrajectoryRecorder {
+    /// Simulation time between two samples (in seconds), 0 samples every tick
+    sample_interval: f32,
+
+    /// Simulation time of the most recent sample
+    last_sample_time: Option<f32>,
+
+    samples: Vec<TrajectorySample>,
+}
+
+impl TrajectoryRecorder {
+    pub fn new(sample_interval: f32) -> Self {
+        TrajectoryRecorder {
+            sample_interval: sample_interval.max(0.0),
+            last_sample_time: None,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Samples all vehicles in the intersection if a sample is due at `time`
+    ///
+    /// If time jumps backwards (e.g. after loading a snapshot) sampling
+    /// restarts immediately from the new time.
+    pub fn sample(&mut self, time: f32, intersection: &Intersection) {
+        if let Some(last) = self.last_sample_time {
+            if time >= last && time < last + self.sample_interval {
+                return;
+            }
+        }
+        self.last_sample_time = Some(time);
+
+        let mut tick: Vec<TrajectorySample> = intersection.lanes
+            .values()
+            .flat_map(|lane| lane.iter())
+            .filter(|vehicle| vehicle.active)
+            .map(|vehicle| TrajectorySample {
+                time,
+                id: vehicle.id,
+                x: vehicle.position.0,
+                y: vehicle.position.1,
+                heading: vehicle.direction.heading(),
+                speed: vehicle.velocity,
+                acceleration: vehicle.acceleration,
+                direction: vehicle.direction,
+                lane: vehicle.lane_index(),
+                state: vehicle.state(),
+                vehicle_type: vehicle.vehicle_type,
+            })
+            .collect();
+        tick.sort_by_key(|sample| sample.id);
+
+        self.samples.extend(tick);
+    }
+
+    /// Drops the samples taken after `time`, e.g. when the run continues
+    /// from a rewound state
+    pub fn truncate(&mut self, time: f32) {
+        self.samples.retain(|sample| sample.time <= time);
+        self.last_sample_time = self.samples.last().map(|sample| sample.time);
+    }
+
+    /// Forgets all samples, e.g. after loading an unrelated state
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.last_sample_time = None;
+    }
+
+    /// All samples recorded so far, ordered by time and then by vehicle id
+    pub fn samples(&self) -> &[TrajectorySample] {
+        &self.samples
+    }
+
+    /// Writes all samples as CSV to the given writer
+    pub fn write_csv<W: Write>(&self, writer: W) -> io::Result<()> {
+        write_csv(writer, &self.samples)
+    }
+
+    /// Writes all samples as CSV to the file at `path`
+    pub fn save_csv(&self, path: &str) -> io::Result<()> {
+        self.write_csv(BufWriter::new(File::create(path)?))
+    }
+
+    /// Writes all samples as SUMO FCD XML to the file at `path`
+    pub fn save_fcd(&self, path: &str) -> io::Result<()> {
+        fcd::write_fcd(BufWriter::new(File::create(path)?), &self.samples)
+    }
+}
+
+/// Writes trajectory samples as CSV, one row per vehicle and sample
+pub fn write_csv<W: Write>(mut writer: W, samples: &[TrajectorySample]) -> io::Result<()> {
+    writeln!(writer, "{}", CSV_HEADER)?;
+    for sample in samples {
+        writeln!(writer, "{}", sample.to_csv_row())?;
+    }
+    writer.flush()
+}
+
+/// Reads trajectory samples from CSV written by `write_csv`
+pub fn read_csv<R: BufRead>(reader: R) -> Result<Vec<TrajectorySample>, String> {
+    let mut samples = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if index == 0 && line.trim() == CSV_HEADER {
+            continue;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let sample = TrajectorySample::from_csv_row(&line)
+            .map_err(|e| format!("line {}: {}", index + 1, e))?;
+        samples.push(sample);
+    }
+    Ok(samples)
+}
+
+/// Reads trajectory samples from the CSV file at `path`
+pub fn load_csv(path: &str) -> Result<Vec<TrajectorySample>, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+    read_csv(BufReader::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Vehicle;
+
+    fn intersection_with(vehicles: Vec<Vehicle>) -> Intersection {
+        let mut intersection = Intersection::new(10.0);
+        for vehicle in vehicles {
+            intersection.lanes.get_mut(&vehicle.direction).unwrap().push(vehicle);
+        }
+        intersection
+    }
+
+    #[test]
+    fn test_sample_rate() {
+        let mut intersection = intersection_with(vec![
+            Vehicle::new(1, (0.0, -100.0), 10.0, Route::Straight, Direction::North, 100.0),
+        ]);
+        let mut recorder = TrajectoryRecorder::new(0.5);
+
+        // Sample every 0.25 s for 1 s, only every second tick is recorded
+        for tick in 0..5 {
+            recorder.sample(tick as f32 * 0.25, &intersection);
+            intersection.lanes.get_mut(&Direction::North).unwrap()[0].update_position(0.25);
+        }
+
+        let times: Vec<f32> = recorder.samples().iter().map(|s| s.time).collect();
+        assert_eq!(times, vec![0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn test_truncate_continues_from_rewound_time() {
+        let intersection = intersection_with(vec![
+            Vehicle::new(1, (0.0, -100.0), 10.0, Route::Straight, Direction::North, 100.0),
+        ]);
+        let mut recorder = TrajectoryRecorder::new(0.5);
+        for tick in 0..5 {
+            recorder.sample(tick as f32 * 0.5, &intersection);
+        }
+
+        // Rewound to 1.2 s, the run goes on from there
+        recorder.truncate(1.2);
+        for tick in 0..4 {
+            recorder.sample(1.2 + tick as f32 * 0.25, &intersection);
+        }
+        let times: Vec<f32> = recorder.samples().iter().map(|s| s.time).collect();
+        assert_eq!(times, vec![0.0, 0.5, 1.0, 1.7]);
+
+        recorder.clear();
+        assert!(recorder.samples().is_empty());
+        recorder.sample(30.0, &intersection);
+        assert_eq!(recorder.samples()[0].time, 30.0);
+    }
+
+    #[test]
+    fn test_records_vehicle_acceleration() {
+        let mut intersection = intersection_with(vec![
+            Vehicle::new(1, (0.0, -100.0), 10.0, Route::Straight, Direction::North, 100.0),
+        ]);
+        let mut recorder = TrajectoryRecorder::new(0.0);
+
+        recorder.sample(0.0, &intersection);
+        intersection.lanes.get_mut(&Direction::North).unwrap()[0].accelerate_towards(15.0, 0.5);
+        recorder.sample(0.5, &intersection);
+
+        assert_eq!(recorder.samples()[0].acceleration, 0.0);
+        assert_eq!(recorder.samples()[1].acceleration, VehicleType::Car.spec().max_acceleration);
+    }
+
+    #[test]
+    fn test_samples_sorted_by_id() {
+        let intersection = intersection_with(vec![
+            Vehicle::new(3, (100.0, 0.0), 10.0, Route::Left, Direction::West, 100.0),
+            Vehicle::new(1, (0.0, -100.0), 10.0, Route::Right, Direction::North, 100.0),
+            Vehicle::new(2, (-100.0, 0.0), 10.0, Route::Straight, Direction::East, 100.0),
+        ]);
+        let mut recorder = TrajectoryRecorder::new(0.1);
+        recorder.sample(0.0, &intersection);
+
+        let ids: Vec<u32> = recorder.samples().iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let intersection = intersection_with(vec![
+            Vehicle::new(7, (-100.0, 0.0), 5.0, Route::Left, Direction::East, 100.0)
+                .with_type(VehicleType::Bus),
+        ]);
+        let mut recorder = TrajectoryRecorder::new(0.1);
+        recorder.sample(0.0, &intersection);
+
+        let mut buffer = Vec::new();
+        recorder.write_csv(&mut buffer).unwrap();
+
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert!(text.starts_with(CSV_HEADER));
+        assert!(text.contains("7,-100.000,0.000,90.0,5.000,0.000,East_2,Approaching,bus"));
+
+        let samples = read_csv(buffer.as_slice()).unwrap();
+        assert_eq!(samples, recorder.samples());
+    }
+
+    #[test]
+    fn test_bicycles_recorded_in_bike_lane() {
+        let intersection = intersection_with(vec![
+            Vehicle::new(4, (0.0, -100.0), 5.0, Route::Straight, Direction::North, 100.0)
+                .with_type(VehicleType::Bicycle),
+        ]);
+        let mut recorder = TrajectoryRecorder::new(0.1);
+        recorder.sample(0.0, &intersection);
+        assert_eq!(recorder.samples()[0].lane, BIKE_LANE);
+
+        let mut buffer = Vec::new();
+        recorder.write_csv(&mut buffer).unwrap();
+        assert!(String::from_utf8(buffer.clone()).unwrap().contains(",North_3,Approaching,bicycle"));
+        assert_eq!(read_csv(buffer.as_slice()).unwrap(), recorder.samples());
+    }
+
+    #[test]
+    fn test_read_csv_rejects_bad_rows() {
+        let csv = format!("{}\n0.0,1,0,0,0,0,0,North_9,Approaching,car\n", CSV_HEADER);
+        assert!(read_csv(csv.as_bytes()).is_err());
+    }
+}
@@ -1,30 +1,19 @@
 use sdl2::rect::Rect;
 use sdl2::render::Canvas;
 use sdl2::video::Window;
-use crate::simulation::vehicle::{Vehicle, Direction, Route};
+use crate::simulation::vehicle::{Vehicle, Direction, Route, BIKE_LANE};
+use crate::simulation::manager::STOP_LINE_DISTANCE;
+use crate::simulation::pedestrian::CROSSWALK_WIDTH;
+use crate::simulation::physics::{lane_position, lanes};
+use crate::simulation::queue::{QueueMonitor, APPROACHES};
+use crate::simulation::signal::{Aspect, Signal};
+use crate::simulation::{Pedestrian, VehicleType};
 use super::assets::AssetManager;
 
-/// Lane configuration constants
-pub mod lanes {
-    /// Width of each lane in meters
-    pub const LANE_WIDTH: f32 = 3.5;
-
-    /// Number of lanes in each direction
-    pub const LANES_PER_DIRECTION: i32 = 3;
-
-    /// Total road width on each side (3 lanes * 3.5m)
-    pub const ROAD_WIDTH: f32 = LANE_WIDTH * 3.0;
-
-    /// Intersection size (center area)
-    pub const INTERSECTION_SIZE: f32 = ROAD_WIDTH * 2.0;
-    
-    /// Width of the median separator in meters
-    pub const SEPARATOR_WIDTH: f32 = 0.5;
-}
-
 /// Handles animation and rendering of vehicles
 pub struct AnimationManager {
-    /// Size of vehicle sprite in meters
+    /// Size of the (square) car sprite in meters; other vehicle types are
+    /// stretched from it in proportion to their length and width
     vehicle_size: f32,
 }
 
@@ -48,6 +37,176 @@ impl AnimationManager {
         Ok(())
     }
 
+    /// Renders pedestrians as small dots, red when waiting at the curb
+    pub fn render_pedestrians(
+        &self,
+        canvas: &mut Canvas<Window>,
+        pedestrians: &[Pedestrian],
+        assets: &AssetManager,
+    ) -> Result<(), String> {
+        use sdl2::pixels::Color;
+
+        let size = (0.8 * assets.scale) as u32;
+        for pedestrian in pedestrians.iter().filter(|pedestrian| pedestrian.active) {
+            let color = if pedestrian.crossing {
+                Color::RGB(255, 220, 0)
+            } else {
+                Color::RGB(220, 30, 30)
+            };
+            canvas.set_draw_color(color);
+
+            let (world_x, world_y) = pedestrian.world_position();
+            let (screen_x, screen_y) = assets.world_to_screen(world_x, world_y);
+            canvas.fill_rect(Rect::new(
+                screen_x - (size / 2) as i32,
+                screen_y - (size / 2) as i32,
+                size,
+                size,
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Renders the signal head of every lane as a bar across the lane at
+    /// its stop line, in the colour the movement is shown
+    pub fn render_signals(
+        &self,
+        canvas: &mut Canvas<Window>,
+        signal: &Signal,
+        assets: &AssetManager,
+    ) -> Result<(), String> {
+        use sdl2::pixels::Color;
+
+        let across = (lanes::LANE_WIDTH * 0.8 * assets.scale) as u32;
+        let depth = (0.8 * assets.scale) as u32;
+        for direction in [Direction::North, Direction::South, Direction::East, Direction::West] {
+            for route in [Route::Right, Route::Straight, Route::Left] {
+                let color = match signal.aspect((direction, route)) {
+                    Aspect::Green => Color::RGB(40, 200, 60),
+                    Aspect::Amber => Color::RGB(255, 180, 0),
+                    Aspect::Red => Color::RGB(220, 30, 30),
+                };
+                canvas.set_draw_color(color);
+
+                let offset = lanes::lane_offset(route);
+                let (world_x, world_y) = match direction {
+                    Direction::North => (offset, -STOP_LINE_DISTANCE),
+                    Direction::South => (-offset, STOP_LINE_DISTANCE),
+                    Direction::East => (-STOP_LINE_DISTANCE, -offset),
+                    Direction::West => (STOP_LINE_DISTANCE, offset),
+                };
+                let (width, height) = match direction {
+                    Direction::North | Direction::South => (across, depth),
+                    Direction::East | Direction::West => (depth, across),
+                };
+                let (screen_x, screen_y) = assets.world_to_screen(world_x, world_y);
+                canvas.fill_rect(Rect::new(
+                    screen_x - (width / 2) as i32,
+                    screen_y - (height / 2) as i32,
+                    width,
+                    height,
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders a white stop line across every approach and a stop sign on
+    /// the curb next to it
+    pub fn render_stop_signs(
+        &self,
+        canvas: &mut Canvas<Window>,
+        assets: &AssetManager,
+    ) -> Result<(), String> {
+        use sdl2::pixels::Color;
+
+        let road = (lanes::ROAD_WIDTH * assets.scale) as u32;
+        let line = (0.5 * assets.scale).max(2.0) as u32;
+        let sign = (1.6 * assets.scale) as u32;
+        // Centre of the approach lanes and of the curb beyond them, across the road
+        let lanes_centre = lanes::ROAD_WIDTH / 2.0;
+        let curb = lanes::ROAD_WIDTH + 1.5;
+        for direction in [Direction::North, Direction::South, Direction::East, Direction::West] {
+            let place = |across: f32| match direction {
+                Direction::North => (across, -STOP_LINE_DISTANCE),
+                Direction::South => (-across, STOP_LINE_DISTANCE),
+                Direction::East => (-STOP_LINE_DISTANCE, -across),
+                Direction::West => (STOP_LINE_DISTANCE, across),
+            };
+            let (width, height) = match direction {
+                Direction::North | Direction::South => (road, line),
+                Direction::East | Direction::West => (line, road),
+            };
+
+            canvas.set_draw_color(Color::RGB(255, 255, 255));
+            let (x, y) = place(lanes_centre);
+            let (x, y) = assets.world_to_screen(x, y);
+            canvas.fill_rect(Rect::new(x - (width / 2) as i32, y - (height / 2) as i32, width, height))?;
+
+            let (x, y) = place(curb);
+            let (x, y) = assets.world_to_screen(x, y);
+            let plate = Rect::new(x - (sign / 2) as i32, y - (sign / 2) as i32, sign, sign);
+            canvas.set_draw_color(Color::RGB(200, 20, 20));
+            canvas.fill_rect(plate)?;
+            canvas.set_draw_color(Color::RGB(255, 255, 255));
+            canvas.draw_rect(plate)?;
+        }
+        Ok(())
+    }
+
+    /// Renders the queue on every lane as a thin bar along its inner edge,
+    /// from the stop line to the back of the queue, red on approaches that
+    /// spilled back to the spawn point
+    pub fn render_queues(
+        &self,
+        canvas: &mut Canvas<Window>,
+        queues: &QueueMonitor,
+        assets: &AssetManager,
+    ) -> Result<(), String> {
+        use sdl2::pixels::Color;
+
+        let thickness = (0.5 * assets.scale).max(2.0) as u32;
+        for direction in APPROACHES {
+            let color = if queues.spilled.contains(&direction) {
+                Color::RGB(220, 30, 30)
+            } else {
+                Color::RGB(255, 140, 0)
+            };
+            canvas.set_draw_color(color);
+            for lane in 0..=BIKE_LANE {
+                let queue = queues.lane(direction, lane);
+                if queue.vehicles == 0 {
+                    continue;
+                }
+                // Inner edge of the lane, across the road from its centre line
+                let across = match Route::from_lane_index(lane) {
+                    Some(route) => lanes::lane_offset(route) - lanes::LANE_WIDTH / 2.0,
+                    None => lanes::ROAD_WIDTH - lanes::BIKE_LANE_WIDTH,
+                } + 0.3;
+                let place = |along: f32| match direction {
+                    Direction::North => (across, -along),
+                    Direction::South => (-across, along),
+                    Direction::East => (-along, -across),
+                    Direction::West => (along, across),
+                };
+                let (x1, y1) = place(STOP_LINE_DISTANCE);
+                let (x2, y2) = place(STOP_LINE_DISTANCE + queue.length);
+                let (x1, y1) = assets.world_to_screen(x1, y1);
+                let (x2, y2) = assets.world_to_screen(x2, y2);
+                let bar = match direction {
+                    Direction::North | Direction::South => {
+                        Rect::new(x1 - (thickness / 2) as i32, y1.min(y2), thickness, y1.abs_diff(y2).max(1))
+                    }
+                    Direction::East | Direction::West => {
+                        Rect::new(x1.min(x2), y1 - (thickness / 2) as i32, x1.abs_diff(x2).max(1), thickness)
+                    }
+                };
+                canvas.fill_rect(bar)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Renders a single vehicle
     fn render_vehicle(
         &self,
@@ -56,72 +215,67 @@ impl AnimationManager {
         assets: &AssetManager,
     ) -> Result<(), String> {
         // Get the appropriate texture
-        let texture = assets.get_vehicle_texture(vehicle.direction)
+        let texture = assets.get_vehicle_texture(vehicle.vehicle_type, vehicle.direction)
             .ok_or("Vehicle texture not found")?;
 
         // Calculate adjusted position based on lane and route
-        let (world_x, world_y) = self.calculate_vehicle_position(vehicle);
+        let (world_x, world_y) = lane_position(vehicle.position, vehicle.direction, vehicle.route, vehicle.vehicle_type);
 
         // Convert to screen coordinates
         let (screen_x, screen_y) = assets.world_to_screen(world_x, world_y);
 
-        // Calculate sprite size in pixels
-        let sprite_size = (self.vehicle_size * assets.scale) as u32;
+        // Calculate sprite size in pixels along and across the direction of travel
+        let (along, across) = self.sprite_size(vehicle.vehicle_type);
+        let along = (along * assets.scale) as u32;
+        let across = (across * assets.scale) as u32;
+        let (sprite_width, sprite_height) = match vehicle.direction {
+            Direction::North | Direction::South => (across, along),
+            Direction::East | Direction::West => (along, across),
+        };
 
         // Create destination rectangle (centered on vehicle position)
         let dest_rect = Rect::new(
-            screen_x - (sprite_size / 2) as i32,
-            screen_y - (sprite_size / 2) as i32,
-            sprite_size,
-            sprite_size,
+            screen_x - (sprite_width / 2) as i32,
+            screen_y - (sprite_height / 2) as i32,
+            sprite_width,
+            sprite_height,
         );
 
         // Draw the vehicle
         canvas.copy(texture, None, Some(dest_rect))?;
 
-        Ok(())
-    }
-
-    /// Calculates the actual world position of a vehicle based on its lane
-    fn calculate_vehicle_position(&self, vehicle: &Vehicle) -> (f32, f32) {
-        let base_x = vehicle.position.0;
-        let base_y = vehicle.position.1;
+        // Outline vehicles driven by humans, who ignore the manager
+        if vehicle.is_human() {
+            canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 140, 0));
+            canvas.draw_rect(dest_rect)?;
+        }
 
-        // Determine lane offset based on direction and route
-        let lane_offset = self.get_lane_offset(vehicle.route);
+        // Outline rogue vehicles, which misbehave on purpose
+        if vehicle.rogue.is_some() {
+            canvas.set_draw_color(sdl2::pixels::Color::RGB(220, 30, 30));
+            canvas.draw_rect(dest_rect)?;
+        }
 
-        match vehicle.direction {
-            Direction::North => {
-                // Coming from south, moving north (upward)
-                // Lanes are on the RIGHT side of the road (positive X)
-                (base_x + lane_offset, base_y)
-            }
-            Direction::South => {
-                // Coming from north, moving south (downward)
-                // Lanes are on the RIGHT side (negative X)
-                (base_x - lane_offset, base_y)
-            }
-            Direction::East => {
-                // Coming from west, moving east (rightward)
-                // Lanes are on the RIGHT side (negative Y)
-                (base_x, base_y - lane_offset)
-            }
-            Direction::West => {
-                // Coming from east, moving west (leftward)
-                // Lanes are on the RIGHT side (positive Y)
-                (base_x, base_y + lane_offset)
-            }
+        // Outline platoon members, which cross as one unit
+        if vehicle.platoon.is_some() {
+            canvas.set_draw_color(sdl2::pixels::Color::RGB(80, 160, 255));
+            canvas.draw_rect(dest_rect)?;
         }
+
+        Ok(())
     }
 
-    /// Gets the lane offset based on the route
-    /// Right lane = rightmost, Straight = middle, Left = leftmost
-    fn get_lane_offset(&self, route: Route) -> f32 {
-        match route {
-            Route::Right => lanes::LANE_WIDTH * 0.5,      // Rightmost lane
-            Route::Straight => lanes::LANE_WIDTH * 1.5,   // Middle lane
-            Route::Left => lanes::LANE_WIDTH * 2.5,       // Leftmost lane
-        }
+    /// Gets the sprite size in meters (along, across the direction of travel)
+    ///
+    /// The car sprite is a `vehicle_size` square; other types are scaled
+    /// relative to the car's length and width.
+    fn sprite_size(&self, vehicle_type: VehicleType) -> (f32, f32) {
+        let car = VehicleType::Car.spec();
+        let spec = vehicle_type.spec();
+        (
+            self.vehicle_size * spec.length / car.length,
+            self.vehicle_size * spec.width / car.width,
+        )
     }
 
     /// Draws the intersection layout
@@ -161,6 +315,63 @@ impl AnimationManager {
         // Draw lane markings (only outside intersection area)
         self.draw_lane_markings(canvas, assets)?;
 
+        self.draw_bike_lanes(canvas, assets)?;
+
+        self.draw_crosswalks(canvas, assets)?;
+
+        Ok(())
+    }
+
+    /// Draws the solid lines separating the bike lanes, excluding the intersection area
+    fn draw_bike_lanes(
+        &self,
+        canvas: &mut Canvas<Window>,
+        assets: &AssetManager,
+    ) -> Result<(), String> {
+        use sdl2::pixels::Color;
+
+        canvas.set_draw_color(Color::RGB(120, 220, 120));
+
+        let half_width = (lanes::ROAD_WIDTH * assets.scale) as i32;
+        let line = ((lanes::ROAD_WIDTH - lanes::BIKE_LANE_WIDTH) * assets.scale) as i32;
+        let (cx, cy) = (assets.center_x, assets.center_y);
+
+        for offset in [-line, line] {
+            // North-South road
+            canvas.draw_line((cx + offset, 0), (cx + offset, cy - half_width))?;
+            canvas.draw_line((cx + offset, cy + half_width), (cx + offset, cy * 2))?;
+            // East-West road
+            canvas.draw_line((0, cy + offset), (cx - half_width, cy + offset))?;
+            canvas.draw_line((cx + half_width, cy + offset), (cx * 2, cy + offset))?;
+        }
+
+        Ok(())
+    }
+
+    /// Draws zebra crosswalks on every arm, right outside the intersection
+    fn draw_crosswalks(
+        &self,
+        canvas: &mut Canvas<Window>,
+        assets: &AssetManager,
+    ) -> Result<(), String> {
+        use sdl2::pixels::Color;
+
+        canvas.set_draw_color(Color::RGB(230, 230, 230));
+
+        let half_width = (lanes::ROAD_WIDTH * assets.scale) as i32;
+        let depth = (CROSSWALK_WIDTH * assets.scale) as i32;
+        let stripe = (0.6 * assets.scale) as i32;
+
+        // Stripes run along the road, spaced across it
+        for offset in (-half_width..half_width).step_by((stripe * 2) as usize) {
+            // North and south arms
+            canvas.fill_rect(Rect::new(assets.center_x + offset, assets.center_y - half_width - depth, stripe as u32, depth as u32))?;
+            canvas.fill_rect(Rect::new(assets.center_x + offset, assets.center_y + half_width, stripe as u32, depth as u32))?;
+            // East and west arms
+            canvas.fill_rect(Rect::new(assets.center_x + half_width, assets.center_y + offset, depth as u32, stripe as u32))?;
+            canvas.fill_rect(Rect::new(assets.center_x - half_width - depth, assets.center_y + offset, depth as u32, stripe as u32))?;
+        }
+
         Ok(())
     }
 
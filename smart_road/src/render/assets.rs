@@ -2,12 +2,14 @@ use sdl2::image::LoadTexture;
 use sdl2::render::{Texture, TextureCreator};
 use sdl2::video::WindowContext;
 use std::collections::HashMap;
+use std::path::Path;
 use crate::simulation::vehicle::Direction;
+use crate::simulation::VehicleType;
 
 /// Manages loading and storing of graphical assets
 pub struct AssetManager<'a> {
-    /// Vehicle textures for each direction
-    vehicle_textures: HashMap<Direction, Texture<'a>>,
+    /// Vehicle textures for each vehicle type and direction
+    vehicle_textures: HashMap<(VehicleType, Direction), Texture<'a>>,
 
     /// World scale: pixels per meter
     pub scale: f32,
@@ -19,6 +21,9 @@ pub struct AssetManager<'a> {
 
 impl<'a> AssetManager<'a> {
     /// Creates a new asset manager and loads all textures
+    ///
+    /// Each vehicle type uses the sprites in `assets/vehicle/<type>/` when
+    /// they exist, otherwise the default car sprites tinted in the type's colour.
     pub fn new(
         texture_creator: &'a TextureCreator<WindowContext>,
         window_width: u32,
@@ -27,16 +32,26 @@ impl<'a> AssetManager<'a> {
     ) -> Result<Self, String> {
         let mut vehicle_textures = HashMap::new();
 
-        // Load vehicle sprites for each direction
-        let north_texture = texture_creator.load_texture("assets/vehicle/NORTH.png")?;
-        let south_texture = texture_creator.load_texture("assets/vehicle/SOUTH.png")?;
-        let east_texture = texture_creator.load_texture("assets/vehicle/EAST.png")?;
-        let west_texture = texture_creator.load_texture("assets/vehicle/WEST.png")?;
-
-        vehicle_textures.insert(Direction::North, north_texture);
-        vehicle_textures.insert(Direction::South, south_texture);
-        vehicle_textures.insert(Direction::East, east_texture);
-        vehicle_textures.insert(Direction::West, west_texture);
+        // Load vehicle sprites for each type and direction
+        for vehicle_type in VehicleType::ALL {
+            for (direction, file) in [
+                (Direction::North, "NORTH.png"),
+                (Direction::South, "SOUTH.png"),
+                (Direction::East, "EAST.png"),
+                (Direction::West, "WEST.png"),
+            ] {
+                let own_sprite = format!("assets/vehicle/{}/{}", vehicle_type.name(), file);
+                let texture = if Path::new(&own_sprite).exists() {
+                    texture_creator.load_texture(&own_sprite)?
+                } else {
+                    let mut texture = texture_creator.load_texture(format!("assets/vehicle/{}", file))?;
+                    let (r, g, b) = Self::type_tint(vehicle_type);
+                    texture.set_color_mod(r, g, b);
+                    texture
+                };
+                vehicle_textures.insert((vehicle_type, direction), texture);
+            }
+        }
 
         Ok(AssetManager {
             vehicle_textures,
@@ -46,9 +61,23 @@ impl<'a> AssetManager<'a> {
         })
     }
 
-    /// Gets the vehicle texture for a specific direction
-    pub fn get_vehicle_texture(&self, direction: Direction) -> Option<&Texture> {
-        self.vehicle_textures.get(&direction)
+    /// Colour applied to the default sprite for types without their own sprites
+    fn type_tint(vehicle_type: VehicleType) -> (u8, u8, u8) {
+        match vehicle_type {
+            VehicleType::Car => (255, 255, 255),
+            VehicleType::Van => (170, 200, 255),
+            VehicleType::Truck => (255, 170, 90),
+            VehicleType::Bus => (255, 230, 80),
+            VehicleType::Motorcycle => (150, 255, 150),
+            VehicleType::Ambulance => (255, 120, 120),
+            VehicleType::FireTruck => (255, 40, 40),
+            VehicleType::Bicycle => (120, 220, 220),
+        }
+    }
+
+    /// Gets the vehicle texture for a specific vehicle type and direction
+    pub fn get_vehicle_texture(&self, vehicle_type: VehicleType, direction: Direction) -> Option<&Texture<'a>> {
+        self.vehicle_textures.get(&(vehicle_type, direction))
     }
 
     /// Converts world coordinates (meters) to screen coordinates (pixels)
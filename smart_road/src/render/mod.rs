@@ -1,23 +1,31 @@
 mod assets;
 mod animation;
+mod timeline;
 
 pub use assets::AssetManager;
-pub use animation::{AnimationManager};
+pub use animation::AnimationManager;
+pub use timeline::TimelineBar;
 
 use sdl2::render::Canvas;
 use sdl2::video::Window;
-use crate::simulation::Simulation;
+use crate::simulation::queue::QueueMonitor;
+use crate::simulation::signal::Signal;
+use crate::simulation::{Pedestrian, Simulation, Vehicle};
+use crate::recording::{History, Replay};
 
 pub struct Renderer<'a> {
     pub assets: AssetManager<'a>,
     pub animation: AnimationManager,
+    pub timeline: TimelineBar,
 }
 
 impl<'a> Renderer<'a> {
     pub fn new(assets: AssetManager<'a>) -> Self {
+        let timeline = TimelineBar::new(assets.center_x as u32 * 2, assets.center_y as u32 * 2);
         Renderer {
             assets,
             animation: AnimationManager::new(5.5), // Increased from 4.0 to 5.5 meters for bigger cars
+            timeline,
         }
     }
 
@@ -25,6 +33,53 @@ impl<'a> Renderer<'a> {
         &self,
         canvas: &mut Canvas<Window>,
         simulation: &Simulation,
+    ) -> Result<(), String> {
+        // Draw all vehicles
+        let all_vehicles: Vec<_> = simulation.intersection.lanes
+            .values()
+            .flat_map(|lane| lane.iter())
+            .collect();
+
+        self.render_scene(
+            canvas,
+            &all_vehicles,
+            &simulation.intersection.pedestrians,
+            simulation.intersection.signal.as_ref(),
+            simulation.intersection.all_way_stop.is_some(),
+            Some(&simulation.intersection.queues),
+        )
+    }
+
+    /// Draws the rewind timeline on top of the rendered frame
+    pub fn render_timeline(
+        &self,
+        canvas: &mut Canvas<Window>,
+        history: &History,
+    ) -> Result<(), String> {
+        self.timeline.draw(canvas, history)
+    }
+
+    /// Renders the current frame of a recorded trajectory
+    pub fn render_replay(
+        &self,
+        canvas: &mut Canvas<Window>,
+        replay: &Replay,
+    ) -> Result<(), String> {
+        let vehicles: Vec<_> = replay.current_vehicles().iter().collect();
+        self.render_scene(canvas, &vehicles, &[], None, false, None)
+    }
+
+    /// Draws the background, the intersection, its signal heads or stop
+    /// signs if it has them, the given vehicles and pedestrians, and the
+    /// queues if they are measured
+    fn render_scene(
+        &self,
+        canvas: &mut Canvas<Window>,
+        vehicles: &Vec<&Vehicle>,
+        pedestrians: &[Pedestrian],
+        signal: Option<&Signal>,
+        stop_signs: bool,
+        queues: Option<&QueueMonitor>,
     ) -> Result<(), String> {
         use sdl2::pixels::Color;
 
@@ -34,14 +89,18 @@ impl<'a> Renderer<'a> {
 
         // Draw intersection
         self.animation.draw_intersection(canvas, &self.assets)?;
+        if let Some(signal) = signal {
+            self.animation.render_signals(canvas, signal, &self.assets)?;
+        }
+        if stop_signs {
+            self.animation.render_stop_signs(canvas, &self.assets)?;
+        }
 
-        // Draw all vehicles
-        let all_vehicles: Vec<_> = simulation.intersection.lanes
-            .values()
-            .flat_map(|lane| lane.iter())
-            .collect();
-
-        self.animation.render_vehicles(canvas, &all_vehicles, &self.assets)?;
+        self.animation.render_vehicles(canvas, vehicles, &self.assets)?;
+        if let Some(queues) = queues {
+            self.animation.render_queues(canvas, queues, &self.assets)?;
+        }
+        self.animation.render_pedestrians(canvas, pedestrians, &self.assets)?;
 
         Ok(())
     }
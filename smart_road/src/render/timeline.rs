@@ -0,0 +1,79 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use crate::recording::History;
+
+/// Height of the timeline bar in pixels
+const BAR_HEIGHT: u32 = 12;
+
+/// Gap between the bar and the window edges in pixels
+const MARGIN: i32 = 20;
+
+/// Timeline bar at the bottom of the window for scrubbing through history
+pub struct TimelineBar {
+    /// Screen area covered by the bar
+    area: Rect,
+}
+
+impl TimelineBar {
+    pub fn new(window_width: u32, window_height: u32) -> Self {
+        TimelineBar {
+            area: Rect::new(
+                MARGIN,
+                window_height as i32 - MARGIN - BAR_HEIGHT as i32,
+                window_width - 2 * MARGIN as u32,
+                BAR_HEIGHT,
+            ),
+        }
+    }
+
+    /// Converts a click at screen position (x, y) into a timeline fraction
+    ///
+    /// Returns `None` if the click is outside the bar.
+    pub fn fraction_at(&self, x: i32, y: i32) -> Option<f32> {
+        // Accept clicks slightly above and below the thin bar
+        let hit_area = Rect::new(
+            self.area.x(),
+            self.area.y() - BAR_HEIGHT as i32,
+            self.area.width(),
+            BAR_HEIGHT * 3,
+        );
+        if !hit_area.contains_point((x, y)) {
+            return None;
+        }
+        Some((x - self.area.x()) as f32 / self.area.width() as f32)
+    }
+
+    /// Draws the bar, the covered part up to the cursor and the cursor marker
+    pub fn draw(&self, canvas: &mut Canvas<Window>, history: &History) -> Result<(), String> {
+        // Background track
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 160));
+        canvas.fill_rect(self.area)?;
+
+        // Recorded part up to the cursor
+        let position = history.position();
+        let filled = (self.area.width() as f32 * position) as u32;
+        let fill_color = if history.is_scrubbing() {
+            Color::RGB(255, 165, 0)
+        } else {
+            Color::RGB(0, 200, 255)
+        };
+        if filled > 0 {
+            canvas.set_draw_color(fill_color);
+            canvas.fill_rect(Rect::new(self.area.x(), self.area.y(), filled, self.area.height()))?;
+        }
+
+        // Cursor marker
+        canvas.set_draw_color(Color::RGB(255, 255, 255));
+        let marker_x = self.area.x() + filled as i32;
+        canvas.fill_rect(Rect::new(
+            marker_x - 2,
+            self.area.y() - 4,
+            4,
+            self.area.height() + 8,
+        ))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use std::io::{self, Write};
+use crate::stats::stats::DelayStats;
+use super::vehicle::Direction;
+
+/// Waiting time after which every agent bids its whole budget (in seconds)
+///
+/// Bids rise with the time spent waiting so that vehicles with little
+/// urgency are not outbid forever.
+pub const BID_ESCALATION_TIME: f32 = 30.0;
+
+/// Column header of the auction log CSV file
+const CSV_HEADER: &str = "id,approach,urgency,budget,bid,won,losses,waited";
+
+/// Sealed bid of an agent with the given urgency and budget that has been
+/// waiting for `waited` seconds (in credits)
+///
+/// An agent starts out bidding `urgency` times its budget and raises its bid
+/// until it bids the whole budget after `BID_ESCALATION_TIME`.
+pub fn bid(urgency: f32, budget: f32, waited: f32) -> f32 {
+    (budget * (urgency + waited / BID_ESCALATION_TIME)).min(budget)
+}
+
+/// Result of the auctions one vehicle took part in, logged when it wins or
+/// withdraws without winning
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuctionOutcome {
+    pub id: u32,
+    pub approach: Direction,
+    pub urgency: f32,
+    pub budget: f32,
+    pub bid: f32,               // Last bid, which the vehicle pays if it won (in credits)
+    pub won: bool,
+    pub losses: u32,            // Rounds won by another bidder in total
+    pub waited: f32,            // Time between request and reservation or withdrawal (in seconds)
+}
+
+/// Waiting time of the vehicles from `approach` that won an auction, and
+/// the average price they paid
+pub fn approach_summary(log: &[AuctionOutcome], approach: Direction) -> (DelayStats, f32) {
+    let mut waits = DelayStats::default();
+    let mut paid = 0.0;
+    for outcome in winners(log).filter(|outcome| outcome.approach == approach) {
+        waits.record(outcome.waited);
+        paid += outcome.bid;
+    }
+    let average_paid = if waits.count == 0 { 0.0 } else { paid / waits.count as f32 };
+    (waits, average_paid)
+}
+
+/// Bids made from `approach`, won or lost, and the share of them that won
+///
+/// Approaches whose bids win far less often than the others' are being
+/// outbid, whatever their waiting times.
+pub fn approach_win_rate(log: &[AuctionOutcome], approach: Direction) -> (u32, f32) {
+    let from_approach = || log.iter().filter(|outcome| outcome.approach == approach);
+    let won = from_approach().filter(|outcome| outcome.won).count() as u32;
+    let bids = won + from_approach().map(|outcome| outcome.losses).sum::<u32>();
+    let rate = if bids == 0 { 0.0 } else { won as f32 / bids as f32 };
+    (bids, rate)
+}
+
+/// Average waiting time of the winners weighted by urgency (in seconds)
+///
+/// Lower than the plain average when urgent vehicles were let through
+/// sooner, which is what the auction is meant to achieve.
+pub fn urgency_weighted_wait(log: &[AuctionOutcome]) -> f32 {
+    let weight: f32 = winners(log).map(|outcome| outcome.urgency).sum();
+    if weight == 0.0 {
+        return 0.0;
+    }
+    winners(log).map(|outcome| outcome.urgency * outcome.waited).sum::<f32>() / weight
+}
+
+/// The vehicles in the log that won
+pub fn winners(log: &[AuctionOutcome]) -> impl Iterator<Item = &AuctionOutcome> {
+    log.iter().filter(|outcome| outcome.won)
+}
+
+/// Writes the log as CSV, one row per vehicle
+pub fn write_csv<W: Write>(log: &[AuctionOutcome], mut writer: W) -> io::Result<()> {
+    writeln!(writer, "{}", CSV_HEADER)?;
+    for outcome in log {
+        writeln!(
+            writer,
+            "{},{},{:.3},{:.3},{:.3},{},{},{:.3}",
+            outcome.id,
+            outcome.approach,
+            outcome.urgency,
+            outcome.budget,
+            outcome.bid,
+            outcome.won,
+            outcome.losses,
+            outcome.waited,
+        )?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(id: u32, approach: Direction, urgency: f32, waited: f32) -> AuctionOutcome {
+        AuctionOutcome {
+            id,
+            approach,
+            urgency,
+            budget: 100.0,
+            bid: bid(urgency, 100.0, waited),
+            won: true,
+            losses: 0,
+            waited,
+        }
+    }
+
+    #[test]
+    fn test_bids_rise_while_waiting() {
+        assert_eq!(bid(0.2, 100.0, 0.0), 20.0);
+        assert_eq!(bid(0.2, 100.0, 15.0), 70.0);
+        // Never more than the budget
+        assert_eq!(bid(0.2, 100.0, 60.0), 100.0);
+        assert_eq!(bid(1.0, 50.0, 0.0), 50.0);
+    }
+
+    #[test]
+    fn test_summaries() {
+        let log = vec![
+            outcome(1, Direction::North, 1.0, 2.0),
+            outcome(2, Direction::North, 0.0, 10.0),
+            outcome(3, Direction::East, 0.5, 4.0),
+            AuctionOutcome { won: false, losses: 3, ..outcome(4, Direction::East, 0.2, 3.0) },
+        ];
+        let (waits, paid) = approach_summary(&log, Direction::North);
+        assert_eq!(waits.count, 2);
+        assert_eq!(waits.average(), 6.0);
+        assert!((paid - 200.0 / 3.0).abs() < 1e-4);
+        assert_eq!(approach_summary(&log, Direction::South).0.count, 0);
+        assert_eq!(approach_summary(&log, Direction::East).0.count, 1);
+        assert_eq!(approach_win_rate(&log, Direction::North), (2, 1.0));
+        assert_eq!(approach_win_rate(&log, Direction::East), (4, 0.25));
+        assert_eq!(approach_win_rate(&log, Direction::South), (0, 0.0));
+
+        // Only the urgent vehicles count towards the weighted wait
+        assert!((urgency_weighted_wait(&log) - 8.0 / 3.0).abs() < 1e-5);
+
+        let mut csv = Vec::new();
+        write_csv(&log, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 5);
+        assert!(csv.lines().nth(2).unwrap().starts_with("2,North,0.000,100.000,33.333,true,0,10.000"));
+        assert!(csv.lines().nth(4).unwrap().starts_with("4,East,0.200,100.000,30.000,false,3,3.000"));
+    }
+}
@@ -0,0 +1,242 @@
+use std::str::FromStr;
+use rand::Rng;
+use super::physics::velocities;
+
+/// Wait a fully polite driver concedes to a rude one when two stopped
+/// drivers take turns (in seconds)
+pub const MAX_COURTESY_WAIT: f32 = 20.0;
+
+/// Budget of every vehicle unless configured otherwise (in credits)
+pub const DEFAULT_BUDGET: f32 = 100.0;
+
+/// Distribution a behaviour parameter is drawn from
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamDistribution {
+    /// Always the same value
+    Fixed(f32),
+    /// Uniform between the two bounds
+    Uniform(f32, f32),
+    /// Normal with the given mean and standard deviation
+    Normal(f32, f32),
+    /// One of the listed values, all equally likely
+    Choice(Vec<f32>),
+}
+
+impl ParamDistribution {
+    /// Draws a value, clamped to `bounds`
+    ///
+    /// Fixed values do not consume any randomness, so adding a fixed
+    /// parameter leaves the rest of a seeded run unchanged.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R, bounds: (f32, f32)) -> f32 {
+        let value = match self {
+            ParamDistribution::Fixed(value) => *value,
+            ParamDistribution::Uniform(low, high) if low < high => rng.random_range(*low..*high),
+            ParamDistribution::Uniform(low, _) => *low,
+            ParamDistribution::Normal(mean, std_dev) => {
+                // Box-Muller transform
+                let u1: f32 = rng.random_range(f32::EPSILON..1.0);
+                let u2: f32 = rng.random_range(0.0..1.0);
+                mean + std_dev * (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+            }
+            ParamDistribution::Choice(values) => values[rng.random_range(0..values.len())],
+        };
+        value.clamp(bounds.0, bounds.1)
+    }
+}
+
+impl FromStr for ParamDistribution {
+    type Err = String;
+
+    /// Parses `5`, `uniform:0.8:1.6`, `normal:12:2` or `choice:5:10:15`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().split(':');
+        let kind = parts.next().unwrap_or_default();
+        let values = parts
+            .map(|value| value.trim().parse::<f32>().map_err(|_| format!("Invalid number: {}", value)))
+            .collect::<Result<Vec<f32>, String>>()?;
+
+        match (kind, values.as_slice()) {
+            ("uniform", [low, high]) if low <= high => Ok(ParamDistribution::Uniform(*low, *high)),
+            ("normal", [mean, std_dev]) if *std_dev >= 0.0 => Ok(ParamDistribution::Normal(*mean, *std_dev)),
+            ("choice", values) if !values.is_empty() => Ok(ParamDistribution::Choice(values.to_vec())),
+            (value, []) => value
+                .parse()
+                .map(ParamDistribution::Fixed)
+                .map_err(|_| format!("Invalid distribution: {}", s)),
+            _ => Err(format!("Invalid distribution: {}", s)),
+        }
+    }
+}
+
+/// Behaviour parameters of one vehicle
+///
+/// Automated vehicles only use the headway; reaction time, critical gap and
+/// politeness describe how a human driver reacts and judges gaps at the
+/// intersection. Urgency and budget set what the vehicle bids for the
+/// intersection under the auction policy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Behaviour {
+    pub reaction_time: f32,     // Delay before reacting to the vehicle ahead or a gap (in seconds)
+    pub headway: f32,           // Desired time gap to the vehicle ahead on top of braking distance (in seconds)
+    pub critical_gap: f32,      // Smallest time gap to conflicting traffic accepted (in seconds)
+    pub politeness: f32,        // Willingness to let waiting vehicles go first (0–1)
+    pub urgency: f32,           // How much of its budget the vehicle bids right away (0–1)
+    pub budget: f32,            // Most the vehicle is willing to pay to cross (in credits)
+}
+
+impl Default for Behaviour {
+    fn default() -> Self {
+        Behaviour {
+            reaction_time: 0.0,
+            headway: 0.0,
+            critical_gap: 4.5,
+            politeness: 0.0,
+            urgency: 1.0,
+            budget: DEFAULT_BUDGET,
+        }
+    }
+}
+
+/// Distributions the generator draws vehicle behaviour from
+#[derive(Debug, Clone, PartialEq)]
+pub struct BehaviourProfile {
+    pub desired_speed: ParamDistribution,
+    pub reaction_time: ParamDistribution,
+    pub headway: ParamDistribution,
+    pub critical_gap: ParamDistribution,
+    pub politeness: ParamDistribution,
+    pub urgency: ParamDistribution,
+    pub budget: ParamDistribution,
+}
+
+impl Default for BehaviourProfile {
+    /// The three fixed speeds, no extra headway, human drivers with
+    /// reaction times of 0.8–1.6 s and critical gaps around 4.5 s, and the
+    /// same urgency and budget for everyone
+    fn default() -> Self {
+        BehaviourProfile {
+            desired_speed: ParamDistribution::Choice(vec![
+                velocities::SLOW,
+                velocities::MEDIUM,
+                velocities::FAST,
+            ]),
+            reaction_time: ParamDistribution::Uniform(0.8, 1.6),
+            headway: ParamDistribution::Fixed(0.0),
+            critical_gap: ParamDistribution::Normal(4.5, 0.75),
+            politeness: ParamDistribution::Uniform(0.0, 1.0),
+            urgency: ParamDistribution::Fixed(1.0),
+            budget: ParamDistribution::Fixed(DEFAULT_BUDGET),
+        }
+    }
+}
+
+impl BehaviourProfile {
+    /// Draws a desired speed (in m/s)
+    pub fn sample_desired_speed<R: Rng + ?Sized>(&self, rng: &mut R) -> f32 {
+        self.desired_speed.sample(rng, (1.0, f32::MAX))
+    }
+
+    /// Draws the behaviour of one vehicle
+    ///
+    /// Parameters only human drivers use are drawn for them only.
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R, human: bool) -> Behaviour {
+        let headway = self.headway.sample(rng, (0.0, 5.0));
+        let urgency = self.urgency.sample(rng, (0.0, 1.0));
+        let budget = self.budget.sample(rng, (1.0, f32::MAX));
+        if !human {
+            return Behaviour { headway, urgency, budget, ..Behaviour::default() };
+        }
+        Behaviour {
+            reaction_time: self.reaction_time.sample(rng, (0.0, 3.0)),
+            headway,
+            critical_gap: self.critical_gap.sample(rng, (1.0, 10.0)),
+            politeness: self.politeness.sample(rng, (0.0, 1.0)),
+            urgency,
+            budget,
+        }
+    }
+}
+
+impl FromStr for BehaviourProfile {
+    type Err = String;
+
+    /// Parses overrides of the default profile such as
+    /// `speed=normal:12:2,headway=1,politeness=0.5`
+    ///
+    /// Parameters are `speed`, `reaction`, `headway`, `gap`, `politeness`,
+    /// `urgency` and `budget`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut profile = BehaviourProfile::default();
+        for entry in s.split(',') {
+            let (name, distribution) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Expected parameter=distribution, found: {}", entry))?;
+            let distribution = distribution.parse()?;
+            match name.trim() {
+                "speed" => profile.desired_speed = distribution,
+                "reaction" => profile.reaction_time = distribution,
+                "headway" => profile.headway = distribution,
+                "gap" => profile.critical_gap = distribution,
+                "politeness" => profile.politeness = distribution,
+                "urgency" => profile.urgency = distribution,
+                "budget" => profile.budget = distribution,
+                other => return Err(format!("Unknown behaviour parameter: {}", other)),
+            }
+        }
+        Ok(profile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::rng::SimRng;
+
+    #[test]
+    fn test_parse_distributions() {
+        assert_eq!("2.5".parse(), Ok(ParamDistribution::Fixed(2.5)));
+        assert_eq!("uniform:1:2".parse(), Ok(ParamDistribution::Uniform(1.0, 2.0)));
+        assert_eq!("normal:12:2".parse(), Ok(ParamDistribution::Normal(12.0, 2.0)));
+        assert_eq!("choice:5:10".parse(), Ok(ParamDistribution::Choice(vec![5.0, 10.0])));
+
+        assert!("uniform:2:1".parse::<ParamDistribution>().is_err());
+        assert!("normal:1".parse::<ParamDistribution>().is_err());
+        assert!("fast".parse::<ParamDistribution>().is_err());
+    }
+
+    #[test]
+    fn test_parse_profile() {
+        let profile: BehaviourProfile = "speed=normal:12:2, headway=1".parse().unwrap();
+        assert_eq!(profile.desired_speed, ParamDistribution::Normal(12.0, 2.0));
+        assert_eq!(profile.headway, ParamDistribution::Fixed(1.0));
+        assert_eq!(profile.politeness, BehaviourProfile::default().politeness);
+
+        assert!("mood=1".parse::<BehaviourProfile>().is_err());
+        assert!("speed".parse::<BehaviourProfile>().is_err());
+    }
+
+    #[test]
+    fn test_samples_vary_within_bounds() {
+        let profile: BehaviourProfile = "speed=normal:12:3".parse().unwrap();
+        let mut rng = SimRng::new(4);
+        let speeds: Vec<f32> = (0..500).map(|_| profile.sample_desired_speed(&mut rng)).collect();
+        let mean = speeds.iter().sum::<f32>() / speeds.len() as f32;
+        assert!((11.5..12.5).contains(&mean));
+        assert!(speeds.iter().all(|&speed| speed >= 1.0));
+
+        let behaviours: Vec<Behaviour> = (0..100).map(|_| profile.sample(&mut rng, true)).collect();
+        assert!(behaviours.iter().all(|b| (0.8..1.6).contains(&b.reaction_time)));
+        assert!(behaviours.iter().all(|b| (0.0..=1.0).contains(&b.politeness)));
+        assert!(behaviours.iter().any(|b| b.critical_gap < 4.0));
+        assert!(behaviours.iter().any(|b| b.critical_gap > 5.0));
+    }
+
+    #[test]
+    fn test_automated_vehicles_only_draw_headway() {
+        let mut rng = SimRng::new(4);
+        let behaviour = BehaviourProfile::default().sample(&mut rng, false);
+        assert_eq!(behaviour, Behaviour::default());
+        // Nothing was drawn
+        assert_eq!(rng.state(), SimRng::new(4).state());
+    }
+}
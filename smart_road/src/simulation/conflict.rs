@@ -0,0 +1,178 @@
+use super::vehicle::{Direction, Route, Vehicle};
+
+/// A movement through the intersection: the direction of travel on approach
+/// and the route taken at the centre
+pub type Movement = (Direction, Route);
+
+/// A part of the intersection an agent needs exclusive use of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Claim {
+    /// A vehicle movement through the conflict zone, including the
+    /// crosswalks it passes on the way in and out
+    Movement(Movement),
+    /// A bicycle movement from the bike lane along the curb
+    Cycling(Movement),
+    /// The crosswalk on one arm of the intersection (compass side)
+    Crosswalk(Direction),
+}
+
+/// Gets the claim a vehicle needs to cross the intersection
+pub fn vehicle_claim(vehicle: &Vehicle) -> Claim {
+    let movement = (vehicle.direction, vehicle.route);
+    if vehicle.vehicle_type.is_bicycle() {
+        Claim::Cycling(movement)
+    } else {
+        Claim::Movement(movement)
+    }
+}
+
+/// Gets the direction pointing the opposite way
+pub fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::North => Direction::South,
+        Direction::South => Direction::North,
+        Direction::East => Direction::West,
+        Direction::West => Direction::East,
+    }
+}
+
+/// Gets the direction of travel after leaving the intersection
+pub fn exit_direction((direction, route): Movement) -> Direction {
+    match (direction, route) {
+        (_, Route::Straight) => direction,
+        (Direction::North, Route::Right) | (Direction::South, Route::Left) => Direction::East,
+        (Direction::North, Route::Left) | (Direction::South, Route::Right) => Direction::West,
+        (Direction::East, Route::Right) | (Direction::West, Route::Left) => Direction::South,
+        (Direction::East, Route::Left) | (Direction::West, Route::Right) => Direction::North,
+    }
+}
+
+/// Conflict matrix: whether two movements may not use the conflict zone at the same time
+///
+/// Traffic drives on the right. Movements from the same approach use separate
+/// lanes and never conflict. Otherwise two movements conflict when they
+/// merge into the same exit or when their paths cross: right turns only
+/// merge, opposing straight movements and opposing left turns pass each
+/// other, and every other combination crosses.
+pub fn movements_conflict(a: Movement, b: Movement) -> bool {
+    if a.0 == b.0 {
+        return false;
+    }
+    if exit_direction(a) == exit_direction(b) {
+        return true;
+    }
+    if a.1 == Route::Right || b.1 == Route::Right {
+        return false;
+    }
+    if opposite(a.0) == b.0 {
+        // Only a left turn against oncoming straight traffic crosses
+        return a.1 != b.1;
+    }
+    true
+}
+
+/// Whether a movement passes the crosswalk on `arm`
+///
+/// Vehicles enter from the arm opposite their direction of travel and leave
+/// by the arm in their exit direction.
+pub fn uses_crosswalk(movement: Movement, arm: Direction) -> bool {
+    opposite(movement.0) == arm || exit_direction(movement) == arm
+}
+
+/// Right hook: a vehicle turning right cuts across the bike lane of its own
+/// approach, in front of a cyclist riding on straight ahead
+pub fn right_hook(vehicle: Movement, bicycle: Movement) -> bool {
+    vehicle.0 == bicycle.0 && vehicle.1 == Route::Right && bicycle.1 == Route::Straight
+}
+
+/// Whether two claims may not be held at the same time
+///
+/// Bicycles conflict with other approaches like vehicles taking the same
+/// route; from their own approach only right-turning vehicles cross them.
+pub fn claims_conflict(a: Claim, b: Claim) -> bool {
+    match (a, b) {
+        (Claim::Movement(a), Claim::Movement(b))
+        | (Claim::Cycling(a), Claim::Cycling(b)) => movements_conflict(a, b),
+        (Claim::Movement(vehicle), Claim::Cycling(bicycle))
+        | (Claim::Cycling(bicycle), Claim::Movement(vehicle)) => {
+            movements_conflict(vehicle, bicycle) || right_hook(vehicle, bicycle)
+        }
+        (Claim::Movement(movement) | Claim::Cycling(movement), Claim::Crosswalk(arm))
+        | (Claim::Crosswalk(arm), Claim::Movement(movement) | Claim::Cycling(movement)) => {
+            uses_crosswalk(movement, arm)
+        }
+        (Claim::Crosswalk(_), Claim::Crosswalk(_)) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIRECTIONS: [Direction; 4] = [Direction::North, Direction::South, Direction::East, Direction::West];
+    const ROUTES: [Route; 3] = [Route::Right, Route::Straight, Route::Left];
+
+    #[test]
+    fn test_exit_direction() {
+        assert_eq!(exit_direction((Direction::North, Route::Right)), Direction::East);
+        assert_eq!(exit_direction((Direction::North, Route::Left)), Direction::West);
+        assert_eq!(exit_direction((Direction::West, Route::Right)), Direction::North);
+        assert_eq!(exit_direction((Direction::East, Route::Straight)), Direction::East);
+    }
+
+    #[test]
+    fn test_matrix_is_symmetric() {
+        for &d1 in &DIRECTIONS {
+            for &r1 in &ROUTES {
+                for &d2 in &DIRECTIONS {
+                    for &r2 in &ROUTES {
+                        assert_eq!(
+                            movements_conflict((d1, r1), (d2, r2)),
+                            movements_conflict((d2, r2), (d1, r1)),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_known_conflicts() {
+        // Perpendicular straight movements cross
+        assert!(movements_conflict((Direction::North, Route::Straight), (Direction::East, Route::Straight)));
+        // Left turn against oncoming straight traffic
+        assert!(movements_conflict((Direction::North, Route::Left), (Direction::South, Route::Straight)));
+        // Right turn merging with straight traffic from the left
+        assert!(movements_conflict((Direction::North, Route::Right), (Direction::East, Route::Straight)));
+    }
+
+    #[test]
+    fn test_known_compatible_movements() {
+        assert!(!movements_conflict((Direction::North, Route::Straight), (Direction::South, Route::Straight)));
+        assert!(!movements_conflict((Direction::North, Route::Left), (Direction::South, Route::Left)));
+        assert!(!movements_conflict((Direction::North, Route::Right), (Direction::South, Route::Right)));
+        assert!(!movements_conflict((Direction::North, Route::Right), (Direction::West, Route::Straight)));
+        assert!(!movements_conflict((Direction::North, Route::Left), (Direction::North, Route::Straight)));
+    }
+
+    #[test]
+    fn test_right_hook() {
+        let bicycle = Claim::Cycling((Direction::North, Route::Straight));
+        assert!(claims_conflict(Claim::Movement((Direction::North, Route::Right)), bicycle));
+        assert!(!claims_conflict(Claim::Movement((Direction::North, Route::Straight)), bicycle));
+        assert!(!claims_conflict(Claim::Movement((Direction::North, Route::Right)), Claim::Cycling((Direction::North, Route::Right))));
+        // Other approaches conflict as for vehicles
+        assert!(claims_conflict(Claim::Movement((Direction::East, Route::Straight)), bicycle));
+    }
+
+    #[test]
+    fn test_crosswalk_claims() {
+        let crosswalk = Claim::Crosswalk(Direction::West);
+        // Enters from the west arm
+        assert!(claims_conflict(crosswalk, Claim::Movement((Direction::East, Route::Straight))));
+        // Leaves by the west arm
+        assert!(claims_conflict(Claim::Movement((Direction::North, Route::Left)), crosswalk));
+        assert!(!claims_conflict(crosswalk, Claim::Movement((Direction::North, Route::Right))));
+        assert!(!claims_conflict(crosswalk, Claim::Crosswalk(Direction::East)));
+    }
+}
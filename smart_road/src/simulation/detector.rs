@@ -0,0 +1,345 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::str::FromStr;
+use super::vehicle::{Direction, Route, Vehicle, BIKE_LANE};
+
+/// Default length of a loop along the lane (in meters)
+pub const DEFAULT_LOOP_LENGTH: f32 = 2.0;
+
+/// Default length of the intervals the measurements are aggregated over (in seconds)
+pub const DEFAULT_AGGREGATION_INTERVAL: f32 = 60.0;
+
+/// Column header of the detector CSV file
+const CSV_HEADER: &str = "start,end,detector,count,flow,occupancy,mean_speed";
+
+/// Virtual induction loop on one lane
+///
+/// The loop covers `length` meters of the lane downstream of `distance`,
+/// both measured like `Vehicle::distance_to_intersection`, so negative
+/// distances lie past the centre. A vehicle is counted, and its spot speed
+/// taken, when its front reaches the loop; the loop is occupied while any
+/// part of a vehicle is over it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoopDetector {
+    pub direction: Direction,
+    /// Lane index, `BIKE_LANE` for the bike lane
+    pub lane: usize,
+    /// Distance of the upstream edge of the loop from the centre (in meters)
+    pub distance: f32,
+    pub length: f32,
+    /// Only vehicles on this route trigger the loop, as with the call
+    /// buttons of a bike lane; `None` for every vehicle
+    pub route: Option<Route>,
+    /// Vehicles counted in the current interval
+    pub count: u32,
+    /// Time the loop was occupied in the current interval (in seconds)
+    pub occupied_time: f32,
+    /// Sum of the spot speeds of the vehicles counted (in m/s)
+    pub speed_sum: f32,
+    /// Vehicles over the loop after the last step
+    pub over: HashSet<u32>,
+}
+
+impl LoopDetector {
+    pub fn new(direction: Direction, lane: usize, distance: f32, length: f32) -> Self {
+        LoopDetector {
+            direction,
+            lane,
+            distance,
+            length,
+            route: None,
+            count: 0,
+            occupied_time: 0.0,
+            speed_sum: 0.0,
+            over: HashSet::new(),
+        }
+    }
+
+    /// Loop only triggered by vehicles on `route`
+    pub fn for_route(mut self, route: Route) -> Self {
+        self.route = Some(route);
+        self
+    }
+
+    /// Name of the detector, in the format it is configured with, e.g. `North_1@30`
+    pub fn name(&self) -> String {
+        format!("{}_{}@{}", self.direction, self.lane, self.distance)
+    }
+
+    /// Whether any part of the vehicle is over the loop
+    fn covers(&self, vehicle: &Vehicle) -> bool {
+        let half_length = vehicle.spec().length / 2.0;
+        vehicle.active
+            && vehicle.lane_index() == self.lane
+            && self.route.is_none_or(|route| vehicle.route == route)
+            && vehicle.distance_to_intersection - half_length < self.distance
+            && vehicle.distance_to_intersection + half_length > self.distance - self.length
+    }
+
+    /// Vehicles over the loop right now
+    fn vehicles_over<'a>(&self, lanes: &'a HashMap<Direction, Vec<Vehicle>>) -> impl Iterator<Item = &'a Vehicle> + use<'a, '_> {
+        lanes
+            .get(&self.direction)
+            .into_iter()
+            .flatten()
+            .filter(|vehicle| self.covers(vehicle))
+    }
+
+    /// Whether a vehicle is over the loop right now
+    pub fn occupied(&self, lanes: &HashMap<Direction, Vec<Vehicle>>) -> bool {
+        self.vehicles_over(lanes).next().is_some()
+    }
+
+    /// Counts the vehicles that reached the loop and adds up its occupancy
+    pub fn update(&mut self, lanes: &HashMap<Direction, Vec<Vehicle>>, delta_time: f32) {
+        let vehicles: Vec<(u32, f32)> = self
+            .vehicles_over(lanes)
+            .map(|vehicle| (vehicle.id, vehicle.velocity))
+            .collect();
+        let mut over = HashSet::new();
+        for (id, velocity) in vehicles {
+            over.insert(id);
+            if !self.over.contains(&id) {
+                self.count += 1;
+                self.speed_sum += velocity;
+            }
+        }
+        if !over.is_empty() {
+            self.occupied_time += delta_time;
+        }
+        self.over = over;
+    }
+
+    /// Closes the current interval, returning its measurements
+    fn take_record(&mut self, start: f32, end: f32) -> DetectorRecord {
+        let record = self.record(start, end);
+        self.count = 0;
+        self.occupied_time = 0.0;
+        self.speed_sum = 0.0;
+        record
+    }
+
+    /// Measurements of the current interval so far
+    fn record(&self, start: f32, end: f32) -> DetectorRecord {
+        DetectorRecord {
+            start,
+            end,
+            detector: self.name(),
+            count: self.count,
+            occupied_time: self.occupied_time,
+            speed_sum: self.speed_sum,
+        }
+    }
+}
+
+impl FromStr for LoopDetector {
+    type Err = String;
+
+    /// Parses `<lane>@<distance>[:<length>]`, e.g. `North_1@30` or
+    /// `East_3@-20:4`, with the lane id as in `Vehicle::lane_id`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (lane_id, position) = s
+            .trim()
+            .split_once('@')
+            .ok_or_else(|| format!("Expected lane@distance, found: {}", s))?;
+        let (direction, lane) = lane_id
+            .split_once('_')
+            .ok_or_else(|| format!("Invalid lane: {}", lane_id))?;
+        let direction: Direction = direction.parse()?;
+        let lane: usize = match lane.parse() {
+            Ok(lane) if lane <= BIKE_LANE => lane,
+            _ => return Err(format!("Invalid lane: {}", lane_id)),
+        };
+        let (distance, length) = match position.split_once(':') {
+            Some((distance, length)) => (distance, length.parse().map_err(|_| format!("Invalid loop length: {}", length))?),
+            None => (position, DEFAULT_LOOP_LENGTH),
+        };
+        let distance: f32 = distance.parse().map_err(|_| format!("Invalid detector distance: {}", distance))?;
+        if length <= 0.0 {
+            return Err(format!("Invalid loop length: {}", s));
+        }
+        Ok(LoopDetector::new(direction, lane, distance, length))
+    }
+}
+
+/// Measurements of one detector over one aggregation interval
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectorRecord {
+    pub start: f32,
+    pub end: f32,
+    pub detector: String,
+    pub count: u32,
+    /// Time the loop was occupied (in seconds)
+    pub occupied_time: f32,
+    /// Sum of the spot speeds of the vehicles counted (in m/s)
+    pub speed_sum: f32,
+}
+
+impl DetectorRecord {
+    /// Vehicles per hour
+    pub fn flow(&self) -> f32 {
+        let duration = self.end - self.start;
+        if duration > 0.0 { self.count as f32 * 3600.0 / duration } else { 0.0 }
+    }
+
+    /// Share of the interval the loop was occupied
+    pub fn occupancy(&self) -> f32 {
+        let duration = self.end - self.start;
+        if duration > 0.0 { self.occupied_time / duration } else { 0.0 }
+    }
+
+    /// Time-mean spot speed of the vehicles counted (in m/s)
+    pub fn mean_speed(&self) -> Option<f32> {
+        (self.count > 0).then(|| self.speed_sum / self.count as f32)
+    }
+}
+
+/// Loop detectors placed on the lanes and the measurements they took
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detectors {
+    pub loops: Vec<LoopDetector>,
+    /// Length of the aggregation intervals (in seconds)
+    pub interval: f32,
+    /// Time since the detectors were placed (in seconds)
+    pub time: f32,
+    /// Start of the current interval (in seconds)
+    pub interval_start: f32,
+    /// Measurements of every interval closed so far
+    pub records: Vec<DetectorRecord>,
+}
+
+impl Detectors {
+    pub fn new(loops: Vec<LoopDetector>, interval: f32) -> Self {
+        Detectors {
+            loops,
+            interval,
+            time: 0.0,
+            interval_start: 0.0,
+            records: Vec::new(),
+        }
+    }
+
+    /// Lets every loop measure, closing the interval when it is over
+    pub fn update(&mut self, lanes: &HashMap<Direction, Vec<Vehicle>>, delta_time: f32) {
+        self.time += delta_time;
+        for detector in &mut self.loops {
+            detector.update(lanes, delta_time);
+        }
+        if self.time - self.interval_start >= self.interval {
+            let (start, end) = (self.interval_start, self.time);
+            for detector in &mut self.loops {
+                self.records.push(detector.take_record(start, end));
+            }
+            self.interval_start = self.time;
+        }
+    }
+
+    /// Records of the closed intervals followed by those of the interval in
+    /// progress, if it has begun
+    pub fn all_records(&self) -> Vec<DetectorRecord> {
+        let mut records = self.records.clone();
+        if self.time > self.interval_start {
+            records.extend(self.loops.iter().map(|detector| detector.record(self.interval_start, self.time)));
+        }
+        records
+    }
+
+    /// Measurements of every loop over the whole run, in the order the loops were placed
+    pub fn totals(&self) -> Vec<DetectorRecord> {
+        let records = self.all_records();
+        self.loops
+            .iter()
+            .map(|detector| {
+                let name = detector.name();
+                let mut total = DetectorRecord {
+                    start: 0.0,
+                    end: self.time,
+                    detector: name.clone(),
+                    count: 0,
+                    occupied_time: 0.0,
+                    speed_sum: 0.0,
+                };
+                for record in records.iter().filter(|record| record.detector == name) {
+                    total.count += record.count;
+                    total.occupied_time += record.occupied_time;
+                    total.speed_sum += record.speed_sum;
+                }
+                total
+            })
+            .collect()
+    }
+}
+
+/// Writes detector records as CSV, one row per detector and interval
+///
+/// Flow is in vehicles per hour, occupancy the share of the interval and
+/// mean speed in m/s, left empty when no vehicle was counted.
+pub fn write_csv<W: Write>(records: &[DetectorRecord], mut writer: W) -> io::Result<()> {
+    writeln!(writer, "{}", CSV_HEADER)?;
+    for record in records {
+        writeln!(
+            writer,
+            "{:.3},{:.3},{},{},{:.1},{:.4},{}",
+            record.start,
+            record.end,
+            record.detector,
+            record.count,
+            record.flow(),
+            record.occupancy(),
+            record.mean_speed().map_or(String::new(), |speed| format!("{:.3}", speed)),
+        )?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::test_support::lanes;
+
+    #[test]
+    fn test_parse_detector() {
+        let detector: LoopDetector = "North_1@30".parse().unwrap();
+        assert_eq!((detector.direction, detector.lane, detector.distance), (Direction::North, 1, 30.0));
+        assert_eq!(detector.length, DEFAULT_LOOP_LENGTH);
+        assert_eq!(detector.name(), "North_1@30");
+        let detector: LoopDetector = "West_3@-20:4".parse().unwrap();
+        assert_eq!((detector.lane, detector.distance, detector.length), (BIKE_LANE, -20.0, 4.0));
+        assert!("North@30".parse::<LoopDetector>().is_err());
+        assert!("North_4@30".parse::<LoopDetector>().is_err());
+        assert!("North_1@30:0".parse::<LoopDetector>().is_err());
+    }
+
+    #[test]
+    fn test_counts_occupancy_and_speed() {
+        let mut detectors = Detectors::new(vec!["North_1@50".parse().unwrap()], 10.0);
+        let mut vehicle = Vehicle::new(1, (0.0, -60.0), 10.0, Route::Straight, Direction::North, 60.0);
+        // Another lane does not count
+        let other = Vehicle::new(2, (0.0, -49.0), 10.0, Route::Left, Direction::North, 49.0);
+
+        for _ in 0..150 {
+            detectors.update(&lanes(vec![vehicle.clone(), other.clone()]), 0.1);
+            vehicle.update_position(0.1);
+        }
+        assert_eq!(detectors.records.len(), 1);
+        let record = &detectors.records[0];
+        assert_eq!(record.count, 1);
+        assert_eq!(record.mean_speed(), Some(10.0));
+        assert!((record.flow() - 360.0).abs() < 0.1);
+        // Car length plus loop length at 10 m/s
+        let over = (vehicle.spec().length + DEFAULT_LOOP_LENGTH) / 10.0;
+        assert!((record.occupied_time - over).abs() <= 0.1 + 1e-3);
+
+        let totals = detectors.totals();
+        assert_eq!(totals[0].count, 1);
+        assert_eq!(detectors.all_records().len(), 2);
+
+        let mut csv = Vec::new();
+        write_csv(&detectors.all_records(), &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with(CSV_HEADER));
+        assert!(csv.lines().nth(1).unwrap().contains(",North_1@50,1,360.0,"));
+        // Nothing counted in the second interval, so no mean speed
+        assert!(csv.lines().nth(2).unwrap().ends_with(','));
+    }
+}
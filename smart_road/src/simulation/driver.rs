@@ -0,0 +1,260 @@
+use super::behaviour::MAX_COURTESY_WAIT;
+use super::conflict::{claims_conflict, vehicle_claim, Claim};
+use super::manager::{IntersectionManager, STOP_LINE_DISTANCE};
+use super::pedestrian::Pedestrian;
+use super::physics::CONFLICT_ZONE_HALF_SIZE;
+use super::vehicle::Vehicle;
+
+/// Distance from the centre within which human drivers look for a gap (in meters)
+pub const DECISION_DISTANCE: f32 = STOP_LINE_DISTANCE + 10.0;
+
+/// A human driver, who ignores the intersection manager
+///
+/// Human drivers approach as if the intersection had yield signs on every
+/// arm: they slow down to stop at the stop line and cross once they have
+/// seen a large enough gap in conflicting traffic for their reaction time.
+/// Reaction time, critical gap and politeness come from the vehicle's
+/// `Behaviour`; this only keeps track of the decision.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct HumanDriver {
+    pub gap_timer: f32,         // How long an acceptable gap has been seen so far (in seconds)
+    pub decided: bool,          // Whether the driver has committed to crossing
+}
+
+impl HumanDriver {
+    pub fn new() -> Self {
+        HumanDriver::default()
+    }
+
+    /// Updates the decision with what the driver currently sees
+    ///
+    /// The driver commits once an acceptable gap has been seen for the whole
+    /// reaction time; a closing gap restarts the count.
+    pub fn observe(&mut self, gap_acceptable: bool, reaction_time: f32, delta_time: f32) {
+        if self.decided {
+            return;
+        }
+        if gap_acceptable {
+            self.gap_timer += delta_time;
+            if self.gap_timer >= reaction_time {
+                self.decided = true;
+            }
+        } else {
+            self.gap_timer = 0.0;
+        }
+    }
+}
+
+/// Time until the front of `vehicle` reaches the conflict zone (in seconds)
+///
+/// Zero once it is inside, and infinite while it stands still in front of it.
+pub fn time_to_zone(vehicle: &Vehicle) -> f32 {
+    let distance = vehicle.distance_to_intersection - vehicle.spec().length / 2.0 - CONFLICT_ZONE_HALF_SIZE;
+    if distance <= 0.0 {
+        0.0
+    } else if vehicle.velocity < 0.1 {
+        f32::INFINITY
+    } else {
+        distance / vehicle.velocity
+    }
+}
+
+/// Time `vehicle` would need to reach the conflict zone when accelerating
+/// at full power from its current velocity (in seconds)
+pub fn time_to_zone_when_going(vehicle: &Vehicle) -> f32 {
+    let distance = vehicle.distance_to_intersection - vehicle.spec().length / 2.0 - CONFLICT_ZONE_HALF_SIZE;
+    if distance <= 0.0 {
+        return 0.0;
+    }
+    let acceleration = vehicle.spec().max_acceleration;
+    let velocity = vehicle.velocity;
+    ((velocity * velocity + 2.0 * acceleration * distance).sqrt() - velocity) / acceleration
+}
+
+/// Time until the conflicting `other` is in the conflict zone, as far as a
+/// human driver in `vehicle` can tell (in seconds)
+///
+/// A human who has decided to go is taken to be in the zone already. A
+/// vehicle standing at the head of its queue near the stop line may set
+/// off at any moment, so it counts as going now, unless it is a human whose
+/// turn comes after the driver's (see `goes_before`).
+fn arrival_time(vehicle: &Vehicle, other: &Vehicle, others: &[&Vehicle]) -> f32 {
+    if other.driver.is_some_and(|driver| driver.decided) {
+        return 0.0;
+    }
+    let queued = others.iter().any(|ahead| {
+        ahead.id != other.id
+            && ahead.active
+            && ahead.direction == other.direction
+            && ahead.lane_index() == other.lane_index()
+            && ahead.distance_to_intersection < other.distance_to_intersection
+            && !IntersectionManager::has_cleared(ahead)
+    });
+    let waits_for_driver = other.is_human() && goes_before(vehicle, other);
+    if is_waiting(other) && !queued && !waits_for_driver {
+        time_to_zone_when_going(other)
+    } else {
+        time_to_zone(other)
+    }
+}
+
+/// Whether the vehicle stands waiting near the stop line
+fn is_waiting(vehicle: &Vehicle) -> bool {
+    vehicle.velocity < 0.1 && vehicle.distance_to_intersection <= DECISION_DISTANCE
+}
+
+/// Whether the human driver in `vehicle` takes their turn before the one in
+/// the waiting `other`
+///
+/// The driver held up longest goes first, or the one who came first on a
+/// tie. Politeness gives up part of a driver's claim: the more polite the
+/// driver, the more of `MAX_COURTESY_WAIT` they let a less polite one have,
+/// while two drivers who are equally polite go in order of delay.
+fn goes_before(vehicle: &Vehicle, other: &Vehicle) -> bool {
+    let claim = |vehicle: &Vehicle| vehicle.delay() - vehicle.behaviour.politeness * MAX_COURTESY_WAIT;
+    let (own, theirs) = (claim(vehicle), claim(other));
+    own > theirs || (own == theirs && vehicle.id < other.id)
+}
+
+/// Whether a human driver in `vehicle` sees a large enough gap to cross
+///
+/// Conflicting vehicles still to pass must arrive at the zone at least the
+/// driver's critical gap after the driver would (see `arrival_time`), and
+/// nobody may be walking on a crosswalk in the way.
+pub fn gap_available(vehicle: &Vehicle, others: &[&Vehicle], pedestrians: &[Pedestrian]) -> bool {
+    if vehicle.distance_to_intersection > DECISION_DISTANCE {
+        return false;
+    }
+
+    let claim = vehicle_claim(vehicle);
+    let pedestrian_in_way = pedestrians
+        .iter()
+        .filter(|pedestrian| pedestrian.active && pedestrian.crossing)
+        .any(|pedestrian| claims_conflict(claim, Claim::Crosswalk(pedestrian.arm)));
+    if pedestrian_in_way {
+        return false;
+    }
+
+    let own_arrival = time_to_zone_when_going(vehicle);
+    let critical_gap = vehicle.behaviour.critical_gap;
+    others
+        .iter()
+        .filter(|other| other.id != vehicle.id && other.active)
+        .filter(|other| !IntersectionManager::has_cleared(other))
+        .filter(|other| claims_conflict(claim, vehicle_claim(other)))
+        .all(|other| arrival_time(vehicle, other, others) >= own_arrival + critical_gap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::behaviour::Behaviour;
+    use crate::simulation::vehicle::{Direction, Route};
+
+    fn vehicle(id: u32, direction: Direction, distance: f32, velocity: f32) -> Vehicle {
+        Vehicle::new(id, (0.0, 0.0), velocity, Route::Straight, direction, distance)
+    }
+
+    fn behaviour(critical_gap: f32, politeness: f32) -> Behaviour {
+        Behaviour { reaction_time: 1.0, headway: 0.0, critical_gap, politeness, ..Behaviour::default() }
+    }
+
+    #[test]
+    fn test_decides_after_reaction_time() {
+        let mut driver = HumanDriver::new();
+        driver.observe(true, 1.0, 0.6);
+        driver.observe(false, 1.0, 0.1);
+        driver.observe(true, 1.0, 0.6);
+        assert!(!driver.decided);
+
+        driver.observe(true, 1.0, 0.5);
+        assert!(driver.decided);
+
+        // A decision is never taken back
+        driver.observe(false, 1.0, 0.1);
+        assert!(driver.decided);
+    }
+
+    #[test]
+    fn test_polite_driver_gives_way() {
+        let mut human = vehicle(1, Direction::North, STOP_LINE_DISTANCE + 2.25, 0.0)
+            .with_behaviour(behaviour(4.0, 0.5));
+        human.cruise_velocity = 10.0;
+        // A rude human who arrived after the driver
+        let mut waiting = vehicle(2, Direction::East, STOP_LINE_DISTANCE + 2.25, 0.0)
+            .with_driver(HumanDriver::new());
+        waiting.cruise_velocity = 10.0;
+        assert!(!gap_available(&human, &[&waiting], &[]));
+
+        // Held up longer than the driver is willing to concede
+        human.time_elapsed = 15.0;
+        assert!(gap_available(&human, &[&waiting], &[]));
+
+        // Even a rude driver lets one held up longer go first
+        human.behaviour.politeness = 0.0;
+        human.time_elapsed = 5.0;
+        waiting.time_elapsed = 8.0;
+        assert!(!gap_available(&human, &[&waiting], &[]));
+        assert!(goes_before(&waiting, &human) && !goes_before(&human, &waiting));
+    }
+
+    #[test]
+    fn test_stopped_humans_take_turns() {
+        let first = vehicle(1, Direction::North, STOP_LINE_DISTANCE + 2.25, 0.0)
+            .with_driver(HumanDriver::new())
+            .with_behaviour(behaviour(4.0, 0.0));
+        let mut second = vehicle(2, Direction::East, STOP_LINE_DISTANCE + 2.25, 0.0)
+            .with_driver(HumanDriver::new())
+            .with_behaviour(behaviour(4.0, 0.0));
+
+        // Only the driver who came first sees a gap
+        assert!(gap_available(&first, &[&second], &[]));
+        assert!(!gap_available(&second, &[&first], &[]));
+
+        // Once one has decided to go the other waits for it
+        second.driver = Some(HumanDriver { gap_timer: 1.0, decided: true });
+        assert!(!gap_available(&first, &[&second], &[]));
+    }
+
+    #[test]
+    fn test_stopped_vehicle_may_set_off() {
+        let human = vehicle(2, Direction::North, STOP_LINE_DISTANCE + 2.25, 0.0)
+            .with_driver(HumanDriver::new())
+            .with_behaviour(behaviour(4.0, 0.0));
+        let stopped = vehicle(1, Direction::East, STOP_LINE_DISTANCE + 2.25, 0.0);
+        assert!(!gap_available(&human, &[&stopped], &[]));
+
+        // Queued behind a human who waits for the driver, it cannot go first
+        let later = vehicle(3, Direction::East, STOP_LINE_DISTANCE + 2.25, 0.0).with_driver(HumanDriver::new());
+        let queued = vehicle(4, Direction::East, STOP_LINE_DISTANCE + 9.0, 0.0);
+        assert!(gap_available(&human, &[&later, &queued], &[]));
+    }
+
+    #[test]
+    fn test_gap_acceptance() {
+        let human = vehicle(1, Direction::North, STOP_LINE_DISTANCE + 2.25, 0.0)
+            .with_behaviour(behaviour(4.0, 0.0));
+
+        // Cross traffic far enough away
+        let far = vehicle(2, Direction::East, 80.0, 10.0);
+        assert!(gap_available(&human, &[&far], &[]));
+
+        // Cross traffic arriving too soon
+        let near = vehicle(3, Direction::East, 30.0, 10.0);
+        assert!(!gap_available(&human, &[&near], &[]));
+
+        // Only a less cautious driver takes a medium gap
+        let medium = vehicle(6, Direction::East, 50.0, 10.0);
+        assert!(!gap_available(&human, &[&medium], &[]));
+        let bold = human.clone().with_behaviour(behaviour(1.5, 0.0));
+        assert!(gap_available(&bold, &[&medium], &[]));
+
+        // Opposing straight traffic does not conflict
+        let opposing = vehicle(4, Direction::South, 20.0, 10.0);
+        assert!(gap_available(&human, &[&opposing], &[]));
+
+        // Too far from the intersection to judge
+        let approaching = vehicle(5, Direction::North, 60.0, 10.0);
+        assert!(!gap_available(&approaching, &[], &[]));
+    }
+}
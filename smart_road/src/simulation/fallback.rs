@@ -0,0 +1,431 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use super::conflict::{claims_conflict, vehicle_claim, Claim};
+use super::manager::{Agent, IntersectionManager, REQUEST_DISTANCE, STOP_LINE_DISTANCE};
+use super::pedestrian::Pedestrian;
+use super::vehicle::{Direction, Vehicle};
+
+/// Time without an answer from the manager after which vehicles assume it
+/// has failed and fall back to the all-way-stop rule (in seconds)
+pub const MANAGER_TIMEOUT: f32 = 1.0;
+
+/// Speed below which a vehicle counts as stopped (in m/s)
+const STOPPED_SPEED: f32 = 0.1;
+
+/// Largest distance between the front of a stopped vehicle and the stop
+/// line for it to count as stopped at the line (in meters)
+const STOPPED_AT_LINE: f32 = 1.0;
+
+/// Largest difference between the times two agents stopped for them to
+/// count as arriving together, when the right-hand rule decides (in seconds)
+pub const ARRIVAL_TIE: f32 = 0.5;
+
+/// A period during which the manager does not respond
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Outage {
+    pub start: f32,         // Simulation time the manager fails (in seconds)
+    pub duration: f32,      // How long until it recovers (in seconds)
+}
+
+impl FromStr for Outage {
+    type Err = String;
+
+    /// Parses `<start>:<duration>`, e.g. `120:60`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let [start, duration] = parts.as_slice() else {
+            return Err(format!("Expected start:duration, found: {}", s));
+        };
+        let start: f32 = start.trim().parse().map_err(|_| format!("Invalid outage start: {}", start))?;
+        let duration: f32 = duration.trim().parse().map_err(|_| format!("Invalid outage duration: {}", duration))?;
+        if start < 0.0 || duration <= 0.0 {
+            return Err(format!("Invalid outage: {}", s));
+        }
+        Ok(Outage { start, duration })
+    }
+}
+
+/// Decentralized all-way-stop rule, used while the manager is down or, with
+/// stop signs posted, instead of it
+///
+/// Every automated vehicle stops at the stop line, and pedestrians wait at
+/// the curb. Agents that have stopped go in the order they arrived once
+/// nothing they conflict with is going or arrived before them; emergency
+/// vehicles still come first, and of two vehicles that arrived together the
+/// one on the other's right goes first. Vehicles that can no longer stop go
+/// regardless. Without stop signs, human drivers who have decided to cross
+/// go regardless too and take right of way back from conflicting vehicles
+/// still in front of the intersection; with them, humans stop like everyone.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AllWayStop {
+    /// Whether stop signs are posted, so human drivers stop as well
+    pub stop_signs: bool,
+    /// Time since the rule was taken up (in seconds)
+    pub time: f32,
+    /// When each agent stopped at the line or reached the curb
+    pub arrivals: HashMap<Agent, f32>,
+    /// Agents that took right of way and hold it until they have crossed
+    pub going: HashSet<Agent>,
+}
+
+impl AllWayStop {
+    pub fn new() -> Self {
+        AllWayStop::default()
+    }
+
+    /// Rule of an intersection with stop signs on every approach
+    pub fn with_stop_signs() -> Self {
+        AllWayStop { stop_signs: true, ..AllWayStop::default() }
+    }
+
+    /// Records who has stopped and lets the agents whose turn it is go
+    pub fn update(
+        &mut self,
+        lanes: &HashMap<Direction, Vec<Vehicle>>,
+        pedestrians: &[Pedestrian],
+        delta_time: f32,
+    ) {
+        self.time += delta_time;
+
+        let mut present = HashSet::new();
+        let mut claims: HashMap<Agent, (Claim, bool)> = HashMap::new();
+        let vehicles = lanes
+            .values()
+            .flatten()
+            .filter(|vehicle| vehicle.active && vehicle.distance_to_intersection <= REQUEST_DISTANCE)
+            .filter(|vehicle| !IntersectionManager::has_cleared(vehicle));
+        for vehicle in vehicles {
+            let agent = Agent::Vehicle(vehicle.id);
+            present.insert(agent);
+            claims.insert(agent, (vehicle_claim(vehicle), vehicle.priority));
+            let forced = match vehicle.driver {
+                Some(driver) if !self.stop_signs => driver.decided,
+                _ => IntersectionManager::has_entered(vehicle),
+            };
+            let stops = self.stop_signs || vehicle.driver.is_none();
+            if forced {
+                self.going.insert(agent);
+            } else if stops && Self::stopped_at_line(vehicle) {
+                self.arrivals.entry(agent).or_insert(self.time);
+            }
+        }
+        for pedestrian in pedestrians.iter().filter(|pedestrian| pedestrian.active) {
+            let agent = Agent::Pedestrian(pedestrian.id);
+            present.insert(agent);
+            claims.insert(agent, (Claim::Crosswalk(pedestrian.arm), false));
+            self.arrivals.entry(agent).or_insert(self.time);
+            if pedestrian.crossing {
+                self.going.insert(agent);
+            }
+        }
+
+        // Forget agents that crossed or left
+        self.arrivals.retain(|agent, _| present.contains(agent));
+        self.going.retain(|agent| present.contains(agent));
+
+        // Human drivers who decided to go take right of way back from
+        // conflicting vehicles that have not driven into the intersection yet
+        let humans: Vec<Claim> = lanes
+            .values()
+            .flatten()
+            .filter(|vehicle| !self.stop_signs && vehicle.driver.is_some_and(|driver| driver.decided))
+            .filter(|vehicle| present.contains(&Agent::Vehicle(vehicle.id)))
+            .map(vehicle_claim)
+            .collect();
+        for vehicle in lanes.values().flatten().filter(|vehicle| vehicle.driver.is_none()) {
+            let yields = !IntersectionManager::has_entered(vehicle)
+                && humans.iter().any(|claim| claims_conflict(*claim, vehicle_claim(vehicle)));
+            if yields && !vehicle.priority {
+                self.going.remove(&Agent::Vehicle(vehicle.id));
+            }
+        }
+
+        let mut waiting: Vec<(Agent, f32)> = self.arrivals
+            .iter()
+            .filter(|(agent, _)| !self.going.contains(agent))
+            .map(|(agent, arrival)| (*agent, *arrival))
+            .collect();
+        waiting.sort_by(|(a, a_arrival), (b, b_arrival)| {
+            let (a_priority, b_priority) = (claims[a].1, claims[b].1);
+            b_priority
+                .cmp(&a_priority)
+                .then(a_arrival.total_cmp(b_arrival))
+                .then(sort_key(*a).cmp(&sort_key(*b)))
+        });
+
+        // Nobody passes a conflicting agent that goes before it
+        let precedes = |(a, a_arrival): (Agent, f32), (b, b_arrival): (Agent, f32)| {
+            let ((a_claim, a_priority), (b_claim, b_priority)) = (claims[&a], claims[&b]);
+            if a_priority != b_priority {
+                return a_priority;
+            }
+            if (a_arrival - b_arrival).abs() <= ARRIVAL_TIE {
+                match (approach(a_claim), approach(b_claim)) {
+                    (Some(a_direction), Some(b_direction)) if from_right(b_direction) == a_direction => return true,
+                    (Some(a_direction), Some(b_direction)) if from_right(a_direction) == b_direction => return false,
+                    _ => {}
+                }
+            }
+            a_arrival.total_cmp(&b_arrival).then(sort_key(a).cmp(&sort_key(b))).is_lt()
+        };
+        let mut moved = false;
+        for (agent, arrival) in &waiting {
+            let claim = claims[agent].0;
+            let blocked = waiting.iter().any(|other| {
+                other.0 != *agent && claims_conflict(claims[&other.0].0, claim) && precedes(*other, (*agent, *arrival))
+            }) || self.going.iter().any(|other| claims_conflict(claims[other].0, claim));
+            if !blocked {
+                self.going.insert(*agent);
+                moved = true;
+            }
+        }
+
+        // Four vehicles that arrived together all have someone on their
+        // right; the first to have stopped breaks the deadlock
+        if !moved && self.going.is_empty() {
+            if let Some((agent, _)) = waiting.first() {
+                self.going.insert(*agent);
+            }
+        }
+    }
+
+    /// Lets vehicles that were allowed to cross before the rule was taken
+    /// up keep going if they can no longer stop; the others brake for the line
+    pub fn take_up<'a>(&mut self, allowed: impl IntoIterator<Item = &'a Vehicle>) {
+        for vehicle in allowed {
+            if !IntersectionManager::can_stop(vehicle) {
+                self.going.insert(Agent::Vehicle(vehicle.id));
+            }
+        }
+    }
+
+    fn stopped(vehicle: &Vehicle) -> bool {
+        vehicle.velocity < STOPPED_SPEED
+    }
+
+    /// Whether the vehicle has come to a stop with its front at the stop line
+    fn stopped_at_line(vehicle: &Vehicle) -> bool {
+        let front = vehicle.distance_to_intersection - vehicle.spec().length / 2.0;
+        Self::stopped(vehicle) && front - STOP_LINE_DISTANCE < STOPPED_AT_LINE
+    }
+
+    /// Whether the vehicle has to stop at the stop line
+    pub fn must_wait(&self, vehicle: &Vehicle) -> bool {
+        !IntersectionManager::has_cleared(vehicle) && !self.going.contains(&Agent::Vehicle(vehicle.id))
+    }
+
+    /// Whether the pedestrian may step onto (or stay on) the crosswalk
+    pub fn may_cross(&self, pedestrian_id: u32) -> bool {
+        self.going.contains(&Agent::Pedestrian(pedestrian_id))
+    }
+}
+
+/// Direction of travel on approach of a vehicle claim
+fn approach(claim: Claim) -> Option<Direction> {
+    match claim {
+        Claim::Movement((direction, _)) | Claim::Cycling((direction, _)) => Some(direction),
+        Claim::Crosswalk(_) => None,
+    }
+}
+
+/// Direction of travel of vehicles approaching from the right of a vehicle
+/// travelling `direction`
+fn from_right(direction: Direction) -> Direction {
+    match direction {
+        Direction::North => Direction::West,
+        Direction::West => Direction::South,
+        Direction::South => Direction::East,
+        Direction::East => Direction::North,
+    }
+}
+
+/// Vehicles before pedestrians, then by id, for agents that arrived together
+fn sort_key(agent: Agent) -> (u8, u32) {
+    match agent {
+        Agent::Vehicle(id) => (0, id),
+        Agent::Pedestrian(id) => (1, id),
+    }
+}
+
+/// Detection of a failed manager and the all-way-stop fallback
+///
+/// The manager fails during the scheduled outages, or when toggled by hand.
+/// Vehicles notice when they have not heard from it for `MANAGER_TIMEOUT`
+/// while waiting for an answer, and then follow the all-way-stop rule until
+/// it answers again. A manager that recovers has lost its reservations and
+/// starts over from what it sees.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fallback {
+    /// Time since the simulation started (in seconds)
+    pub time: f32,
+    /// Scheduled manager failures
+    pub outages: Vec<Outage>,
+    /// Manager failed by hand, independent of the outage schedule
+    pub manual_outage: bool,
+    /// Whether the manager is failed right now
+    pub manager_down: bool,
+    /// How long the vehicles have been waiting for the manager to answer (in seconds)
+    pub silence: f32,
+    /// Whether the vehicles follow the all-way-stop rule
+    pub active: bool,
+    pub rule: AllWayStop,
+    /// Times the vehicles fell back
+    pub activations: u32,
+    /// Time spent in fallback mode (in seconds)
+    pub time_active: f32,
+}
+
+impl Fallback {
+    /// Advances the clock and fails or recovers the manager as scheduled
+    ///
+    /// Returns whether the manager recovered this step.
+    pub fn update_outage(&mut self, delta_time: f32) -> bool {
+        self.time += delta_time;
+        let time = self.time;
+        let down = self.manual_outage
+            || self.outages
+                .iter()
+                .any(|outage| time >= outage.start && time < outage.start + outage.duration);
+        let recovered = self.manager_down && !down;
+        self.manager_down = down;
+        recovered
+    }
+
+    /// Switches to the all-way-stop rule once the manager has been silent
+    /// for too long, and back once it answers again
+    ///
+    /// `answered` tells whether an answer from the manager reached the
+    /// vehicles this step, and `awaiting` whether any vehicle wanted one.
+    pub fn update_mode(&mut self, answered: bool, awaiting: bool, delta_time: f32) {
+        if answered || !awaiting {
+            self.silence = 0.0;
+        } else {
+            self.silence += delta_time;
+        }
+
+        if !self.active && self.silence >= MANAGER_TIMEOUT {
+            self.active = true;
+            self.activations += 1;
+            self.rule = AllWayStop::new();
+        } else if self.active && answered {
+            self.active = false;
+        }
+        if self.active {
+            self.time_active += delta_time;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::driver::HumanDriver;
+    use crate::simulation::vehicle::Route;
+    use crate::simulation::test_support::{lanes, stopped};
+
+    const TICK: f32 = 0.1;
+
+    #[test]
+    fn test_parse_outage() {
+        assert_eq!("120:60".parse(), Ok(Outage { start: 120.0, duration: 60.0 }));
+        assert!("120".parse::<Outage>().is_err());
+        assert!("120:0".parse::<Outage>().is_err());
+    }
+
+    #[test]
+    fn test_vehicles_go_in_stopping_order() {
+        let mut rule = AllWayStop::new();
+        let first = stopped(2, Direction::North, Route::Straight);
+        rule.update(&lanes(vec![first.clone()]), &[], TICK);
+        assert!(!rule.must_wait(&first));
+
+        // Arrives later and conflicts, so waits although its id is lower
+        let second = stopped(1, Direction::East, Route::Straight);
+        let mut approaching = Vehicle::new(3, (0.0, 0.0), 10.0, Route::Straight, Direction::South, 40.0);
+        approaching.velocity = 3.0;
+        rule.update(&lanes(vec![first.clone(), second.clone(), approaching.clone()]), &[], TICK);
+        assert!(rule.must_wait(&second));
+        // Has not stopped yet, even though its movement is compatible
+        assert!(rule.must_wait(&approaching));
+
+        rule.update(&lanes(vec![second.clone()]), &[], TICK);
+        assert!(!rule.must_wait(&second));
+    }
+
+    #[test]
+    fn test_right_hand_rule_breaks_ties() {
+        let mut rule = AllWayStop::with_stop_signs();
+        // Arriving from the east is on the right of a northbound vehicle
+        let north = stopped(1, Direction::North, Route::Straight);
+        let west = stopped(2, Direction::West, Route::Straight);
+        rule.update(&lanes(vec![north.clone(), west.clone()]), &[], TICK);
+        assert!(rule.must_wait(&north));
+        assert!(!rule.must_wait(&west));
+
+        // Everyone has someone on their right: the lowest id goes first
+        let mut rule = AllWayStop::with_stop_signs();
+        let all = vec![
+            stopped(4, Direction::North, Route::Straight),
+            stopped(3, Direction::West, Route::Straight),
+            stopped(2, Direction::South, Route::Straight),
+            stopped(1, Direction::East, Route::Straight),
+        ];
+        rule.update(&lanes(all.clone()), &[], TICK);
+        let going: Vec<u32> = all.iter().filter(|vehicle| !rule.must_wait(vehicle)).map(|vehicle| vehicle.id).collect();
+        assert_eq!(going, vec![1]);
+    }
+
+    #[test]
+    fn test_human_drivers_stop_at_stop_signs() {
+        let mut decided = HumanDriver::new();
+        decided.decided = true;
+        let mut human = Vehicle::new(1, (0.0, 0.0), 10.0, Route::Straight, Direction::North, 40.0)
+            .with_driver(decided);
+        human.velocity = 10.0;
+
+        let mut fallback = AllWayStop::new();
+        fallback.update(&lanes(vec![human.clone()]), &[], TICK);
+        assert!(!fallback.must_wait(&human));
+
+        let mut signs = AllWayStop::with_stop_signs();
+        signs.update(&lanes(vec![human.clone()]), &[], TICK);
+        assert!(signs.must_wait(&human));
+    }
+
+    #[test]
+    fn test_only_vehicles_let_through_before_keep_going() {
+        let mut rule = AllWayStop::new();
+        let mut granted = Vehicle::new(1, (0.0, 0.0), 15.0, Route::Straight, Direction::North, 20.0);
+        granted.velocity = 15.0;
+        let mut braking = Vehicle::new(2, (0.0, 0.0), 15.0, Route::Straight, Direction::East, 20.0);
+        braking.velocity = 15.0;
+
+        rule.take_up([&granted]);
+        rule.update(&lanes(vec![granted.clone(), braking.clone()]), &[], TICK);
+        assert!(!rule.must_wait(&granted));
+        assert!(rule.must_wait(&braking));
+    }
+
+    #[test]
+    fn test_falls_back_after_timeout_and_recovers() {
+        let mut fallback = Fallback::default();
+        let steps = (MANAGER_TIMEOUT / TICK) as usize;
+        for _ in 0..steps - 1 {
+            fallback.update_mode(false, true, TICK);
+        }
+        assert!(!fallback.active);
+        fallback.update_mode(false, true, TICK);
+        fallback.update_mode(false, true, TICK);
+        assert!(fallback.active);
+        assert_eq!(fallback.activations, 1);
+
+        fallback.update_mode(true, true, TICK);
+        assert!(!fallback.active);
+
+        // Silence while nobody waits for an answer is no failure
+        for _ in 0..2 * steps {
+            fallback.update_mode(false, false, TICK);
+        }
+        assert!(!fallback.active);
+    }
+}
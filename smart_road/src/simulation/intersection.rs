@@ -1,14 +1,31 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use super::vehicle::{Vehicle, Direction, Route};
-use super::physics::Physics;
+use super::physics::{Physics, CONFLICT_ZONE_HALF_SIZE};
+use super::conflict::{claims_conflict, vehicle_claim};
+use super::detector::Detectors;
+use super::driver::gap_available;
+use super::fallback::{AllWayStop, Fallback};
+use super::manager::{IntersectionManager, REQUEST_DISTANCE, STOP_LINE_DISTANCE};
+use super::pedestrian::{vehicle_on_crosswalk, Pedestrian};
+use super::platoon::{platoon_sizes, update_platoons};
+use super::queue::QueueMonitor;
+use super::rogue::{self, reported_lanes};
+use super::signal::Signal;
+use super::auction::AuctionOutcome;
+use super::v2i::V2iLink;
+use super::v2v::Negotiation;
 
 use std::rc::Rc;
 use std::cell::RefCell;
 use crate::StatisticsManager;  // Add this import
 
+/// How much faster than it should be a vehicle may still be after braking
+/// as hard as it comfortably can, before it counts as an emergency braking
+/// event (in m/s)
+pub const EMERGENCY_BRAKING_EXCESS: f32 = 2.0;
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Intersection {
     /// Stores vehicles in each lane, organized by direction
     /// Each direction (North, South, East, West) has its own queue of vehicles
@@ -17,6 +34,51 @@ pub struct Intersection {
     /// Minimum safe distance between vehicles (in meters)
     pub safe_distance: f32,
     pub physics: Physics,
+
+    /// Pedestrians waiting at or walking over the crosswalks
+    pub pedestrians: Vec<Pedestrian>,
+
+    /// Hands out reservations for the conflict zone and the crosswalks
+    pub manager: IntersectionManager,
+
+    /// Whether automated vehicles form platoons on the approaches
+    pub platooning: bool,
+
+    /// Right-of-way negotiation between the vehicles themselves, which
+    /// replaces the manager when set
+    pub negotiation: Option<Negotiation>,
+
+    /// Message channel between automated vehicles and the manager; without
+    /// it the manager sees every vehicle and its answers arrive instantly
+    pub v2i: Option<V2iLink>,
+
+    /// Manager failures and the all-way-stop rule vehicles fall back to
+    pub fallback: Fallback,
+
+    /// Traffic signal, which replaces the manager and V2V negotiation when set
+    pub signal: Option<Signal>,
+
+    /// Stop signs on every approach, which replace the manager and V2V
+    /// negotiation when set
+    pub all_way_stop: Option<AllWayStop>,
+
+    /// Loop detectors placed on the lanes
+    pub detectors: Option<Detectors>,
+
+    /// Queues on every approach and lane, and spillback to the spawn point
+    pub queues: QueueMonitor,
+
+    /// Pairs of vehicles with conflicting movements inside the
+    /// intersection together, each counted once in the statistics
+    pub conflicting_pairs: HashSet<(u32, u32)>,
+
+    /// Pairs of vehicles whose footprints overlap, each counted once in
+    /// the statistics
+    pub colliding_pairs: HashSet<(u32, u32)>,
+
+    /// Vehicles braking harder than they comfortably can, each braking
+    /// manoeuvre counted once in the statistics
+    pub emergency_braking: HashSet<u32>,
 }
 
 impl Intersection {
@@ -34,6 +96,19 @@ impl Intersection {
             lanes,
             safe_distance,
             physics: Physics::new(safe_distance, 100.0),
+            pedestrians: Vec::new(),
+            manager: IntersectionManager::new(),
+            platooning: true,
+            negotiation: None,
+            v2i: None,
+            fallback: Fallback::default(),
+            signal: None,
+            all_way_stop: None,
+            detectors: None,
+            queues: QueueMonitor::default(),
+            conflicting_pairs: HashSet::new(),
+            colliding_pairs: HashSet::new(),
+            emergency_braking: HashSet::new(),
         }
     }
 
@@ -100,12 +175,16 @@ impl Intersection {
     /// Adds a vehicle to the intersection
     /// 
     /// The vehicle is added to the appropriate lane based on its direction.
-    /// The vehicle is only added if it can safely enter.
-    pub fn add_vehicle(&mut self, direction: Direction, vehicle: Vehicle,stats: Rc<RefCell<StatisticsManager>>) -> bool {
+    /// The vehicle is only added if it can safely enter, and enters no
+    /// faster than it can still stop behind the vehicle ahead in its lane.
+    pub fn add_vehicle(&mut self, direction: Direction, mut vehicle: Vehicle,stats: Rc<RefCell<StatisticsManager>>) -> bool {
         // Check if vehicle can safely enter
         if !self.can_enter(&vehicle,stats) {
             return false;
         }
+        if let Some(leader) = self.leader_of(&vehicle) {
+            vehicle.velocity = vehicle.velocity.min(self.physics.following_velocity(&vehicle, leader));
+        }
         
         // Add vehicle to the appropriate lane
         if let Some(lane) = self.lanes.get_mut(&direction) {
@@ -116,18 +195,91 @@ impl Intersection {
         false
     }
 
-    /// Updates all vehicles in the intersection
+    /// Adds a pedestrian waiting at the curb of its crosswalk
+    pub fn add_pedestrian(&mut self, pedestrian: Pedestrian) {
+        self.pedestrians.push(pedestrian);
+    }
+
+    /// Updates all vehicles and pedestrians in the intersection
     /// 
     /// This method:
-    /// 1. Updates the position of each active vehicle
-    /// 2. Removes vehicles that have completed their journey through the intersection
+    /// 1. Lets human drivers look for a gap and the manager hand out
+    ///    reservations for the conflict zone and crosswalks
+    /// 2. Updates the position of each active vehicle
+    /// 3. Removes vehicles that have completed their journey through the intersection
+    /// 4. Moves pedestrians that may cross and removes those that reached the far curb
     pub fn update(&mut self, delta_time: f32,stats: Rc<RefCell<StatisticsManager>> ) {
+        self.update_drivers(delta_time);
+        if self.platooning {
+            update_platoons(&mut self.lanes);
+        }
+        match (self.signal.as_mut(), self.all_way_stop.as_mut(), self.negotiation.as_mut()) {
+            // Detectors and drivers see where the vehicles really are
+            (Some(signal), _, _) => signal.update(&self.lanes, &self.pedestrians, delta_time),
+            (None, Some(rule), _) => rule.update(&self.lanes, &self.pedestrians, delta_time),
+            (None, None, Some(negotiation)) => {
+                let reported = reported_lanes(&self.lanes);
+                let lanes = reported.as_ref().unwrap_or(&self.lanes);
+                negotiation.update(lanes, &self.pedestrians, delta_time);
+            }
+            (None, None, None) => {
+                for outcome in self.update_manager(delta_time) {
+                    stats.borrow_mut().record_auction(outcome);
+                }
+            }
+        }
+        self.record_conflicts(&stats);
+        let waiting: HashSet<u32> = self.lanes
+            .values()
+            .flatten()
+            .filter(|vehicle| self.is_waiting(vehicle))
+            .map(|vehicle| vehicle.id)
+            .collect();
+
+        // Platoon sizes as their heads leave, so the statistics see whole platoons
+        let sizes = platoon_sizes(&self.lanes);
+
         // Update each lane
         for lane in self.lanes.values_mut() {
+            // Each vehicle keeps its cruise velocity unless the vehicle ahead
+            // in the same lane forces it to slow down, or it has to wait at
+            // the stop line for a reservation (or, for humans, for a gap)
+            let targets: Vec<f32> = lane
+                .iter()
+                .map(|vehicle| {
+                    let mut target = match Self::find_leader(lane, vehicle) {
+                        Some(leader) => vehicle
+                            .cruise_velocity
+                            .min(self.physics.following_velocity(vehicle, leader)),
+                        None => vehicle.cruise_velocity,
+                    };
+                    if waiting.contains(&vehicle.id) {
+                        let room = vehicle.distance_to_intersection
+                            - vehicle.spec().length / 2.0
+                            - STOP_LINE_DISTANCE;
+                        target = target.min(self.physics.stopping_velocity(vehicle, room));
+                    }
+                    if vehicle.rogue.is_some_and(|rogue| rogue.stops(vehicle)) {
+                        target = 0.0;
+                    }
+                    target
+                })
+                .collect();
+
             // Update positions of all vehicles
-            for vehicle in lane.iter_mut() {
+            for (vehicle, target) in lane.iter_mut().zip(targets) {
                 stats.borrow_mut().record_velocity(vehicle.velocity);
                 if vehicle.active {
+                    rogue::stop_suddenly(vehicle, delta_time);
+                    let excess = vehicle.velocity - vehicle.spec().max_deceleration * delta_time - target;
+                    if excess > EMERGENCY_BRAKING_EXCESS {
+                        if self.emergency_braking.insert(vehicle.id) {
+                            stats.borrow_mut().record_emergency_braking();
+                        }
+                    } else {
+                        self.emergency_braking.remove(&vehicle.id);
+                    }
+                    vehicle.accelerate_towards(target, delta_time);
                     vehicle.update_position(delta_time);
                     // Use physics to check boundaries
                     if self.physics.is_out_of_bounds(vehicle) {
@@ -138,8 +290,272 @@ impl Intersection {
             }
             
             // Remove inactive vehicles (those that have passed through)
+            for vehicle in lane.iter().filter(|v| !v.active) {
+                stats.borrow_mut().record_exit(vehicle);
+                if vehicle.platoon == Some(vehicle.id) {
+                    stats.borrow_mut().record_platoon(sizes.get(&vehicle.id).copied().unwrap_or(1));
+                }
+            }
             lane.retain(|v| v.active);
         }
+        self.emergency_braking
+            .retain(|id| self.lanes.values().flatten().any(|vehicle| vehicle.id == *id));
+        if let Some(detectors) = self.detectors.as_mut() {
+            detectors.update(&self.lanes, delta_time);
+        }
+        self.queues.update(&self.lanes, self.safe_distance, delta_time);
+        self.record_collisions(&stats);
+
+        self.update_pedestrians(delta_time, &stats);
+    }
+
+    /// Runs the manager, or the all-way-stop rule once the vehicles noticed
+    /// that it failed
+    ///
+    /// Returns the auction bidders that won or withdrew this step.
+    fn update_manager(&mut self, delta_time: f32) -> Vec<AuctionOutcome> {
+        if self.fallback.update_outage(delta_time) {
+            self.manager.restart();
+            if let Some(link) = self.v2i.as_mut() {
+                link.restart();
+            }
+        }
+        // The manager only knows where the vehicles say they are
+        let reported = reported_lanes(&self.lanes);
+        let lanes = reported.as_ref().unwrap_or(&self.lanes);
+        let (outcomes, answered, awaiting) = match self.v2i.as_mut() {
+            Some(link) => {
+                if !self.fallback.active {
+                    link.check_entries(lanes, &self.manager);
+                }
+                let manager = if self.fallback.manager_down { None } else { Some(&mut self.manager) };
+                let outcomes = link.update(lanes, &self.pedestrians, manager, delta_time);
+                (outcomes, link.answered, !link.requested.is_empty())
+            }
+            None => {
+                let outcomes = if self.fallback.manager_down {
+                    Vec::new()
+                } else {
+                    self.manager.update(lanes, &self.pedestrians, delta_time)
+                };
+                let awaiting = self.lanes.values().flatten().any(|vehicle| {
+                    vehicle.active
+                        && !vehicle.is_human()
+                        && vehicle.distance_to_intersection <= REQUEST_DISTANCE
+                        && !IntersectionManager::has_cleared(vehicle)
+                });
+                (outcomes, !self.fallback.manager_down, awaiting)
+            }
+        };
+
+        let was_active = self.fallback.active;
+        self.fallback.update_mode(answered, awaiting, delta_time);
+        if self.fallback.active && !was_active {
+            let allowed: Vec<&Vehicle> = self.lanes
+                .values()
+                .flatten()
+                .filter(|vehicle| vehicle.driver.is_none() && !self.reservation_pending(&rogue::reported(vehicle, &self.lanes)))
+                .collect();
+            self.fallback.rule.take_up(allowed);
+            // Answers from before the failure are no longer trusted
+            if let Some(link) = self.v2i.as_mut() {
+                link.granted.clear();
+            }
+        }
+        if self.fallback.active {
+            self.fallback.rule.update(&self.lanes, &self.pedestrians, delta_time);
+        }
+        outcomes
+    }
+
+    /// Counts pairs of vehicles whose conflicting movements are inside the
+    /// intersection at the same time
+    fn record_conflicts(&mut self, stats: &Rc<RefCell<StatisticsManager>>) {
+        let inside: Vec<&Vehicle> = self.lanes
+            .values()
+            .flatten()
+            .filter(|vehicle| vehicle.active && IntersectionManager::has_entered(vehicle))
+            .collect();
+        let mut pairs = HashSet::new();
+        for (index, a) in inside.iter().enumerate() {
+            for b in &inside[index + 1..] {
+                if claims_conflict(vehicle_claim(a), vehicle_claim(b)) {
+                    pairs.insert((a.id.min(b.id), a.id.max(b.id)));
+                }
+            }
+        }
+        for _ in pairs.difference(&self.conflicting_pairs) {
+            stats.borrow_mut().record_zone_conflict();
+        }
+        self.conflicting_pairs = pairs;
+    }
+
+    /// Counts pairs of vehicles that ran into each other (see `find_collisions`)
+    fn record_collisions(&mut self, stats: &Rc<RefCell<StatisticsManager>>) {
+        let pairs = self.find_collisions();
+        for _ in pairs.difference(&self.colliding_pairs) {
+            stats.borrow_mut().record_collision();
+        }
+        self.colliding_pairs = pairs;
+    }
+
+    /// Finds pairs of vehicles whose footprints overlap: one ran into the
+    /// back of the other in the same lane, or both are inside the conflict
+    /// zone on conflicting movements
+    pub fn find_collisions(&self) -> HashSet<(u32, u32)> {
+        let vehicles: Vec<&Vehicle> = self.lanes.values().flatten().collect();
+        let in_zone = |vehicle: &Vehicle| {
+            vehicle.distance_to_intersection.abs() < CONFLICT_ZONE_HALF_SIZE + vehicle.spec().length / 2.0
+        };
+        let mut pairs = HashSet::new();
+        for (index, a) in vehicles.iter().enumerate() {
+            for b in &vehicles[index + 1..] {
+                let colliding = if a.direction == b.direction && a.lane_index() == b.lane_index() {
+                    let (follower, leader) = if a.distance_to_intersection > b.distance_to_intersection {
+                        (a, b)
+                    } else {
+                        (b, a)
+                    };
+                    self.physics.bumper_gap(follower, leader) < 0.0
+                } else {
+                    in_zone(a) && in_zone(b) && claims_conflict(vehicle_claim(a), vehicle_claim(b))
+                };
+                if colliding {
+                    pairs.insert((a.id.min(b.id), a.id.max(b.id)));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Whether the vehicle has to stop at the stop line, waiting for a
+    /// reservation (or right of way) or, with a human driver, for a gap
+    ///
+    /// Rogue vehicles that ignore reservations never wait.
+    fn is_waiting(&self, vehicle: &Vehicle) -> bool {
+        !vehicle.rogue.is_some_and(|rogue| rogue.ignores_reservation()) && !self.has_clearance(vehicle)
+    }
+
+    /// Whether whatever controls the intersection lets the vehicle cross:
+    /// it holds a reservation or right of way or, with a human driver, has
+    /// decided to go
+    ///
+    /// Under a traffic signal or at stop signs human drivers wait for their
+    /// turn like everyone else. The manager and the other vehicles judge a
+    /// vehicle by the position it reports, so a vehicle reporting a false
+    /// position is let go once its reported position has cleared the zone.
+    pub fn has_clearance(&self, vehicle: &Vehicle) -> bool {
+        if let Some(signal) = &self.signal {
+            return !signal.must_wait(vehicle);
+        }
+        if let Some(rule) = &self.all_way_stop {
+            return !rule.must_wait(vehicle);
+        }
+        match (vehicle.driver, &self.negotiation) {
+            (Some(driver), _) => driver.decided,
+            (None, Some(negotiation)) => !negotiation.must_wait(&rogue::reported(vehicle, &self.lanes)),
+            (None, None) if self.fallback.active => !self.fallback.rule.must_wait(vehicle),
+            (None, None) => !self.reservation_pending(&rogue::reported(vehicle, &self.lanes)),
+        }
+    }
+
+    /// Whether the vehicle, at the position it reports, still has to wait
+    /// for the manager (directly or over the V2I link) to grant it the zone
+    fn reservation_pending(&self, vehicle: &Vehicle) -> bool {
+        match &self.v2i {
+            Some(link) => link.must_wait(vehicle),
+            None => self.manager.must_wait(vehicle),
+        }
+    }
+
+    /// Lets human drivers that have not decided yet look for a gap to cross
+    ///
+    /// Only drivers at the head of their queue look; the others follow the
+    /// vehicle ahead up to the stop line first.
+    fn update_drivers(&mut self, delta_time: f32) {
+        let decisions: Vec<(u32, bool)> = {
+            let vehicles: Vec<&Vehicle> = self.lanes
+                .values()
+                .flat_map(|lane| lane.iter())
+                .filter(|vehicle| vehicle.active)
+                .collect();
+            vehicles
+                .iter()
+                .filter_map(|vehicle| {
+                    if vehicle.driver.is_none_or(|driver| driver.decided) {
+                        return None;
+                    }
+                    let queued = self
+                        .leader_of(vehicle)
+                        .is_some_and(|leader| self.is_waiting(leader));
+                    let gap = !queued && gap_available(vehicle, &vehicles, &self.pedestrians);
+                    Some((vehicle.id, gap))
+                })
+                .collect()
+        };
+
+        for vehicle in self.lanes.values_mut().flat_map(|lane| lane.iter_mut()) {
+            let Some((_, gap)) = decisions.iter().find(|(id, _)| *id == vehicle.id) else {
+                continue;
+            };
+            let reaction_time = vehicle.behaviour.reaction_time;
+            if let Some(driver) = vehicle.driver.as_mut() {
+                driver.observe(*gap, reaction_time, delta_time);
+            }
+        }
+    }
+
+    /// Moves pedestrians and records their waiting times and close calls
+    fn update_pedestrians(&mut self, delta_time: f32, stats: &Rc<RefCell<StatisticsManager>>) {
+        for pedestrian in &mut self.pedestrians {
+            let may_cross = match (&self.signal, &self.all_way_stop, &self.negotiation) {
+                (Some(signal), _, _) => signal.may_cross(pedestrian.id),
+                (None, Some(rule), _) => rule.may_cross(pedestrian.id),
+                (None, None, Some(negotiation)) => negotiation.may_cross(pedestrian.id),
+                (None, None, None) if self.fallback.active => self.fallback.rule.may_cross(pedestrian.id),
+                (None, None, None) => self.manager.may_cross(pedestrian.id),
+            };
+            pedestrian.update(delta_time, may_cross);
+
+            // A vehicle on the crosswalk while someone walks over it is a close call
+            if pedestrian.crossing && pedestrian.active && !pedestrian.close_call {
+                let vehicle_on_it = self.lanes
+                    .values()
+                    .flat_map(|lane| lane.iter())
+                    .any(|vehicle| vehicle_on_crosswalk(vehicle, pedestrian.arm));
+                if vehicle_on_it {
+                    pedestrian.close_call = true;
+                    stats.borrow_mut().record_pedestrian_close_call();
+                }
+            }
+
+            if !pedestrian.active {
+                stats.borrow_mut().record_pedestrian_wait(pedestrian.waiting_time);
+            }
+        }
+        self.pedestrians.retain(|pedestrian| pedestrian.active);
+    }
+
+    /// Finds the nearest vehicle ahead of `vehicle` in the same lane
+    ///
+    /// Vehicles share a lane when they come from the same direction and take
+    /// the same route; bicycles all share the bike lane.
+    fn find_leader<'a>(lane: &'a [Vehicle], vehicle: &Vehicle) -> Option<&'a Vehicle> {
+        lane.iter()
+            .filter(|other| {
+                other.id != vehicle.id
+                    && other.active
+                    && other.lane_index() == vehicle.lane_index()
+                    && other.distance_to_intersection < vehicle.distance_to_intersection
+            })
+            .max_by(|a, b| a.distance_to_intersection.total_cmp(&b.distance_to_intersection))
+    }
+
+    /// Gets the vehicle directly ahead of `vehicle` in its lane, if any
+    pub fn leader_of(&self, vehicle: &Vehicle) -> Option<&Vehicle> {
+        self.lanes
+            .get(&vehicle.direction)
+            .and_then(|lane| Self::find_leader(lane, vehicle))
     }
 
     /// Gets the total number of vehicles currently in the intersection
@@ -152,6 +568,215 @@ impl Intersection {
         self.lanes.get(&direction).map_or(0, |lane| lane.len())
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::physics::{CONFLICT_ZONE_HALF_SIZE, MIN_GAP};
+    use super::super::behaviour::Behaviour;
+    use super::super::driver::HumanDriver;
+    use super::super::rogue::Misbehaviour;
+    use super::super::signal::{parse_phases, DEFAULT_PHASES};
+    use super::super::vehicle_type::VehicleType;
+
+    fn stats() -> Rc<RefCell<StatisticsManager>> {
+        Rc::new(RefCell::new(StatisticsManager::new()))
+    }
+
+    fn push(intersection: &mut Intersection, vehicle: Vehicle) {
+        intersection.lanes.get_mut(&vehicle.direction).unwrap().push(vehicle);
+    }
+
+    #[test]
+    fn test_leader_is_nearest_vehicle_ahead_in_same_lane() {
+        let mut intersection = Intersection::new(10.0);
+        push(&mut intersection, Vehicle::new(1, (0.0, -20.0), 10.0, Route::Straight, Direction::North, 20.0));
+        push(&mut intersection, Vehicle::new(2, (0.0, -50.0), 10.0, Route::Straight, Direction::North, 50.0));
+        push(&mut intersection, Vehicle::new(3, (0.0, -60.0), 10.0, Route::Left, Direction::North, 60.0));
+        let follower = Vehicle::new(4, (0.0, -90.0), 10.0, Route::Straight, Direction::North, 90.0);
+
+        assert_eq!(intersection.leader_of(&follower).map(|v| v.id), Some(2));
+
+        let first = intersection.lanes[&Direction::North][0].clone();
+        assert!(intersection.leader_of(&first).is_none());
+    }
+
+    #[test]
+    fn test_follower_never_runs_into_stopped_leader() {
+        let mut intersection = Intersection::new(10.0);
+        let mut leader = Vehicle::new(1, (0.0, -40.0), 0.0, Route::Straight, Direction::North, 40.0);
+        leader.cruise_velocity = 0.0;
+        push(&mut intersection, leader);
+        push(
+            &mut intersection,
+            Vehicle::new(2, (0.0, -100.0), 15.0, Route::Straight, Direction::North, 100.0)
+                .with_type(VehicleType::Truck),
+        );
+
+        let stats = stats();
+        for _ in 0..1000 {
+            intersection.update(0.016, Rc::clone(&stats));
+        }
+
+        let lane = &intersection.lanes[&Direction::North];
+        let gap = intersection.physics.bumper_gap(&lane[1], &lane[0]);
+        assert!(lane[1].velocity < 0.1);
+        assert!(gap > 0.0 && gap < MIN_GAP + 1.0);
+    }
+
+    #[test]
+    fn test_free_vehicle_keeps_cruise_velocity() {
+        let mut intersection = Intersection::new(10.0);
+        push(&mut intersection, Vehicle::new(1, (0.0, -100.0), 10.0, Route::Right, Direction::North, 100.0));
+
+        intersection.update(1.0, stats());
+
+        let vehicle = &intersection.lanes[&Direction::North][0];
+        assert_eq!(vehicle.velocity, 10.0);
+        assert_eq!(vehicle.distance_to_intersection, 90.0);
+    }
+
+    #[test]
+    fn test_ambulance_crosses_before_conflicting_traffic() {
+        let mut intersection = Intersection::new(10.0);
+        push(&mut intersection, Vehicle::new(1, (0.0, -45.0), 10.0, Route::Straight, Direction::North, 45.0));
+        push(
+            &mut intersection,
+            Vehicle::new(2, (-60.0, 0.0), 15.0, Route::Straight, Direction::East, 60.0)
+                .with_type(VehicleType::Ambulance),
+        );
+
+        let stats = stats();
+        let in_zone = |v: &Vehicle| v.distance_to_intersection.abs() < CONFLICT_ZONE_HALF_SIZE;
+        for _ in 0..1500 {
+            intersection.update(0.016, Rc::clone(&stats));
+            let crossing: Vec<&Vehicle> = intersection.lanes.values().flatten().filter(|v| in_zone(v)).collect();
+            assert!(crossing.len() < 2, "conflicting vehicles inside the zone together");
+        }
+
+        let stats = stats.borrow();
+        assert_eq!(stats.emergency_delay.count, 1);
+        assert_eq!(stats.regular_delay.count, 1);
+        assert!(stats.emergency_delay.max < 0.1);
+        assert!(stats.regular_delay.max > 1.0);
+    }
+
+    #[test]
+    fn test_human_driver_waits_for_gap() {
+        let mut intersection = Intersection::new(10.0);
+        push(&mut intersection, Vehicle::new(1, (0.0, -45.0), 10.0, Route::Straight, Direction::North, 45.0));
+        push(
+            &mut intersection,
+            Vehicle::new(2, (-30.0, 0.0), 10.0, Route::Straight, Direction::East, 30.0)
+                .with_driver(HumanDriver::new())
+                .with_behaviour(Behaviour { reaction_time: 1.0, critical_gap: 4.0, ..Behaviour::default() }),
+        );
+
+        let stats = stats();
+        let in_zone = |v: &Vehicle| v.distance_to_intersection.abs() < CONFLICT_ZONE_HALF_SIZE;
+        for _ in 0..1500 {
+            intersection.update(0.016, Rc::clone(&stats));
+            let crossing: Vec<&Vehicle> = intersection.lanes.values().flatten().filter(|v| in_zone(v)).collect();
+            assert!(crossing.len() < 2, "conflicting vehicles inside the zone together");
+        }
+
+        // The automated vehicle was already granted, so the human had to stop
+        let stats = stats.borrow();
+        assert_eq!(stats.regular_delay.count, 2);
+        assert!(stats.regular_delay.max > 1.0);
+    }
+
+    #[test]
+    fn test_signal_holds_red_approach_until_its_phase() {
+        let mut intersection = Intersection::new(10.0);
+        let phases = parse_phases(DEFAULT_PHASES).unwrap();
+        intersection.signal = Some(Signal::new("fixed:10:3:2".parse().unwrap(), phases));
+        push(&mut intersection, Vehicle::new(1, (0.0, -45.0), 10.0, Route::Straight, Direction::North, 45.0));
+        push(
+            &mut intersection,
+            Vehicle::new(2, (-30.0, 0.0), 10.0, Route::Straight, Direction::East, 30.0)
+                .with_driver(HumanDriver::new()),
+        );
+
+        let stats = stats();
+        let in_zone = |v: &Vehicle| v.distance_to_intersection.abs() < CONFLICT_ZONE_HALF_SIZE;
+        for _ in 0..2500 {
+            intersection.update(0.016, Rc::clone(&stats));
+            let crossing: Vec<&Vehicle> = intersection.lanes.values().flatten().filter(|v| in_zone(v)).collect();
+            assert!(crossing.len() < 2, "conflicting vehicles inside the zone together");
+        }
+
+        // The east approach only gets green in the third phase, 30 s in
+        let stats = stats.borrow();
+        assert_eq!(stats.regular_delay.count, 2);
+        assert!(stats.regular_delay.max > 25.0);
+    }
+
+    #[test]
+    fn test_sudden_stop_forces_follower_to_brake_hard() {
+        let mut intersection = Intersection::new(10.0);
+        push(
+            &mut intersection,
+            Vehicle::new(1, (0.0, -12.0), 10.0, Route::Straight, Direction::North, 12.0)
+                .with_rogue(Misbehaviour::SuddenStop),
+        );
+        // Close enough behind that it cannot stop in time by braking normally
+        push(&mut intersection, Vehicle::new(2, (0.0, -20.0), 10.0, Route::Straight, Direction::North, 20.0));
+
+        let stats = stats();
+        for _ in 0..20 {
+            intersection.update(0.016, Rc::clone(&stats));
+        }
+
+        let lane = &intersection.lanes[&Direction::North];
+        assert_eq!(lane[0].velocity, 0.0);
+        assert!(lane[1].velocity < 10.0);
+        assert_eq!(stats.borrow().emergency_brakings, 1);
+    }
+
+    /// Runs a rogue vehicle between two honest ones on the north approach,
+    /// with conflicting traffic from the east and south, for 48 s and
+    /// gets how many vehicles exited
+    fn exited_around_rogue(kind: Misbehaviour) -> u32 {
+        let mut intersection = Intersection::new(10.0);
+        push(&mut intersection, Vehicle::new(1, (0.0, -30.0), 10.0, Route::Straight, Direction::North, 30.0));
+        push(
+            &mut intersection,
+            Vehicle::new(2, (0.0, -45.0), 10.0, Route::Straight, Direction::North, 45.0).with_rogue(kind),
+        );
+        push(&mut intersection, Vehicle::new(3, (0.0, -70.0), 10.0, Route::Straight, Direction::North, 70.0));
+        push(&mut intersection, Vehicle::new(4, (-35.0, 0.0), 10.0, Route::Straight, Direction::East, 35.0));
+        push(&mut intersection, Vehicle::new(5, (0.0, 50.0), 10.0, Route::Left, Direction::South, 50.0));
+
+        let stats = stats();
+        for _ in 0..3000 {
+            intersection.update(0.016, Rc::clone(&stats));
+        }
+        let exited = stats.borrow().regular_delay.count;
+        exited
+    }
+
+    #[test]
+    fn test_traffic_continues_after_vehicle_ignoring_reservations() {
+        assert_eq!(exited_around_rogue(Misbehaviour::IgnoresReservation), 5);
+    }
+
+    #[test]
+    fn test_traffic_continues_after_speeding_vehicle() {
+        assert_eq!(exited_around_rogue(Misbehaviour::Speeding), 5);
+    }
+
+    #[test]
+    fn test_traffic_continues_after_sudden_stop() {
+        assert_eq!(exited_around_rogue(Misbehaviour::SuddenStop), 5);
+    }
+
+    #[test]
+    fn test_traffic_continues_after_false_position_vehicle() {
+        // Nobody is left stuck behind or across from the liar
+        assert_eq!(exited_around_rogue(Misbehaviour::FalsePosition), 5);
+    }
+}
+
 /* 
 
 #[cfg(test)]
@@ -0,0 +1,902 @@
+use std::collections::{HashMap, HashSet};
+use super::auction::{self, AuctionOutcome};
+use super::behaviour::DEFAULT_BUDGET;
+use super::conflict::{claims_conflict, vehicle_claim, Claim};
+use super::pedestrian::{Pedestrian, CROSSWALK_WIDTH};
+use super::physics::CONFLICT_ZONE_HALF_SIZE;
+use super::policy::{Batch, Policy};
+use super::vehicle::{Direction, Vehicle};
+
+/// Distance from the centre at which vehicles ask to cross (in meters)
+pub const REQUEST_DISTANCE: f32 = 50.0;
+
+/// Distance from the centre of the stop line where vehicles without a
+/// reservation wait, in front of the crosswalk (in meters)
+pub const STOP_LINE_DISTANCE: f32 = CONFLICT_ZONE_HALF_SIZE + CROSSWALK_WIDTH + 1.0;
+
+/// Who a request was made by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Agent {
+    Vehicle(u32),
+    Pedestrian(u32),
+}
+
+/// A request to use part of the intersection
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub agent: Agent,
+    pub claim: Claim,
+    /// Emergency vehicles pre-empt conflicting traffic
+    pub priority: bool,
+    /// Arrival order of the request (first come, first served)
+    pub sequence: u64,
+    /// Whether the vehicle holds a reservation and may enter the zone
+    pub granted: bool,
+    /// Human drivers ignore reservations; their request only tracks what they do
+    pub human: bool,
+    /// Entered the intersection without a reservation
+    pub intruder: bool,
+    /// Time spent waiting for the reservation so far (in seconds)
+    pub waited: f32,
+    /// Auction rounds lost to another bidder so far under the auction policy
+    pub losses: u32,
+    /// Latest bid under the auction policy, logged once the request is
+    /// granted or withdrawn
+    pub bid: Option<AuctionOutcome>,
+}
+
+impl Request {
+    /// Whether the request holds all conflicting traffic: priority
+    /// vehicles, crossing humans and intruders
+    pub fn overrides(&self) -> bool {
+        self.priority || (self.human && self.granted) || self.intruder
+    }
+}
+
+/// Central intersection manager handing out reservations for the conflict zone
+///
+/// Vehicles request a reservation once they are within `REQUEST_DISTANCE`,
+/// pedestrians as soon as they reach the curb. Requests are served first
+/// come, first served; a reservation is granted when no conflicting claim
+/// holds one or is waiting ahead in the queue, and is held until the vehicle
+/// has cleared the zone and its exit crosswalk, or the pedestrian has
+/// crossed. Priority vehicles are served first, hold all conflicting traffic
+/// while they pass and take back reservations from conflicting vehicles that
+/// can still stop. Pedestrians on a crosswalk are never interrupted.
+///
+/// Human drivers do not wait for a reservation. Their request is granted as
+/// soon as they decide to cross, which is treated like a priority claim:
+/// conflicting agents that can still stop lose their reservation and nothing
+/// conflicting is granted until the human has cleared the zone. Automated
+/// vehicles seen entering without a reservation are treated the same way.
+///
+/// Platoons are scheduled as one unit: once one member holds a reservation,
+/// the members behind it are let through too as long as nothing granted
+/// conflicts, even ahead of conflicting requests that arrived earlier.
+///
+/// Under the batch policy, waiting requests are instead granted in batches
+/// of mutually compatible claims (see `Policy::Batch`); priority vehicles
+/// and human drivers are handled the same way under every policy. Under the
+/// auction policy, the queue is ordered by bid instead of arrival.
+#[derive(Debug, Clone, Default)]
+pub struct IntersectionManager {
+    /// Open requests in arrival order
+    pub requests: Vec<Request>,
+    /// Sequence number the next request will get
+    pub next_sequence: u64,
+    /// How waiting requests are chosen to be granted
+    pub policy: Policy,
+    /// Batch being let through under the batch policy
+    pub batch: Batch,
+}
+
+impl IntersectionManager {
+    pub fn new() -> Self {
+        IntersectionManager::default()
+    }
+
+    /// Processes new requests and hands out reservations
+    ///
+    /// Returns the auction bidders that won or withdrew this step.
+    pub fn update(
+        &mut self,
+        lanes: &HashMap<Direction, Vec<Vehicle>>,
+        pedestrians: &[Pedestrian],
+        delta_time: f32,
+    ) -> Vec<AuctionOutcome> {
+        let vehicles: HashMap<u32, &Vehicle> = lanes
+            .values()
+            .flat_map(|lane| lane.iter())
+            .filter(|vehicle| vehicle.active)
+            .map(|vehicle| (vehicle.id, vehicle))
+            .collect();
+
+        // Release reservations of vehicles that cleared the zone, of
+        // pedestrians that reached the far curb, and of agents that left
+        let mut outcomes = Vec::new();
+        self.requests.retain(|request| {
+            let open = match request.agent {
+                Agent::Vehicle(id) => vehicles
+                    .get(&id)
+                    .is_some_and(|vehicle| !Self::has_cleared(vehicle)),
+                Agent::Pedestrian(id) => pedestrians
+                    .iter()
+                    .any(|pedestrian| pedestrian.id == id && pedestrian.active),
+            };
+            // A bidder that goes without winning withdrew from the auction
+            if let (false, Some(bid)) = (open, &request.bid) {
+                if !bid.won {
+                    outcomes.push(bid.clone());
+                }
+            }
+            open
+        });
+
+        // Register vehicles that came within range, nearest first so the
+        // arrival order does not depend on lane iteration order
+        let mut arriving: Vec<&Vehicle> = vehicles
+            .values()
+            .filter(|vehicle| vehicle.distance_to_intersection <= REQUEST_DISTANCE)
+            .filter(|vehicle| !Self::has_cleared(vehicle))
+            .filter(|vehicle| !self.has_request(Agent::Vehicle(vehicle.id)))
+            .copied()
+            .collect();
+        arriving.sort_by(|a, b| {
+            a.distance_to_intersection
+                .total_cmp(&b.distance_to_intersection)
+                .then(a.id.cmp(&b.id))
+        });
+        for vehicle in arriving {
+            // A vehicle already past the stop line cannot wait any more,
+            // unless it crept over the line while stopping for it
+            self.push_request(
+                Agent::Vehicle(vehicle.id),
+                vehicle_claim(vehicle),
+                vehicle.priority,
+                Self::is_committed(vehicle) && vehicle.velocity > 0.0,
+            );
+            if let Some(request) = self.requests.last_mut() {
+                request.human = vehicle.is_human();
+            }
+        }
+
+        // Human drivers take the zone when they decide to, not when granted
+        for request in self.requests.iter_mut().filter(|request| request.human) {
+            if let Agent::Vehicle(id) = request.agent {
+                request.granted = vehicles
+                    .get(&id)
+                    .and_then(|vehicle| vehicle.driver)
+                    .is_some_and(|driver| driver.decided);
+            }
+        }
+
+        // Register pedestrians that arrived at the curb, in spawn order
+        for pedestrian in pedestrians.iter().filter(|pedestrian| pedestrian.active) {
+            if !self.has_request(Agent::Pedestrian(pedestrian.id)) {
+                self.push_request(
+                    Agent::Pedestrian(pedestrian.id),
+                    Claim::Crosswalk(pedestrian.arm),
+                    false,
+                    pedestrian.crossing,
+                );
+            }
+        }
+
+        // Vehicles queued behind a human who has not gone yet cannot use a
+        // reservation, and holding one could keep that human waiting for them
+        let queued: HashSet<Agent> = vehicles
+            .values()
+            .filter(|vehicle| Self::behind_undecided_human(lanes, vehicle))
+            .map(|vehicle| Agent::Vehicle(vehicle.id))
+            .collect();
+        for request in self.requests.iter_mut().filter(|r| !r.human && queued.contains(&r.agent)) {
+            request.granted = false;
+        }
+
+        // A vehicle that entered without a reservation, after following the
+        // fallback rule or misbehaving, holds the zone anyway
+        for request in self.requests.iter_mut().filter(|request| !request.granted && !request.human) {
+            if let Agent::Vehicle(id) = request.agent {
+                request.intruder = vehicles.get(&id).is_some_and(|vehicle| Self::has_entered(vehicle));
+                request.granted = request.intruder;
+            }
+        }
+
+        let platoons: HashMap<Agent, u32> = vehicles
+            .values()
+            .filter_map(|vehicle| Some((Agent::Vehicle(vehicle.id), vehicle.platoon?)))
+            .collect();
+
+        for request in self.requests.iter_mut().filter(|request| !request.granted) {
+            request.waited += delta_time;
+        }
+
+        self.preempt_for_priority(&vehicles, pedestrians);
+        match self.policy {
+            Policy::FirstComeFirstServed => {
+                let order = self.arrival_order();
+                self.grant_requests(order, &queued, &platoons);
+            }
+            Policy::Batch { window, max_size } => {
+                self.update_batch(&queued, &platoons, window, max_size, delta_time);
+                self.grant_batch(&queued);
+            }
+            Policy::Auction => outcomes.extend(self.run_auction(&vehicles, &queued, &platoons)),
+        }
+        outcomes
+    }
+
+    /// Forgets all requests and reservations, as a manager restarting
+    /// after a failure does
+    pub fn restart(&mut self) {
+        self.requests.clear();
+        self.batch = Batch::default();
+    }
+
+    /// Whether `agent` has an open request
+    fn has_request(&self, agent: Agent) -> bool {
+        self.requests.iter().any(|request| request.agent == agent)
+    }
+
+    /// Appends a request at the end of the queue
+    fn push_request(&mut self, agent: Agent, claim: Claim, priority: bool, granted: bool) {
+        self.requests.push(Request {
+            agent,
+            claim,
+            priority,
+            sequence: self.next_sequence,
+            granted,
+            human: false,
+            intruder: false,
+            waited: 0.0,
+            losses: 0,
+            bid: None,
+        });
+        self.next_sequence += 1;
+    }
+
+    /// Takes back reservations that conflict with a priority vehicle, a
+    /// crossing human driver or an intruder from agents that can still stop
+    fn preempt_for_priority(&mut self, vehicles: &HashMap<u32, &Vehicle>, pedestrians: &[Pedestrian]) {
+        let priority: Vec<Claim> = self.requests
+            .iter()
+            .filter(|request| request.overrides())
+            .map(|request| request.claim)
+            .collect();
+        if priority.is_empty() {
+            return;
+        }
+
+        for request in self.requests.iter_mut().filter(|r| r.granted && !r.overrides()) {
+            let conflicts = priority.iter().any(|claim| claims_conflict(*claim, request.claim));
+            // Pedestrians already on the crosswalk are never stopped
+            let can_stop = match request.agent {
+                Agent::Vehicle(id) => vehicles.get(&id).is_some_and(|vehicle| Self::can_stop(vehicle)),
+                Agent::Pedestrian(id) => pedestrians
+                    .iter()
+                    .any(|pedestrian| pedestrian.id == id && !pedestrian.crossing),
+            };
+            if conflicts && can_stop {
+                request.granted = false;
+            }
+        }
+    }
+
+    /// Indices of the requests, priority vehicles first and then in arrival order
+    fn arrival_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.requests.len()).collect();
+        order.sort_by_key(|&i| (!self.requests[i].priority, self.requests[i].sequence));
+        order
+    }
+
+    /// Runs one round of the sealed-bid auction
+    ///
+    /// Waiting requests are served in order of their bids, priority
+    /// vehicles first and ties in arrival order. Pedestrians bid like a
+    /// vehicle with full urgency and the default budget. Losers keep
+    /// waiting and bid again, higher, in the next round.
+    ///
+    /// A round is held every step, so each bidder's latest bid is kept on
+    /// its request and only returned once, the first time it wins; bidders
+    /// that never win are returned when they withdraw (see `update`).
+    ///
+    /// A vehicle cannot pass the vehicle ahead in its lane, so a queue
+    /// competes with the highest bid in it: a vehicle bidding more than the
+    /// ones ahead raises their bids to its own.
+    fn run_auction(
+        &mut self,
+        vehicles: &HashMap<u32, &Vehicle>,
+        queued: &HashSet<Agent>,
+        platoons: &HashMap<Agent, u32>,
+    ) -> Vec<AuctionOutcome> {
+        let mut bids: Vec<f32> = self.requests
+            .iter()
+            .map(|request| match request.agent {
+                Agent::Vehicle(id) => vehicles.get(&id).map_or(0.0, |vehicle| {
+                    auction::bid(vehicle.behaviour.urgency, vehicle.behaviour.budget, request.waited)
+                }),
+                Agent::Pedestrian(_) => auction::bid(1.0, DEFAULT_BUDGET, request.waited),
+            })
+            .collect();
+
+        // Back of each queue first, passing the highest bid on to the front
+        let mut queue: Vec<(usize, &Vehicle)> = self.requests
+            .iter()
+            .enumerate()
+            .filter_map(|(index, request)| match request.agent {
+                Agent::Vehicle(id) => vehicles.get(&id).map(|vehicle| (index, *vehicle)),
+                Agent::Pedestrian(_) => None,
+            })
+            .collect();
+        queue.sort_by(|(_, a), (_, b)| b.distance_to_intersection.total_cmp(&a.distance_to_intersection));
+        let mut highest: HashMap<(Direction, usize), f32> = HashMap::new();
+        for (index, vehicle) in queue {
+            let lane_bid = highest.entry((vehicle.direction, vehicle.lane_index())).or_insert(0.0);
+            *lane_bid = lane_bid.max(bids[index]);
+            bids[index] = *lane_bid;
+        }
+        let bidders: Vec<usize> = (0..self.requests.len())
+            .filter(|&i| {
+                let request = &self.requests[i];
+                !request.granted && !request.priority && !request.human && !queued.contains(&request.agent)
+            })
+            .collect();
+
+        let mut order: Vec<usize> = (0..self.requests.len()).collect();
+        order.sort_by(|&a, &b| {
+            let (first, second) = (&self.requests[a], &self.requests[b]);
+            second.priority
+                .cmp(&first.priority)
+                .then(bids[b].total_cmp(&bids[a]))
+                .then(first.sequence.cmp(&second.sequence))
+        });
+        self.grant_requests(order, queued, platoons);
+
+        // A round in which nobody won does not count as lost
+        let decided = bidders.iter().any(|&index| self.requests[index].granted);
+        let mut outcomes = Vec::new();
+        for index in bidders {
+            let request = &mut self.requests[index];
+            if decided && !request.granted {
+                request.losses += 1;
+            }
+            // A reservation taken back and won again is not logged twice
+            if request.bid.as_ref().is_some_and(|bid| bid.won) {
+                continue;
+            }
+            if let Agent::Vehicle(id) = request.agent {
+                if let Some(vehicle) = vehicles.get(&id) {
+                    let bid = AuctionOutcome {
+                        id,
+                        approach: vehicle.direction,
+                        urgency: vehicle.behaviour.urgency,
+                        budget: vehicle.behaviour.budget,
+                        bid: bids[index],
+                        won: request.granted,
+                        losses: request.losses,
+                        waited: request.waited,
+                    };
+                    if bid.won {
+                        outcomes.push(bid.clone());
+                    }
+                    request.bid = Some(bid);
+                }
+            }
+        }
+        outcomes
+    }
+
+    /// Grants waiting requests in the given order
+    ///
+    /// Agents in `queued` are passed over without holding up anyone else.
+    /// Members of a platoon that is already crossing only have to wait for
+    /// conflicting reservations, not for the queue.
+    fn grant_requests(&mut self, order: Vec<usize>, queued: &HashSet<Agent>, platoons: &HashMap<Agent, u32>) {
+
+        // Claims of priority vehicles, crossing humans and intruders hold all
+        // conflicting traffic
+        let held: Vec<Claim> = self.requests
+            .iter()
+            .filter(|request| request.overrides())
+            .map(|request| request.claim)
+            .collect();
+
+        // Claims granted or waiting ahead in the queue block conflicting requests
+        let mut granted: Vec<Claim> = self.requests
+            .iter()
+            .filter(|request| request.granted)
+            .map(|request| request.claim)
+            .collect();
+        let mut blocking = granted.clone();
+        let mut crossing_platoons: HashSet<u32> = self.requests
+            .iter()
+            .filter(|request| request.granted)
+            .filter_map(|request| platoons.get(&request.agent).copied())
+            .collect();
+
+        for index in order {
+            let request = &mut self.requests[index];
+            // Humans still making up their mind neither get nor block
+            // anything, and neither do the vehicles stuck behind them
+            if request.granted || request.human || queued.contains(&request.agent) {
+                continue;
+            }
+            let platoon = platoons.get(&request.agent).copied();
+            let ahead = if platoon.is_some_and(|id| crossing_platoons.contains(&id)) {
+                &granted
+            } else {
+                &blocking
+            };
+            let free = !ahead.iter().any(|claim| claims_conflict(*claim, request.claim));
+            let not_held = request.priority
+                || !held.iter().any(|claim| claims_conflict(*claim, request.claim));
+            if free && not_held {
+                request.granted = true;
+                granted.push(request.claim);
+                crossing_platoons.extend(platoon);
+            }
+            blocking.push(request.claim);
+        }
+    }
+
+    /// Forms the next batch once the current one has gone through and
+    /// requests have been collected for `window` seconds
+    ///
+    /// Members that left or got stuck behind an undecided human are dropped
+    /// from the batch, and waiting platoon members join the batch of their
+    /// platoon. Like under first come, first served, priority requests,
+    /// human drivers and vehicles queued behind them are never batched.
+    fn update_batch(
+        &mut self,
+        queued: &HashSet<Agent>,
+        platoons: &HashMap<Agent, u32>,
+        window: f32,
+        max_size: usize,
+        delta_time: f32,
+    ) {
+        let mut waiting: Vec<&Request> = self.requests
+            .iter()
+            .filter(|request| !request.granted && !request.priority && !request.human)
+            .filter(|request| !queued.contains(&request.agent))
+            .collect();
+        waiting.sort_by_key(|request| request.sequence);
+
+        let open: HashSet<Agent> = self.requests.iter().map(|request| request.agent).collect();
+        let batch = &mut self.batch;
+        batch.members.retain(|agent| open.contains(agent) && !queued.contains(agent));
+
+        if !batch.members.is_empty() {
+            let mut claims: Vec<Claim> = self.requests
+                .iter()
+                .filter(|request| batch.members.contains(&request.agent))
+                .map(|request| request.claim)
+                .collect();
+            let member_platoons: HashSet<u32> = batch.members
+                .iter()
+                .filter_map(|agent| platoons.get(agent).copied())
+                .collect();
+            for request in waiting {
+                let joins = platoons
+                    .get(&request.agent)
+                    .is_some_and(|id| member_platoons.contains(id));
+                let compatible = !claims.iter().any(|claim| claims_conflict(*claim, request.claim));
+                if joins && compatible && !batch.members.contains(&request.agent) {
+                    batch.members.push(request.agent);
+                    claims.push(request.claim);
+                }
+            }
+            return;
+        }
+
+        if waiting.is_empty() {
+            batch.collecting = 0.0;
+            return;
+        }
+        batch.collecting += delta_time;
+        if batch.collecting < window {
+            return;
+        }
+
+        // Greedily group compatible claims in arrival order
+        let mut claims: Vec<Claim> = Vec::new();
+        for request in waiting {
+            if batch.members.len() >= max_size {
+                break;
+            }
+            if !claims.iter().any(|claim| claims_conflict(*claim, request.claim)) {
+                claims.push(request.claim);
+                batch.members.push(request.agent);
+            }
+        }
+        batch.collecting = 0.0;
+    }
+
+    /// Grants priority requests and the members of the current batch
+    ///
+    /// Batch members are compatible with each other, so they only wait for
+    /// conflicting reservations still held from before and for claims of
+    /// priority vehicles and crossing humans.
+    fn grant_batch(&mut self, queued: &HashSet<Agent>) {
+        let order = self.arrival_order();
+
+        let held: Vec<Claim> = self.requests
+            .iter()
+            .filter(|request| request.overrides())
+            .map(|request| request.claim)
+            .collect();
+        let mut granted: Vec<Claim> = self.requests
+            .iter()
+            .filter(|request| request.granted)
+            .map(|request| request.claim)
+            .collect();
+
+        for index in order {
+            let request = &mut self.requests[index];
+            let eligible = request.priority || self.batch.members.contains(&request.agent);
+            if request.granted || request.human || queued.contains(&request.agent) || !eligible {
+                continue;
+            }
+            let free = !granted.iter().any(|claim| claims_conflict(*claim, request.claim));
+            let not_held = request.priority
+                || !held.iter().any(|claim| claims_conflict(*claim, request.claim));
+            if free && not_held {
+                request.granted = true;
+                granted.push(request.claim);
+            }
+        }
+    }
+
+    /// Whether a human driver who has not decided to cross yet is ahead of
+    /// `vehicle` in its lane
+    pub fn behind_undecided_human(lanes: &HashMap<Direction, Vec<Vehicle>>, vehicle: &Vehicle) -> bool {
+        lanes.get(&vehicle.direction).is_some_and(|lane| {
+            lane.iter().any(|other| {
+                other.active
+                    && other.lane_index() == vehicle.lane_index()
+                    && other.distance_to_intersection < vehicle.distance_to_intersection
+                    && other.driver.is_some_and(|driver| !driver.decided)
+            })
+        })
+    }
+
+    /// Whether the front of the vehicle has passed the stop line
+    pub fn is_committed(vehicle: &Vehicle) -> bool {
+        vehicle.distance_to_intersection - vehicle.spec().length / 2.0 < STOP_LINE_DISTANCE
+    }
+
+    /// Whether the front of the vehicle has driven onto the crosswalk in
+    /// front of the conflict zone and the vehicle has not cleared the zone
+    ///
+    /// Vehicles stopping at the stop line may creep over it, so only this
+    /// counts as entering the intersection.
+    pub fn has_entered(vehicle: &Vehicle) -> bool {
+        let front = vehicle.distance_to_intersection - vehicle.spec().length / 2.0;
+        front < CONFLICT_ZONE_HALF_SIZE + CROSSWALK_WIDTH && !Self::has_cleared(vehicle)
+    }
+
+    /// Whether the rear of the vehicle has left the conflict zone and the
+    /// crosswalk behind it
+    pub fn has_cleared(vehicle: &Vehicle) -> bool {
+        vehicle.distance_to_intersection + vehicle.spec().length / 2.0
+            < -(CONFLICT_ZONE_HALF_SIZE + CROSSWALK_WIDTH)
+    }
+
+    /// Whether the vehicle can still brake to a stop before the stop line,
+    /// or, once it crept over the line, before driving onto the crosswalk
+    pub fn can_stop(vehicle: &Vehicle) -> bool {
+        let front = vehicle.distance_to_intersection - vehicle.spec().length / 2.0;
+        let braking_distance = vehicle.velocity.powi(2) / (2.0 * vehicle.spec().max_deceleration);
+        let line = if Self::is_committed(vehicle) { CONFLICT_ZONE_HALF_SIZE + CROSSWALK_WIDTH } else { STOP_LINE_DISTANCE };
+        front - line >= braking_distance
+    }
+
+    /// Whether the vehicle has to stop at the stop line
+    ///
+    /// Vehicles that have cleared the zone no longer need a reservation.
+    pub fn must_wait(&self, vehicle: &Vehicle) -> bool {
+        !Self::has_cleared(vehicle) && !self.is_granted(vehicle.id)
+    }
+
+    /// Whether the vehicle currently holds a reservation
+    pub fn is_granted(&self, vehicle_id: u32) -> bool {
+        self.holds(Agent::Vehicle(vehicle_id))
+    }
+
+    /// Whether the pedestrian may step onto (or stay on) the crosswalk
+    pub fn may_cross(&self, pedestrian_id: u32) -> bool {
+        self.holds(Agent::Pedestrian(pedestrian_id))
+    }
+
+    fn holds(&self, agent: Agent) -> bool {
+        self.requests
+            .iter()
+            .any(|request| request.agent == agent && request.granted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::driver::HumanDriver;
+    use crate::simulation::vehicle::Route;
+    use crate::simulation::vehicle_type::VehicleType;
+    use crate::simulation::test_support::{lanes, vehicle};
+
+    const TICK: f32 = 0.1;
+
+    #[test]
+    fn test_far_vehicles_do_not_request() {
+        let mut manager = IntersectionManager::new();
+        manager.update(&lanes(vec![vehicle(1, Direction::North, Route::Straight, 80.0)]), &[], TICK);
+        assert!(manager.requests.is_empty());
+    }
+
+    #[test]
+    fn test_first_come_first_served() {
+        let mut manager = IntersectionManager::new();
+        manager.update(&lanes(vec![
+            vehicle(1, Direction::North, Route::Straight, 30.0),
+            vehicle(2, Direction::East, Route::Straight, 40.0),
+            vehicle(3, Direction::South, Route::Right, 45.0),
+            vehicle(4, Direction::South, Route::Straight, 48.0),
+        ]), &[], TICK);
+
+        assert!(manager.is_granted(1));
+        assert!(!manager.is_granted(2));
+        // Compatible with everything ahead of it in the queue
+        assert!(manager.is_granted(3));
+        // Compatible with the granted vehicle, but not with the waiting one
+        assert!(!manager.is_granted(4));
+    }
+
+    #[test]
+    fn test_reservation_released_after_clearing() {
+        let mut manager = IntersectionManager::new();
+        let mut first = vehicle(1, Direction::North, Route::Straight, 30.0);
+        let second = vehicle(2, Direction::East, Route::Straight, 40.0);
+        manager.update(&lanes(vec![first.clone(), second.clone()]), &[], TICK);
+        assert!(!manager.is_granted(2));
+
+        first.distance_to_intersection = -20.0;
+        manager.update(&lanes(vec![first, second]), &[], TICK);
+        assert_eq!(manager.requests.len(), 1);
+        assert!(manager.is_granted(2));
+    }
+
+    #[test]
+    fn test_committed_vehicle_is_granted() {
+        let mut manager = IntersectionManager::new();
+        manager.update(&lanes(vec![vehicle(1, Direction::North, Route::Straight, 30.0)]), &[], TICK);
+        manager.update(&lanes(vec![
+            vehicle(1, Direction::North, Route::Straight, 30.0),
+            vehicle(2, Direction::East, Route::Straight, 5.0),
+        ]), &[], TICK);
+        assert!(manager.is_granted(2));
+    }
+
+    #[test]
+    fn test_priority_vehicle_preempts_conflicting_traffic() {
+        let mut manager = IntersectionManager::new();
+        let regular = vehicle(1, Direction::North, Route::Straight, 45.0);
+        manager.update(&lanes(vec![regular.clone()]), &[], TICK);
+        assert!(manager.is_granted(1));
+
+        let ambulance = vehicle(2, Direction::East, Route::Straight, 48.0)
+            .with_type(VehicleType::Ambulance);
+        let late = vehicle(3, Direction::West, Route::Left, 40.0);
+        manager.update(&lanes(vec![regular, ambulance, late]), &[], TICK);
+
+        // The regular vehicle can still stop, so it loses its reservation
+        assert!(!manager.is_granted(1));
+        assert!(manager.is_granted(2));
+        // Cross traffic arriving later is held while the ambulance passes
+        assert!(!manager.is_granted(3));
+    }
+
+    #[test]
+    fn test_priority_waits_for_vehicle_that_cannot_stop() {
+        let mut manager = IntersectionManager::new();
+        let regular = vehicle(1, Direction::North, Route::Straight, 16.0);
+        manager.update(&lanes(vec![regular.clone()]), &[], TICK);
+
+        let ambulance = vehicle(2, Direction::East, Route::Straight, 48.0)
+            .with_type(VehicleType::Ambulance);
+        manager.update(&lanes(vec![regular, ambulance]), &[], TICK);
+
+        assert!(manager.is_granted(1));
+        assert!(!manager.is_granted(2));
+    }
+
+    #[test]
+    fn test_right_turn_waits_for_cyclist() {
+        let mut manager = IntersectionManager::new();
+        let bicycle = vehicle(1, Direction::West, Route::Straight, 20.0).with_type(VehicleType::Bicycle);
+        let turning = vehicle(2, Direction::West, Route::Right, 40.0);
+        let straight = vehicle(3, Direction::West, Route::Straight, 45.0);
+        manager.update(&lanes(vec![bicycle, turning, straight]), &[], TICK);
+
+        assert!(manager.is_granted(1));
+        assert!(!manager.is_granted(2));
+        assert!(manager.is_granted(3));
+    }
+
+    #[test]
+    fn test_pedestrians_share_the_queue() {
+        let mut manager = IntersectionManager::new();
+        // Enters from the south arm
+        let first = vehicle(1, Direction::North, Route::Straight, 30.0);
+        let pedestrians = vec![
+            Pedestrian::new(1, Direction::South, true, 1.4),
+            Pedestrian::new(2, Direction::East, true, 1.4),
+        ];
+        manager.update(&lanes(vec![first.clone()]), &pedestrians, TICK);
+
+        assert!(manager.is_granted(1));
+        assert!(!manager.may_cross(1));
+        // The vehicle neither enters nor leaves by the east arm
+        assert!(manager.may_cross(2));
+
+        // A vehicle arriving after the waiting pedestrian has to wait too
+        let second = vehicle(2, Direction::South, Route::Straight, 45.0);
+        manager.update(&lanes(vec![first, second]), &pedestrians, TICK);
+        assert!(!manager.is_granted(2));
+    }
+
+    #[test]
+    fn test_human_driver_overrides_reservations() {
+        let mut manager = IntersectionManager::new();
+        let automated = vehicle(1, Direction::North, Route::Straight, 45.0);
+        let mut human = vehicle(2, Direction::East, Route::Straight, 20.0)
+            .with_driver(HumanDriver::new());
+        manager.update(&lanes(vec![automated.clone(), human.clone()]), &[], TICK);
+
+        // An undecided human neither holds nor blocks anything
+        assert!(manager.is_granted(1));
+        assert!(!manager.is_granted(2));
+
+        // Once the human goes, traffic that can still stop has to yield
+        if let Some(driver) = human.driver.as_mut() {
+            driver.decided = true;
+        }
+        let late = vehicle(3, Direction::South, Route::Straight, 48.0);
+        manager.update(&lanes(vec![automated, human, late]), &[], TICK);
+        assert!(manager.is_granted(2));
+        assert!(!manager.is_granted(1));
+        assert!(!manager.is_granted(3));
+    }
+
+    #[test]
+    fn test_intruder_holds_conflicting_traffic() {
+        let mut manager = IntersectionManager::new();
+        let automated = vehicle(1, Direction::North, Route::Straight, 45.0);
+        let mut intruder = vehicle(2, Direction::East, Route::Straight, 48.0);
+        manager.update(&lanes(vec![automated.clone(), intruder.clone()]), &[], TICK);
+        assert!(manager.is_granted(1));
+        assert!(!manager.is_granted(2));
+
+        // The second vehicle drives into the zone without its reservation
+        intruder.distance_to_intersection = 10.0;
+        manager.update(&lanes(vec![automated, intruder]), &[], TICK);
+        assert!(manager.requests.iter().any(|request| request.agent == Agent::Vehicle(2) && request.intruder));
+        assert!(manager.is_granted(2));
+        assert!(!manager.is_granted(1));
+    }
+
+    #[test]
+    fn test_vehicle_behind_undecided_human_waits_without_blocking() {
+        let mut manager = IntersectionManager::new();
+        let human = vehicle(1, Direction::West, Route::Straight, 17.0).with_driver(HumanDriver::new());
+        let behind = vehicle(2, Direction::West, Route::Straight, 25.0);
+        let cross = vehicle(3, Direction::North, Route::Straight, 40.0);
+        manager.update(&lanes(vec![human, behind, cross]), &[], TICK);
+
+        assert!(!manager.is_granted(2));
+        // Arrived later, but nothing that can actually go is in its way
+        assert!(manager.is_granted(3));
+    }
+
+    #[test]
+    fn test_platoon_crosses_as_one_unit() {
+        let mut manager = IntersectionManager::new();
+        let mut head = vehicle(1, Direction::North, Route::Straight, 30.0);
+        let cross = vehicle(2, Direction::East, Route::Straight, 40.0);
+        manager.update(&lanes(vec![head.clone(), cross.clone()]), &[], TICK);
+
+        // Behind the head, but it arrived after the conflicting vehicle
+        let mut member = vehicle(3, Direction::North, Route::Straight, 45.0);
+        manager.update(&lanes(vec![head.clone(), cross.clone(), member.clone()]), &[], TICK);
+        assert!(!manager.is_granted(3));
+
+        head.platoon = Some(1);
+        member.platoon = Some(1);
+        manager.update(&lanes(vec![head, cross, member]), &[], TICK);
+        assert!(manager.is_granted(3));
+        assert!(!manager.is_granted(2));
+    }
+
+    #[test]
+    fn test_batch_lets_compatible_movements_through_together() {
+        let mut manager = IntersectionManager::new();
+        manager.policy = Policy::Batch { window: 1.0, max_size: 3 };
+        let vehicles = vec![
+            vehicle(1, Direction::North, Route::Straight, 30.0),
+            vehicle(2, Direction::East, Route::Straight, 35.0),
+            vehicle(3, Direction::South, Route::Straight, 40.0),
+            vehicle(4, Direction::West, Route::Right, 45.0),
+            vehicle(5, Direction::South, Route::Right, 48.0),
+        ];
+        manager.update(&lanes(vehicles.clone()), &[], TICK);
+        // Still collecting requests
+        assert!(manager.requests.iter().all(|request| !request.granted));
+
+        for _ in 0..10 {
+            manager.update(&lanes(vehicles.clone()), &[], TICK);
+        }
+        // The opposing straight movements and a compatible right turn, but
+        // not the movements conflicting with them
+        let granted: Vec<u32> = (1..=5).filter(|&id| manager.is_granted(id)).collect();
+        assert_eq!(granted, vec![1, 3, 5]);
+
+        // The next batch waits until the whole current one has cleared
+        let mut vehicles = vehicles;
+        vehicles[0].distance_to_intersection = -20.0;
+        vehicles[2].distance_to_intersection = -20.0;
+        for _ in 0..20 {
+            manager.update(&lanes(vehicles.clone()), &[], TICK);
+        }
+        assert!(!manager.is_granted(2));
+
+        vehicles[4].distance_to_intersection = -20.0;
+        for _ in 0..20 {
+            manager.update(&lanes(vehicles.clone()), &[], TICK);
+        }
+        assert!(manager.is_granted(2));
+    }
+
+    #[test]
+    fn test_auction_serves_highest_bid_first() {
+        let mut manager = IntersectionManager::new();
+        manager.policy = Policy::Auction;
+        let mut patient = vehicle(1, Direction::North, Route::Straight, 30.0);
+        patient.behaviour.urgency = 0.1;
+        let mut urgent = vehicle(2, Direction::East, Route::Straight, 40.0);
+        urgent.behaviour.urgency = 0.9;
+
+        let outcomes = manager.update(&lanes(vec![patient.clone(), urgent.clone()]), &[], TICK);
+        assert!(!manager.is_granted(1));
+        assert!(manager.is_granted(2));
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!((outcomes[0].id, outcomes[0].won), (2, true));
+
+        // The loser keeps bidding, higher each round
+        urgent.distance_to_intersection = -20.0;
+        let outcomes = manager.update(&lanes(vec![patient.clone(), urgent]), &[], TICK);
+        assert!(manager.is_granted(1));
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].won);
+        assert_eq!(outcomes[0].losses, 1);
+        assert!(outcomes[0].bid > 10.0);
+
+        // The winner is not logged again while it keeps its reservation
+        assert!(manager.update(&lanes(vec![patient]), &[], TICK).is_empty());
+    }
+
+    #[test]
+    fn test_auction_logs_withdrawn_bidder() {
+        let mut manager = IntersectionManager::new();
+        manager.policy = Policy::Auction;
+        let mut patient = vehicle(1, Direction::North, Route::Straight, 30.0);
+        patient.behaviour.urgency = 0.1;
+        let mut urgent = vehicle(2, Direction::East, Route::Straight, 40.0);
+        urgent.behaviour.urgency = 0.9;
+        for _ in 0..3 {
+            manager.update(&lanes(vec![patient.clone(), urgent.clone()]), &[], TICK);
+        }
+
+        // The loser leaves without ever winning, having lost only the round
+        // the other vehicle won
+        let outcomes = manager.update(&lanes(vec![urgent]), &[], TICK);
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!((outcomes[0].id, outcomes[0].won, outcomes[0].losses), (1, false, 1));
+    }
+}
@@ -5,18 +5,47 @@ use crate::StatisticsManager;  // Add this import
 
 /// Expose the vehicle module so other modules can use Vehicle, Route, Direction
 pub mod vehicle;
+pub mod auction;
+pub mod behaviour;
 pub mod intersection;
+pub mod conflict;
+pub mod detector;
+pub mod driver;
+pub mod fallback;
+pub mod manager;
+pub mod monitor;
+pub mod pedestrian;
+pub mod platoon;
+pub mod policy;
+pub mod queue;
 pub(crate) mod physics;
+pub mod rng;
+pub mod rogue;
+pub mod signal;
+#[cfg(test)]
+mod test_support;
+pub mod v2i;
+pub mod v2v;
+pub mod vehicle_type;
 
-pub use vehicle::{Vehicle, Direction, Route};
+pub use vehicle::{Vehicle, Direction, Route, VehicleState};
 pub use intersection::Intersection;
+pub use pedestrian::Pedestrian;
+pub use vehicle_type::{VehicleMix, VehicleType};
+use auction::AuctionOutcome;
+use monitor::SafetyMonitor;
+use signal::Controller;
 
 /// Placeholder for simulation logic (vehicles, world updates)
 pub struct Simulation {
     pub intersection: Intersection,
     pub total_vehicles: u32,
+    /// Simulated time elapsed since the start of the run (in seconds)
+    pub time: f32,
     stats: Rc<RefCell<StatisticsManager>>,  // Add this field
 
+    /// Checks safety invariants after every step when set
+    pub monitor: Option<SafetyMonitor>,
 }
 
 impl Simulation {
@@ -25,7 +54,9 @@ impl Simulation {
         Simulation {
             intersection: Intersection::new(safe_distance),
             total_vehicles: 0,
+            time: 0.0,
             stats:stats,
+            monitor: None,
         }
     }
 
@@ -37,17 +68,221 @@ impl Simulation {
         }
     }
 
+    pub fn add_pedestrian(&mut self, pedestrian: Pedestrian) {
+        self.intersection.add_pedestrian(pedestrian);
+    }
+
     pub fn update(&mut self, delta_time: f32) {
+        if self.halted() {
+            return;
+        }
+        self.time += delta_time;
         self.intersection.update(delta_time, Rc::clone(&self.stats));
         // Update car count from active vehicles in intersection
+        if let Some(monitor) = self.monitor.as_mut() {
+            monitor.check(self.time, &self.intersection);
+        }
+    }
+
+    /// Whether the safety monitor stopped the run at a violation
+    pub fn halted(&self) -> bool {
+        self.monitor.as_ref().is_some_and(|monitor| monitor.halted)
+    }
+
+    /// Copy of the statistics collected so far
+    pub fn statistics(&self) -> StatisticsManager {
+        self.stats.borrow().clone()
+    }
+
+    /// Replaces the collected statistics, keeping the shared handle intact
+    pub fn restore_statistics(&mut self, stats: StatisticsManager) {
+        *self.stats.borrow_mut() = stats;
     }
 
     pub fn print_statistics(&self) {
         println!("\n=== Simulation Statistics ===");
         println!("Total vehicles processed: {}", self.total_vehicles);
         println!("Active vehicles: {}", self.intersection.total_vehicles());
-        for direction in &[Direction::North, Direction::South, Direction::East, Direction::West] {
-            println!("{:?}: {}", direction, self.intersection.vehicles_in_lane(*direction));
+        let queues = &self.intersection.queues;
+        for direction in queue::APPROACHES {
+            let queue = queues.approach(direction);
+            let longest = queues.longest_approach.get(&direction).copied().unwrap_or_default();
+            println!(
+                "{:?}: {} vehicles, {} queued ({:.1} m), longest queue {} vehicles ({:.1} m), {} spillbacks ({:.1} s)",
+                direction,
+                self.intersection.vehicles_in_lane(direction),
+                queue.vehicles,
+                queue.length,
+                longest.vehicles,
+                longest.length,
+                queues.spillbacks.get(&direction).copied().unwrap_or(0),
+                queues.spillback_time.get(&direction).copied().unwrap_or(0.0),
+            );
+        }
+        let stats = self.stats.borrow();
+        println!(
+            "Regular vehicles: {} exited, average delay {:.2} s, max delay {:.2} s",
+            stats.regular_delay.count,
+            stats.regular_delay.average(),
+            stats.regular_delay.max,
+        );
+        println!(
+            "Emergency vehicles: {} exited, average delay {:.2} s, max delay {:.2} s",
+            stats.emergency_delay.count,
+            stats.emergency_delay.average(),
+            stats.emergency_delay.max,
+        );
+        println!(
+            "Cyclists: {} exited, average delay {:.2} s, max delay {:.2} s",
+            stats.cyclist_delay.count,
+            stats.cyclist_delay.average(),
+            stats.cyclist_delay.max,
+        );
+        println!(
+            "Pedestrians: {} crossed, average wait {:.2} s, max wait {:.2} s, close calls {}",
+            stats.pedestrian_wait.count,
+            stats.pedestrian_wait.average(),
+            stats.pedestrian_wait.max,
+            stats.pedestrian_close_calls,
+        );
+        println!("Conflicting vehicles inside the intersection together: {}", stats.zone_conflicts);
+        println!("Collisions: {}, emergency braking events: {}", stats.collisions, stats.emergency_brakings);
+        println!(
+            "Platoons: {} crossed, average size {:.2}, largest {}",
+            stats.platoon_size.count,
+            stats.platoon_size.average(),
+            stats.platoon_size.max,
+        );
+        println!("Throughput: {:.2} vehicles per minute", self.throughput());
+        println!(
+            "Policy: {}, delay fairness (Jain's index) {:.3}",
+            self.intersection.manager.policy,
+            stats.regular_delay.fairness(),
+        );
+        if let Some(negotiation) = &self.intersection.negotiation {
+            println!(
+                "Control: V2V negotiation, range {:.0} m, {} intentions heard",
+                negotiation.range,
+                negotiation.messages,
+            );
+        }
+        if let Some(signal) = &self.intersection.signal {
+            let phases: Vec<String> = signal.phases.iter().map(|phase| phase.to_string()).collect();
+            match signal.controller {
+                Controller::FixedTime { .. } => println!(
+                    "Control: fixed-time signal {}, phases {}, cycle {:.1} s; {} phase changes",
+                    signal.controller,
+                    phases.join(","),
+                    signal.cycle_length(),
+                    signal.phase_changes,
+                ),
+                Controller::Actuated { .. } => println!(
+                    "Control: actuated signal {}, phases {}; {} phase changes, {} gap-outs, {} max-outs",
+                    signal.controller,
+                    phases.join(","),
+                    signal.phase_changes,
+                    signal.gap_outs,
+                    signal.max_outs,
+                ),
+                Controller::MaxPressure { .. } => println!(
+                    "Control: max-pressure signal {}, phases {}; {} phase changes",
+                    signal.controller,
+                    phases.join(","),
+                    signal.phase_changes,
+                ),
+            }
+        }
+        if self.intersection.all_way_stop.is_some() {
+            println!("Control: all-way stop, right of way by arrival order and the right-hand rule");
+        }
+        if let Some(link) = &self.intersection.v2i {
+            println!(
+                "V2I channel: latency {:.3} s, jitter {:.3} s, drop rate {:.3}; {} messages sent, {} dropped, {} unauthorized entries",
+                link.config.latency,
+                link.config.jitter,
+                link.config.drop_rate,
+                link.sent,
+                link.dropped,
+                link.unauthorized_entries,
+            );
+        }
+        let fallback = &self.intersection.fallback;
+        if fallback.activations > 0 || !fallback.outages.is_empty() {
+            println!(
+                "Manager fallback: {} activations, {:.1} s under all-way-stop",
+                fallback.activations,
+                fallback.time_active,
+            );
+        }
+        if !stats.auction_log.is_empty() {
+            Self::print_auction_statistics(&stats.auction_log);
+        }
+        if let Some(detectors) = &self.intersection.detectors {
+            println!("Detectors: {} full intervals of {:.0} s", detectors.records.len() / detectors.loops.len().max(1), detectors.interval);
+            for total in detectors.totals() {
+                println!(
+                    "  {}: {} vehicles, flow {:.0} veh/h, occupancy {:.1}%, mean speed {}",
+                    total.detector,
+                    total.count,
+                    total.flow(),
+                    total.occupancy() * 100.0,
+                    total.mean_speed().map_or("-".to_string(), |speed| format!("{:.2} m/s", speed)),
+                );
+            }
+        }
+        if let Some(monitor) = &self.monitor {
+            let counts: Vec<String> = monitor
+                .counts()
+                .iter()
+                .map(|(invariant, count)| format!("{} {}", invariant, count))
+                .collect();
+            println!("Safety monitor: {} violations ({})", monitor.violations.len(), counts.join(", "));
+            if monitor.halted {
+                println!("Run halted at {:.3} s:", self.time);
+                for violation in monitor.latest() {
+                    println!("{}", violation);
+                }
+            }
+        }
+    }
+
+    /// Prints the share of bids won and the prices paid and waiting times of
+    /// auction winners per approach, with the fairness and urgency-weighted
+    /// efficiency of the waits
+    fn print_auction_statistics(log: &[AuctionOutcome]) {
+        let won = auction::winners(log).count();
+        println!("Auctions: {} bidders, {} won", log.len(), won);
+        for direction in [Direction::North, Direction::South, Direction::East, Direction::West] {
+            let (waits, paid) = auction::approach_summary(log, direction);
+            let (bids, win_rate) = auction::approach_win_rate(log, direction);
+            println!(
+                "  {:?}: {} won of {} bids ({:.1}%), average winning bid {:.2}, average wait {:.2} s, max wait {:.2} s, fairness {:.3}",
+                direction,
+                waits.count,
+                bids,
+                win_rate * 100.0,
+                paid,
+                waits.average(),
+                waits.max,
+                waits.fairness(),
+            );
+        }
+        let average_wait = auction::winners(log).map(|outcome| outcome.waited).sum::<f32>() / won.max(1) as f32;
+        println!(
+            "  Average wait {:.2} s, urgency-weighted {:.2} s",
+            average_wait,
+            auction::urgency_weighted_wait(log),
+        );
+    }
+
+    /// Vehicles that left the intersection per minute of simulated time
+    pub fn throughput(&self) -> f32 {
+        let stats = self.stats.borrow();
+        let exited = stats.regular_delay.count + stats.emergency_delay.count + stats.cyclist_delay.count;
+        if self.time > 0.0 {
+            exited as f32 * 60.0 / self.time
+        } else {
+            0.0
         }
     }
 }
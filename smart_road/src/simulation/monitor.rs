@@ -0,0 +1,299 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+use super::intersection::Intersection;
+use super::manager::IntersectionManager;
+use super::vehicle::Vehicle;
+
+/// How far a speed may exceed the vehicle type's top speed before it counts
+/// as a violation (in m/s), to allow for rounding
+pub const SPEED_TOLERANCE: f32 = 0.01;
+
+/// Safety properties checked after every simulation step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Invariant {
+    /// No two vehicle footprints overlap
+    FootprintOverlap,
+    /// No vehicle drives into the intersection without clearance
+    EnteredWithoutClearance,
+    /// Speeds are never negative or above the vehicle type's top speed
+    SpeedOutOfRange,
+    /// Every vehicle is in exactly one lane, the one of its direction
+    InTwoLanes,
+}
+
+impl Invariant {
+    /// Short name used in reports
+    pub fn name(&self) -> &'static str {
+        match self {
+            Invariant::FootprintOverlap => "overlap",
+            Invariant::EnteredWithoutClearance => "no_clearance",
+            Invariant::SpeedOutOfRange => "speed",
+            Invariant::InTwoLanes => "two_lanes",
+        }
+    }
+}
+
+impl fmt::Display for Invariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// What the monitor does when an invariant is violated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonitorMode {
+    /// Records the violation and lets the run go on
+    Record,
+    /// Records the violation and stops the run where it happened
+    Halt,
+}
+
+impl FromStr for MonitorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "record" => Ok(MonitorMode::Record),
+            "halt" => Ok(MonitorMode::Halt),
+            _ => Err(format!("Unknown monitor mode: {}", s)),
+        }
+    }
+}
+
+/// One invariant violation, with the state of the vehicles involved
+#[derive(Debug, Clone)]
+pub struct Violation {
+    pub time: f32,
+    pub invariant: Invariant,
+    pub detail: String,
+    pub vehicles: Vec<Vehicle>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{:.3} s] {}: {}", self.time, self.invariant, self.detail)?;
+        for vehicle in &self.vehicles {
+            write!(
+                f,
+                "\n  vehicle {} ({}, lane {}, {:?}): distance {:.2} m, velocity {:.2} m/s, \
+                 position ({:.2}, {:.2}), cruise {:.2} m/s",
+                vehicle.id,
+                vehicle.vehicle_type,
+                vehicle.lane_id(),
+                vehicle.route,
+                vehicle.distance_to_intersection,
+                vehicle.velocity,
+                vehicle.position.0,
+                vehicle.position.1,
+                vehicle.cruise_velocity,
+            )?;
+            if vehicle.is_human() {
+                write!(f, ", human driver")?;
+            }
+            if let Some(rogue) = vehicle.rogue {
+                write!(f, ", rogue ({})", rogue.kind)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks safety invariants after every simulation step
+///
+/// A violation that lasts several steps (two vehicles overlapping for a
+/// while, say) is reported once, when it starts.
+#[derive(Debug, Clone)]
+pub struct SafetyMonitor {
+    pub mode: MonitorMode,
+    pub violations: Vec<Violation>,
+    /// Set in `Halt` mode once an invariant was violated
+    pub halted: bool,
+    /// Violations seen in the last check, by invariant and vehicle ids
+    ongoing: HashSet<(Invariant, u32, u32)>,
+    /// Vehicles that had not driven into the intersection yet
+    approaching: HashSet<u32>,
+}
+
+impl SafetyMonitor {
+    pub fn new(mode: MonitorMode) -> Self {
+        SafetyMonitor {
+            mode,
+            violations: Vec::new(),
+            halted: false,
+            ongoing: HashSet::new(),
+            approaching: HashSet::new(),
+        }
+    }
+
+    /// Checks every invariant against the intersection at `time`
+    pub fn check(&mut self, time: f32, intersection: &Intersection) {
+        let mut found: Vec<(Invariant, u32, u32, String)> = Vec::new();
+
+        for (a, b) in intersection.find_collisions() {
+            found.push((Invariant::FootprintOverlap, a, b, format!("vehicles {} and {} overlap", a, b)));
+        }
+
+        let vehicles: Vec<&Vehicle> = intersection.lanes.values().flatten().collect();
+        let mut lanes: HashMap<u32, usize> = HashMap::new();
+        for (direction, lane) in &intersection.lanes {
+            for vehicle in lane {
+                *lanes.entry(vehicle.id).or_default() += 1;
+                if vehicle.direction != *direction {
+                    let detail = format!("vehicle {} driving {} is in the {} lane", vehicle.id, vehicle.direction, direction);
+                    found.push((Invariant::InTwoLanes, vehicle.id, vehicle.id, detail));
+                }
+            }
+        }
+        for (id, count) in lanes.into_iter().filter(|(_, count)| *count > 1) {
+            found.push((Invariant::InTwoLanes, id, id, format!("vehicle {} is in {} lanes", id, count)));
+        }
+
+        for vehicle in &vehicles {
+            let max_speed = vehicle.spec().max_speed;
+            if !(0.0..=max_speed + SPEED_TOLERANCE).contains(&vehicle.velocity) {
+                let detail = format!(
+                    "vehicle {} drives {:.2} m/s, top speed {:.2} m/s",
+                    vehicle.id, vehicle.velocity, max_speed,
+                );
+                found.push((Invariant::SpeedOutOfRange, vehicle.id, vehicle.id, detail));
+            }
+        }
+
+        // Clearance is checked in the step the vehicle drives onto the
+        // crosswalk in front of the conflict zone, before the manager could
+        // have noticed it entered
+        let mut approaching = HashSet::new();
+        for vehicle in &vehicles {
+            if !IntersectionManager::has_entered(vehicle) && !IntersectionManager::has_cleared(vehicle) {
+                approaching.insert(vehicle.id);
+            } else if self.approaching.contains(&vehicle.id)
+                && IntersectionManager::has_entered(vehicle)
+                && !intersection.has_clearance(vehicle)
+            {
+                let detail = format!("vehicle {} entered the intersection without clearance", vehicle.id);
+                found.push((Invariant::EnteredWithoutClearance, vehicle.id, vehicle.id, detail));
+            }
+        }
+        self.approaching = approaching;
+
+        let mut ongoing = HashSet::new();
+        for (invariant, a, b, detail) in found {
+            ongoing.insert((invariant, a, b));
+            if self.ongoing.contains(&(invariant, a, b)) {
+                continue;
+            }
+            let involved = vehicles
+                .iter()
+                .filter(|vehicle| vehicle.id == a || vehicle.id == b)
+                .map(|vehicle| (*vehicle).clone())
+                .collect();
+            self.violations.push(Violation { time, invariant, detail, vehicles: involved });
+            if self.mode == MonitorMode::Halt {
+                self.halted = true;
+            }
+        }
+        self.ongoing = ongoing;
+    }
+
+    /// Violations found in the latest check that found any
+    pub fn latest(&self) -> &[Violation] {
+        let time = self.violations.last().map_or(0.0, |violation| violation.time);
+        let start = self.violations.iter().rposition(|violation| violation.time != time).map_or(0, |index| index + 1);
+        &self.violations[start..]
+    }
+
+    /// Number of violations recorded per invariant, in a fixed order
+    pub fn counts(&self) -> Vec<(Invariant, usize)> {
+        [
+            Invariant::FootprintOverlap,
+            Invariant::EnteredWithoutClearance,
+            Invariant::SpeedOutOfRange,
+            Invariant::InTwoLanes,
+        ]
+        .into_iter()
+        .map(|invariant| {
+            let count = self.violations.iter().filter(|violation| violation.invariant == invariant).count();
+            (invariant, count)
+        })
+        .collect()
+    }
+
+    /// Writes every recorded violation as a detailed text report
+    pub fn write_report<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "Safety monitor: {} violations", self.violations.len())?;
+        for violation in &self.violations {
+            writeln!(writer, "{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::StatisticsManager;
+    use super::super::rogue::Misbehaviour;
+    use super::super::vehicle::{Direction, Route};
+
+    fn vehicle(id: u32, direction: Direction, distance: f32) -> Vehicle {
+        Vehicle::new(id, (0.0, 0.0), 10.0, Route::Straight, direction, distance)
+    }
+
+    #[test]
+    fn test_overlap_reported_once() {
+        let mut intersection = Intersection::new(10.0);
+        let lane = intersection.lanes.get_mut(&Direction::North).unwrap();
+        lane.push(vehicle(1, Direction::North, 40.0));
+        lane.push(vehicle(2, Direction::North, 42.0));
+
+        let mut monitor = SafetyMonitor::new(MonitorMode::Record);
+        monitor.check(0.0, &intersection);
+        monitor.check(0.1, &intersection);
+        assert_eq!(monitor.violations.len(), 1);
+        assert_eq!(monitor.violations[0].invariant, Invariant::FootprintOverlap);
+        assert_eq!(monitor.violations[0].vehicles.len(), 2);
+        assert!(!monitor.halted);
+    }
+
+    #[test]
+    fn test_speed_and_lane_violations() {
+        let mut intersection = Intersection::new(10.0);
+        let mut fast = vehicle(1, Direction::North, 80.0);
+        fast.velocity = 100.0;
+        intersection.lanes.get_mut(&Direction::North).unwrap().push(fast);
+        intersection.lanes.get_mut(&Direction::South).unwrap().push(vehicle(2, Direction::East, 80.0));
+
+        let mut monitor = SafetyMonitor::new(MonitorMode::Halt);
+        monitor.check(0.0, &intersection);
+        let counts = monitor.counts();
+        assert!(counts.contains(&(Invariant::SpeedOutOfRange, 1)));
+        assert!(counts.contains(&(Invariant::InTwoLanes, 1)));
+        assert!(monitor.halted);
+    }
+
+    #[test]
+    fn test_entering_without_clearance() {
+        let mut intersection = Intersection::new(10.0);
+        intersection.lanes.get_mut(&Direction::North).unwrap()
+            .push(vehicle(1, Direction::North, 30.0));
+        intersection.lanes.get_mut(&Direction::East).unwrap()
+            .push(vehicle(2, Direction::East, 40.0).with_rogue(Misbehaviour::IgnoresReservation));
+        let stats = Rc::new(RefCell::new(StatisticsManager::new()));
+
+        let mut monitor = SafetyMonitor::new(MonitorMode::Record);
+        for step in 0..300 {
+            intersection.update(0.016, Rc::clone(&stats));
+            monitor.check(step as f32 * 0.016, &intersection);
+        }
+        let entered: Vec<&Violation> = monitor.violations
+            .iter()
+            .filter(|violation| violation.invariant == Invariant::EnteredWithoutClearance)
+            .collect();
+        assert_eq!(entered.len(), 1);
+        assert_eq!(entered[0].vehicles[0].id, 2);
+    }
+}
@@ -0,0 +1,146 @@
+use super::conflict::{exit_direction, opposite};
+use super::physics::CONFLICT_ZONE_HALF_SIZE;
+use super::vehicle::{Direction, Vehicle};
+
+/// Width of a crosswalk along the road (in meters)
+pub const CROSSWALK_WIDTH: f32 = 3.0;
+
+/// Length of a crosswalk from curb to curb (in meters)
+pub const CROSSWALK_LENGTH: f32 = CONFLICT_ZONE_HALF_SIZE * 2.0;
+
+/// Typical walking speed (in m/s)
+pub const WALKING_SPEED: f32 = 1.4;
+
+/// A person crossing one arm of the intersection on its crosswalk
+///
+/// Crosswalks sit on every arm right outside the conflict zone, so vehicles
+/// pass one when entering and one when leaving. Pedestrians wait at the curb
+/// until the intersection manager lets them cross.
+#[derive(Debug, Clone)]
+pub struct Pedestrian {
+    pub id: u32,
+    pub arm: Direction,             // Side of the intersection whose crosswalk is used
+    pub position: f32,              // Distance walked along the crosswalk (in meters)
+    pub forward: bool,              // Walking from the left to the right curb, seen from the centre
+    pub speed: f32,                 // Walking speed in m/s
+    pub waiting_time: f32,          // Time spent waiting at the curb (in seconds)
+    pub crossing: bool,             // Whether the pedestrian has stepped onto the crosswalk
+    pub active: bool,
+    pub close_call: bool,           // Whether a vehicle drove onto the crosswalk while crossing
+}
+
+impl Pedestrian {
+    pub fn new(id: u32, arm: Direction, forward: bool, speed: f32) -> Self {
+        Pedestrian {
+            id,
+            arm,
+            position: 0.0,
+            forward,
+            speed,
+            waiting_time: 0.0,
+            crossing: false,
+            active: true,
+            close_call: false,
+        }
+    }
+
+    /// Waits at the curb or walks on, depending on whether crossing is allowed
+    ///
+    /// Once on the crosswalk the pedestrian keeps walking until the far curb.
+    pub fn update(&mut self, delta_time: f32, may_cross: bool) {
+        if !self.crossing {
+            if !may_cross {
+                self.waiting_time += delta_time;
+                return;
+            }
+            self.crossing = true;
+        }
+
+        self.position += self.speed * delta_time;
+        if self.position >= CROSSWALK_LENGTH {
+            self.active = false;
+        }
+    }
+
+    /// World position (x, y) of the pedestrian
+    pub fn world_position(&self) -> (f32, f32) {
+        // Offset along the crosswalk from its middle, measured from the left
+        // curb as seen from the centre of the intersection
+        let along = self.position.min(CROSSWALK_LENGTH) - CONFLICT_ZONE_HALF_SIZE;
+        let along = if self.forward { along } else { -along };
+        let out = CONFLICT_ZONE_HALF_SIZE + CROSSWALK_WIDTH / 2.0;
+
+        match self.arm {
+            Direction::North => (-along, out),
+            Direction::South => (along, -out),
+            Direction::East => (out, along),
+            Direction::West => (-out, -along),
+        }
+    }
+}
+
+/// Whether any part of `vehicle` is on the crosswalk of `arm`
+///
+/// Vehicles pass the crosswalk of their approach arm before the conflict
+/// zone and the one of their exit arm after it.
+pub fn vehicle_on_crosswalk(vehicle: &Vehicle, arm: Direction) -> bool {
+    let half_length = vehicle.spec().length / 2.0;
+    let near = CONFLICT_ZONE_HALF_SIZE - half_length;
+    let far = CONFLICT_ZONE_HALF_SIZE + CROSSWALK_WIDTH + half_length;
+    let distance = vehicle.distance_to_intersection;
+
+    let entering = opposite(vehicle.direction) == arm && distance > near && distance < far;
+    let leaving = exit_direction((vehicle.direction, vehicle.route)) == arm
+        && -distance > near
+        && -distance < far;
+    entering || leaving
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::vehicle::Route;
+
+    #[test]
+    fn test_waits_until_allowed() {
+        let mut pedestrian = Pedestrian::new(1, Direction::North, true, WALKING_SPEED);
+        pedestrian.update(2.0, false);
+        assert!(!pedestrian.crossing);
+        assert_eq!(pedestrian.waiting_time, 2.0);
+
+        pedestrian.update(1.0, true);
+        assert!(pedestrian.crossing);
+        assert_eq!(pedestrian.position, WALKING_SPEED);
+
+        // Keeps walking once on the crosswalk
+        pedestrian.update(100.0, false);
+        assert!(!pedestrian.active);
+        assert_eq!(pedestrian.waiting_time, 2.0);
+    }
+
+    #[test]
+    fn test_world_position_lies_on_crosswalk() {
+        let mut pedestrian = Pedestrian::new(1, Direction::East, false, WALKING_SPEED);
+        let (x, y) = pedestrian.world_position();
+        assert_eq!(x, CONFLICT_ZONE_HALF_SIZE + CROSSWALK_WIDTH / 2.0);
+        assert_eq!(y, CONFLICT_ZONE_HALF_SIZE);
+
+        pedestrian.position = CROSSWALK_LENGTH;
+        assert_eq!(pedestrian.world_position().1, -CONFLICT_ZONE_HALF_SIZE);
+    }
+
+    #[test]
+    fn test_vehicle_on_crosswalk() {
+        // Heading north, so it enters from the south arm and leaves by the north arm
+        let mut vehicle = Vehicle::new(1, (0.0, -12.0), 10.0, Route::Straight, Direction::North, 12.0);
+        assert!(vehicle_on_crosswalk(&vehicle, Direction::South));
+        assert!(!vehicle_on_crosswalk(&vehicle, Direction::North));
+
+        vehicle.distance_to_intersection = -12.0;
+        assert!(vehicle_on_crosswalk(&vehicle, Direction::North));
+        assert!(!vehicle_on_crosswalk(&vehicle, Direction::South));
+
+        vehicle.distance_to_intersection = 30.0;
+        assert!(!vehicle_on_crosswalk(&vehicle, Direction::South));
+    }
+}
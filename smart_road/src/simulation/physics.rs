@@ -1,4 +1,6 @@
-use super::vehicle::{Vehicle, Direction};
+use super::platoon::{same_platoon, PLATOON_MIN_GAP};
+use super::vehicle::{Vehicle, Direction, Route};
+use super::vehicle_type::VehicleType;
 
 /// Predefined velocity constants for the simulation
 /// These represent different vehicle speeds in meters per second (m/s)
@@ -13,10 +15,87 @@ pub mod velocities {
     pub const FAST: f32 = 15.0;
 }
 
+/// Half the side length of the conflict zone at the centre of the intersection
+/// (three 3.5 m lanes on each side of the median)
+pub const CONFLICT_ZONE_HALF_SIZE: f32 = 10.5;
+
+/// Bumper-to-bumper gap kept to the vehicle ahead when stopped (in meters)
+pub const MIN_GAP: f32 = 2.0;
+
+/// Lane configuration constants
+pub mod lanes {
+    use super::Route;
+
+    /// Width of each lane in meters
+    pub const LANE_WIDTH: f32 = 3.5;
+
+    /// Number of lanes in each direction
+    pub const LANES_PER_DIRECTION: i32 = 3;
+
+    /// Total road width on each side (3 lanes * 3.5m)
+    pub const ROAD_WIDTH: f32 = LANE_WIDTH * 3.0;
+
+    /// Intersection size (center area)
+    pub const INTERSECTION_SIZE: f32 = ROAD_WIDTH * 2.0;
+    
+    /// Width of the median separator in meters
+    pub const SEPARATOR_WIDTH: f32 = 0.5;
+
+    /// Width of the bike lane along the curb, inside the outer lane (in meters)
+    pub const BIKE_LANE_WIDTH: f32 = 1.2;
+
+    /// Gets the distance of a lane's centre line from the middle of the road
+    /// Right lane = rightmost, Straight = middle, Left = leftmost
+    pub fn lane_offset(route: Route) -> f32 {
+        match route {
+            Route::Right => LANE_WIDTH * 0.5,      // Rightmost lane
+            Route::Straight => LANE_WIDTH * 1.5,   // Middle lane
+            Route::Left => LANE_WIDTH * 2.5,       // Leftmost lane
+        }
+    }
+}
+
+/// Gets the world position of a vehicle in its lane: its `position` on the
+/// approach centre line, moved across to the middle of its lane
+pub fn lane_position(position: (f32, f32), direction: Direction, route: Route, vehicle_type: VehicleType) -> (f32, f32) {
+    let (base_x, base_y) = position;
+
+    // Determine lane offset based on direction and route; bicycles
+    // ride in the middle of the bike lane
+    let lane_offset = if vehicle_type.is_bicycle() {
+        lanes::ROAD_WIDTH - lanes::BIKE_LANE_WIDTH / 2.0
+    } else {
+        lanes::lane_offset(route)
+    };
+
+    match direction {
+        Direction::North => {
+            // Coming from south, moving north (upward)
+            // Lanes are on the RIGHT side of the road (positive X)
+            (base_x + lane_offset, base_y)
+        }
+        Direction::South => {
+            // Coming from north, moving south (downward)
+            // Lanes are on the RIGHT side (negative X)
+            (base_x - lane_offset, base_y)
+        }
+        Direction::East => {
+            // Coming from west, moving east (rightward)
+            // Lanes are on the RIGHT side (negative Y)
+            (base_x, base_y - lane_offset)
+        }
+        Direction::West => {
+            // Coming from east, moving west (leftward)
+            // Lanes are on the RIGHT side (positive Y)
+            (base_x, base_y + lane_offset)
+        }
+    }
+}
+
 /// Physics engine for the traffic simulation
 ///
 /// Handles time calculations, safety distance enforcement, and boundary checking
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Physics {
     /// Minimum safe distance between vehicles (in meters)
     pub safe_distance: f32,
@@ -92,6 +171,50 @@ impl Physics {
         }
     }
 
+    /// Gets the free space between the rear of `leader` and the front of `follower`
+    ///
+    /// Both vehicles are assumed to drive in the same lane, `leader` ahead.
+    pub fn bumper_gap(&self, follower: &Vehicle, leader: &Vehicle) -> f32 {
+        follower.distance_to_intersection
+            - leader.distance_to_intersection
+            - (follower.spec().length + leader.spec().length) / 2.0
+    }
+
+    /// Gets the highest velocity at which `vehicle` can still stop behind `leader`
+    ///
+    /// The follower must be able to brake to a halt within the current gap
+    /// (minus `MIN_GAP`) plus the distance the leader needs to stop, so the
+    /// leader braking hard never causes a rear-end collision. The leader's
+    /// stopping distance is taken at the harder of the two decelerations:
+    /// a follower that brakes harder than its leader would otherwise run
+    /// into it halfway through braking, before either has stopped. Distance driven
+    /// during the follower's reaction time and desired headway is kept free
+    /// on top of that. Members of a platoon brake together with the member
+    /// ahead, so they follow it without headway and with a smaller gap.
+    pub fn following_velocity(&self, vehicle: &Vehicle, leader: &Vehicle) -> f32 {
+        let platooning = same_platoon(vehicle, leader);
+        let min_gap = if platooning { PLATOON_MIN_GAP } else { MIN_GAP };
+        let gap = self.bumper_gap(vehicle, leader) - min_gap;
+        let deceleration = vehicle.spec().max_deceleration;
+        let leader_stopping_distance =
+            leader.velocity.powi(2) / (2.0 * leader.spec().max_deceleration.max(deceleration));
+        let braking_room = (gap + leader_stopping_distance).max(0.0);
+
+        // Largest v with v·t + v²/2b <= braking_room
+        let time_gap = if platooning {
+            0.0
+        } else {
+            vehicle.behaviour.reaction_time + vehicle.behaviour.headway
+        };
+        let reaction_term = deceleration * time_gap;
+        (reaction_term.powi(2) + 2.0 * deceleration * braking_room).sqrt() - reaction_term
+    }
+
+    /// Gets the highest velocity at which `vehicle` can still stop within `distance`
+    pub fn stopping_velocity(&self, vehicle: &Vehicle, distance: f32) -> f32 {
+        (2.0 * vehicle.spec().max_deceleration * distance.max(0.0)).sqrt()
+    }
+
     /// Gets the recommended velocity based on distance to intersection
     /// Vehicles slow down as they approach the intersection for safety.
     pub fn get_adjusted_velocity(&self, distance_to_intersection: f32, base_velocity: f32) -> f32 {
@@ -112,6 +235,7 @@ impl Physics {
 mod tests {
     use super::*;
     use super::super::vehicle::Route;
+    use super::super::vehicle_type::VehicleType;
 
     #[test]
     fn test_velocity_constants() {
@@ -274,6 +398,114 @@ mod tests {
         assert!(vehicle.is_stopped());
     }
 
+    #[test]
+    fn test_bumper_gap() {
+        let physics = Physics::new(10.0, 50.0);
+        let follower = Vehicle::new(1, (0.0, -100.0), 10.0, Route::Straight, Direction::North, 100.0);
+        let leader = Vehicle::new(2, (0.0, -80.0), 10.0, Route::Straight, Direction::North, 80.0);
+
+        // 20 m between centres minus half of each 4.5 m car
+        assert_eq!(physics.bumper_gap(&follower, &leader), 15.5);
+    }
+
+    #[test]
+    fn test_following_velocity() {
+        let physics = Physics::new(10.0, 50.0);
+        let follower = Vehicle::new(1, (0.0, -100.0), 10.0, Route::Straight, Direction::North, 100.0);
+
+        // Stopped leader: follower must be able to stop within the gap
+        let stopped = Vehicle::new(2, (0.0, -80.0), 0.0, Route::Straight, Direction::North, 80.0);
+        let limit = physics.following_velocity(&follower, &stopped);
+        let braking_distance = limit.powi(2) / (2.0 * follower.spec().max_deceleration);
+        assert!((braking_distance - (15.5 - MIN_GAP)).abs() < 1e-3);
+
+        // A moving leader leaves more room than a stopped one
+        let moving = Vehicle::new(3, (0.0, -80.0), 10.0, Route::Straight, Direction::North, 80.0);
+        assert!(physics.following_velocity(&follower, &moving) > limit);
+
+        // No room at all means standing still
+        let touching = Vehicle::new(4, (0.0, -97.0), 0.0, Route::Straight, Direction::North, 97.0);
+        assert_eq!(physics.following_velocity(&follower, &touching), 0.0);
+    }
+
+    /// Bumper gaps while `follower` drives behind `leader` as fast as
+    /// `following_velocity` allows: once the leader has cruised at 10 m/s
+    /// for 10 s, and the smallest one after the leader then braked to a halt
+    fn gaps_behind_leader(follower_type: VehicleType, leader_type: VehicleType) -> (f32, f32) {
+        let physics = Physics::new(10.0, 50.0);
+        let mut leader = Vehicle::new(2, (0.0, -70.0), 10.0, Route::Straight, Direction::North, 70.0)
+            .with_type(leader_type);
+        let mut follower = Vehicle::new(1, (0.0, -100.0), 15.0, Route::Straight, Direction::North, 100.0)
+            .with_type(follower_type);
+        follower.velocity = follower.velocity.min(physics.following_velocity(&follower, &leader));
+
+        let mut cruising = 0.0;
+        let mut smallest = f32::MAX;
+        for step in 0..400 {
+            let target = follower.cruise_velocity.min(physics.following_velocity(&follower, &leader));
+            follower.accelerate_towards(target, 0.05);
+            if step >= 200 {
+                leader.accelerate_towards(0.0, 0.05);
+            }
+            follower.update_position(0.05);
+            leader.update_position(0.05);
+            let gap = physics.bumper_gap(&follower, &leader);
+            if step == 199 {
+                cruising = gap;
+            }
+            smallest = smallest.min(gap);
+        }
+        (cruising, smallest)
+    }
+
+    #[test]
+    fn test_car_behind_truck_does_not_overlap() {
+        // The car brakes harder than the truck, so it must not count on the
+        // truck's longer stopping distance
+        let (cruising, smallest) = gaps_behind_leader(VehicleType::Car, VehicleType::Truck);
+        assert!(cruising >= MIN_GAP - 1e-2, "gap {} while cruising", cruising);
+        assert!(smallest > 0.0, "gap {} while braking", smallest);
+    }
+
+    #[test]
+    fn test_motorcycle_behind_bus_does_not_overlap() {
+        let (cruising, smallest) = gaps_behind_leader(VehicleType::Motorcycle, VehicleType::Bus);
+        assert!(cruising >= MIN_GAP - 1e-2, "gap {} while cruising", cruising);
+        assert!(smallest > 0.0, "gap {} while braking", smallest);
+    }
+
+    #[test]
+    fn test_headway_keeps_followers_back() {
+        let physics = Physics::new(10.0, 50.0);
+        let stopped = Vehicle::new(2, (0.0, -80.0), 0.0, Route::Straight, Direction::North, 80.0);
+        let eager = Vehicle::new(1, (0.0, -100.0), 10.0, Route::Straight, Direction::North, 100.0);
+        let mut careful = eager.clone();
+        careful.behaviour.headway = 1.5;
+        careful.behaviour.reaction_time = 1.0;
+
+        let limit = physics.following_velocity(&careful, &stopped);
+        assert!(limit < physics.following_velocity(&eager, &stopped));
+        // Still stops within the gap after driving on during the time gap
+        let distance = limit * 2.5 + limit.powi(2) / (2.0 * careful.spec().max_deceleration);
+        assert!((distance - (15.5 - MIN_GAP)).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_platoon_members_follow_closely() {
+        let physics = Physics::new(10.0, 50.0);
+        let mut leader = Vehicle::new(2, (0.0, -80.0), 0.0, Route::Straight, Direction::North, 80.0);
+        let mut member = Vehicle::new(1, (0.0, -100.0), 10.0, Route::Straight, Direction::North, 100.0);
+        member.behaviour.headway = 1.5;
+        let alone = physics.following_velocity(&member, &leader);
+
+        leader.platoon = Some(2);
+        member.platoon = Some(2);
+        let limit = physics.following_velocity(&member, &leader);
+        assert!(limit > alone);
+        let braking_distance = limit.powi(2) / (2.0 * member.spec().max_deceleration);
+        assert!((braking_distance - (15.5 - PLATOON_MIN_GAP)).abs() < 1e-3);
+    }
+
     #[test]
     fn test_get_adjusted_velocity_far() {
         let physics = Physics::new(10.0, 50.0);
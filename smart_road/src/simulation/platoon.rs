@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use super::manager::REQUEST_DISTANCE;
+use super::vehicle::{Direction, Vehicle};
+
+/// Largest bumper gap at which a vehicle joins the platoon ahead (in meters)
+pub const JOIN_GAP: f32 = 20.0;
+
+/// Largest speed difference at which a vehicle joins the platoon ahead (in m/s)
+pub const JOIN_SPEED_DIFFERENCE: f32 = 2.0;
+
+/// Most vehicles in one platoon, so cross traffic is not held up for long
+pub const MAX_PLATOON_SIZE: usize = 6;
+
+/// Bumper-to-bumper gap platoon members keep to the member ahead when
+/// stopped, instead of `MIN_GAP` (in meters)
+pub const PLATOON_MIN_GAP: f32 = 1.0;
+
+/// Whether `vehicle` can be part of a platoon
+///
+/// Only automated vehicles platoon; emergency vehicles and misbehaving
+/// vehicles always cross alone.
+fn can_platoon(vehicle: &Vehicle) -> bool {
+    vehicle.active && !vehicle.is_human() && !vehicle.priority && vehicle.rogue.is_none()
+}
+
+/// Whether `follower` and `leader` both belong to the same platoon
+pub fn same_platoon(follower: &Vehicle, leader: &Vehicle) -> bool {
+    follower.platoon.is_some() && follower.platoon == leader.platoon
+}
+
+/// Forms and breaks up platoons on every lane
+///
+/// A vehicle joins the platoon of the vehicle directly ahead in its lane
+/// when the gap and the speed difference between them are small. Platoons
+/// are named after their first vehicle and only change while that vehicle
+/// is farther than `REQUEST_DISTANCE` from the centre; after that the
+/// manager schedules them as one unit and membership is fixed.
+pub fn update_platoons(lanes: &mut HashMap<Direction, Vec<Vehicle>>) {
+    for lane in lanes.values_mut() {
+        // Front to back, grouped by lane
+        let mut order: Vec<usize> = (0..lane.len()).collect();
+        order.sort_by(|&a, &b| {
+            lane[a].lane_index()
+                .cmp(&lane[b].lane_index())
+                .then(lane[a].distance_to_intersection.total_cmp(&lane[b].distance_to_intersection))
+        });
+
+        let distances: HashMap<u32, f32> = lane
+            .iter()
+            .map(|vehicle| (vehicle.id, vehicle.distance_to_intersection))
+            .collect();
+        let forming = |head: u32| distances.get(&head).is_some_and(|&distance| distance > REQUEST_DISTANCE);
+
+        let mut sizes: HashMap<u32, usize> = HashMap::new();
+        for position in 0..order.len() {
+            let index = order[position];
+            let head = lane[index].platoon.unwrap_or(lane[index].id);
+            if forming(head) {
+                let ahead = position
+                    .checked_sub(1)
+                    .map(|previous| order[previous])
+                    .filter(|&ahead| lane[ahead].lane_index() == lane[index].lane_index());
+                let platoon = ahead.and_then(|ahead| {
+                    joined_platoon(&lane[index], &lane[ahead], &sizes).filter(|&id| forming(id))
+                });
+                lane[index].platoon = platoon;
+
+                // The vehicle ahead leads the platoon if it did not already
+                if let (Some(id), Some(ahead)) = (platoon, ahead) {
+                    if lane[ahead].platoon.is_none() {
+                        lane[ahead].platoon = Some(id);
+                        sizes.insert(id, 1);
+                    }
+                }
+            }
+            if let Some(id) = lane[index].platoon {
+                *sizes.entry(id).or_default() += 1;
+            }
+        }
+
+        // A platoon of one is no platoon
+        for vehicle in lane.iter_mut() {
+            let alone = sizes.get(&vehicle.id).copied().unwrap_or(0) < 2;
+            if vehicle.platoon == Some(vehicle.id) && alone && forming(vehicle.id) {
+                vehicle.platoon = None;
+            }
+        }
+    }
+}
+
+/// Gets the platoon `follower` should belong to behind `leader`, if any
+fn joined_platoon(follower: &Vehicle, leader: &Vehicle, sizes: &HashMap<u32, usize>) -> Option<u32> {
+    if !can_platoon(follower) || !can_platoon(leader) {
+        return None;
+    }
+    let id = leader.platoon.unwrap_or(leader.id);
+    let size = sizes.get(&id).copied().unwrap_or(1);
+    let gap = follower.distance_to_intersection
+        - leader.distance_to_intersection
+        - (follower.spec().length + leader.spec().length) / 2.0;
+    let close = gap <= JOIN_GAP && (follower.velocity - leader.velocity).abs() <= JOIN_SPEED_DIFFERENCE;
+    (close && size < MAX_PLATOON_SIZE).then_some(id)
+}
+
+/// Number of vehicles in each platoon on the intersection
+pub fn platoon_sizes(lanes: &HashMap<Direction, Vec<Vehicle>>) -> HashMap<u32, usize> {
+    let mut sizes = HashMap::new();
+    for platoon in lanes.values().flatten().filter_map(|vehicle| vehicle.platoon) {
+        *sizes.entry(platoon).or_default() += 1;
+    }
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::driver::HumanDriver;
+    use crate::simulation::vehicle::Route;
+    use crate::simulation::test_support::lanes;
+
+    fn vehicle(id: u32, distance: f32, velocity: f32) -> Vehicle {
+        Vehicle::new(id, (0.0, -distance), velocity, Route::Straight, Direction::North, distance)
+    }
+
+    fn platoons(lanes: &HashMap<Direction, Vec<Vehicle>>) -> Vec<(u32, Option<u32>)> {
+        let mut platoons: Vec<_> = lanes.values().flatten().map(|v| (v.id, v.platoon)).collect();
+        platoons.sort();
+        platoons
+    }
+
+    #[test]
+    fn test_close_vehicles_form_platoon() {
+        let mut lanes = lanes(vec![
+            vehicle(1, 60.0, 10.0),
+            vehicle(2, 70.0, 10.0),
+            vehicle(3, 80.0, 11.0),
+            // Too far behind
+            vehicle(4, 120.0, 10.0),
+            // Other lane
+            Vehicle::new(5, (0.0, -75.0), 10.0, Route::Left, Direction::North, 75.0),
+        ]);
+        update_platoons(&mut lanes);
+        assert_eq!(
+            platoons(&lanes),
+            vec![(1, Some(1)), (2, Some(1)), (3, Some(1)), (4, None), (5, None)],
+        );
+    }
+
+    #[test]
+    fn test_speed_difference_and_humans_prevent_platoons() {
+        let mut lanes = lanes(vec![
+            vehicle(1, 60.0, 5.0),
+            vehicle(2, 70.0, 15.0),
+            vehicle(3, 85.0, 15.0).with_driver(HumanDriver::new()),
+        ]);
+        update_platoons(&mut lanes);
+        assert!(platoons(&lanes).iter().all(|(_, platoon)| platoon.is_none()));
+    }
+
+    #[test]
+    fn test_platoon_is_fixed_near_intersection() {
+        let mut lanes = lanes(vec![vehicle(1, 60.0, 10.0), vehicle(2, 70.0, 10.0)]);
+        update_platoons(&mut lanes);
+
+        // The head is within request range, so nobody joins or leaves any more
+        for vehicle in lanes.get_mut(&Direction::North).unwrap() {
+            vehicle.distance_to_intersection -= 20.0;
+        }
+        lanes.get_mut(&Direction::North).unwrap()[1].velocity = 3.0;
+        lanes.get_mut(&Direction::North).unwrap().push(vehicle(3, 58.0, 10.0));
+        update_platoons(&mut lanes);
+        assert_eq!(platoons(&lanes), vec![(1, Some(1)), (2, Some(1)), (3, None)]);
+    }
+
+    #[test]
+    fn test_platoon_size_is_limited() {
+        let vehicles = (0..10).map(|i| vehicle(i + 1, 60.0 + 8.0 * i as f32, 10.0)).collect();
+        let mut lanes = lanes(vehicles);
+        update_platoons(&mut lanes);
+        let sizes = platoon_sizes(&lanes);
+        assert_eq!(sizes.get(&1), Some(&MAX_PLATOON_SIZE));
+        assert!(sizes.values().all(|&size| size <= MAX_PLATOON_SIZE));
+    }
+}
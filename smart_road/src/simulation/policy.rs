@@ -0,0 +1,93 @@
+use std::fmt;
+use std::str::FromStr;
+use super::manager::Agent;
+
+/// Default time the batch policy collects requests before forming a batch (in seconds)
+pub const DEFAULT_BATCH_WINDOW: f32 = 2.0;
+
+/// Default largest number of agents let through in one batch
+pub const DEFAULT_BATCH_SIZE: usize = 8;
+
+/// How the intersection manager chooses which waiting requests to grant
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Policy {
+    /// Grant each request as soon as nothing conflicting holds a
+    /// reservation or is waiting ahead of it in the queue
+    #[default]
+    FirstComeFirstServed,
+    /// Collect requests for `window` seconds, group up to `max_size` of them
+    /// that do not conflict with each other in arrival order, and let that
+    /// batch through completely before forming the next one
+    Batch { window: f32, max_size: usize },
+    /// Run a sealed-bid, first-price auction among the waiting requests
+    /// every step: the highest bids are served first as under first come,
+    /// first served, and winners pay their bid (see `auction::bid`)
+    Auction,
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Policy::FirstComeFirstServed => write!(f, "fcfs"),
+            Policy::Batch { window, max_size } => write!(f, "batch:{}:{}", window, max_size),
+            Policy::Auction => write!(f, "auction"),
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    /// Parses `fcfs`, `batch`, `batch:<window>:<max size>` or `auction`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split(':').collect();
+        match parts.as_slice() {
+            ["fcfs"] => Ok(Policy::FirstComeFirstServed),
+            ["auction"] => Ok(Policy::Auction),
+            ["batch"] => Ok(Policy::Batch {
+                window: DEFAULT_BATCH_WINDOW,
+                max_size: DEFAULT_BATCH_SIZE,
+            }),
+            ["batch", window, max_size] => {
+                let window: f32 = window.parse().map_err(|_| format!("Invalid batch window: {}", window))?;
+                let max_size: usize = max_size.parse().map_err(|_| format!("Invalid batch size: {}", max_size))?;
+                if window < 0.0 || max_size == 0 {
+                    return Err(format!("Invalid batch policy: {}", s));
+                }
+                Ok(Policy::Batch { window, max_size })
+            }
+            _ => Err(format!("Unknown policy: {}", s)),
+        }
+    }
+}
+
+/// Progress of the batch policy
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Batch {
+    /// Agents in the batch currently let through
+    pub members: Vec<Agent>,
+    /// How long requests have been collected for the next batch (in seconds)
+    pub collecting: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!("fcfs".parse(), Ok(Policy::FirstComeFirstServed));
+        assert_eq!(
+            "batch".parse(),
+            Ok(Policy::Batch { window: DEFAULT_BATCH_WINDOW, max_size: DEFAULT_BATCH_SIZE }),
+        );
+        let policy: Policy = "batch:1.5:4".parse().unwrap();
+        assert_eq!(policy, Policy::Batch { window: 1.5, max_size: 4 });
+        assert_eq!(policy.to_string().parse(), Ok(policy));
+        assert_eq!("auction".parse(), Ok(Policy::Auction));
+
+        assert!("batch:1".parse::<Policy>().is_err());
+        assert!("batch:1:0".parse::<Policy>().is_err());
+        assert!("lottery".parse::<Policy>().is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::{HashMap, HashSet};
+use super::manager::{IntersectionManager, STOP_LINE_DISTANCE};
+use super::vehicle::{Direction, Vehicle, BIKE_LANE};
+
+/// Vehicles slower than this count as queued (in m/s)
+pub const QUEUE_SPEED: f32 = 2.0;
+
+/// Largest gap from the stop line, or from the vehicle ahead in the queue,
+/// at which a slow vehicle still joins the queue (in meters)
+pub const QUEUE_GAP: f32 = 10.0;
+
+/// Distance from the centre at which vehicles are spawned (in meters), the
+/// same as for vehicles spawned with the keyboard
+pub const DEFAULT_SPAWN_DISTANCE: f32 = 100.0;
+
+/// Every approach, in the order queues are reported
+pub const APPROACHES: [Direction; 4] = [Direction::North, Direction::South, Direction::East, Direction::West];
+
+/// Length of a queue
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Queue {
+    pub vehicles: usize,
+    /// Distance from the stop line to the back of the last queued vehicle (in meters)
+    pub length: f32,
+}
+
+impl Queue {
+    /// Queue as long as the longer of the two in each measure
+    fn max(self, other: Queue) -> Queue {
+        Queue {
+            vehicles: self.vehicles.max(other.vehicles),
+            length: self.length.max(other.length),
+        }
+    }
+}
+
+/// Queue on one lane: the unbroken line of slow vehicles back from the
+/// stop line, each within `QUEUE_GAP` of the one ahead
+pub fn lane_queue(lanes: &HashMap<Direction, Vec<Vehicle>>, direction: Direction, lane: usize) -> Queue {
+    let mut vehicles: Vec<&Vehicle> = lanes
+        .get(&direction)
+        .into_iter()
+        .flatten()
+        .filter(|vehicle| vehicle.active && vehicle.lane_index() == lane)
+        .filter(|vehicle| !IntersectionManager::has_entered(vehicle) && !IntersectionManager::has_cleared(vehicle))
+        .collect();
+    vehicles.sort_by(|a, b| a.distance_to_intersection.total_cmp(&b.distance_to_intersection));
+
+    let mut queue = Queue::default();
+    let mut end = STOP_LINE_DISTANCE;
+    for vehicle in vehicles {
+        let half_length = vehicle.spec().length / 2.0;
+        if vehicle.velocity >= QUEUE_SPEED || vehicle.distance_to_intersection - half_length - end > QUEUE_GAP {
+            break;
+        }
+        queue.vehicles += 1;
+        end = vehicle.distance_to_intersection + half_length;
+    }
+    queue.length = end - STOP_LINE_DISTANCE;
+    queue
+}
+
+/// Live and longest queues on every approach and lane, and spillback
+///
+/// An approach spills back when the back of one of its queues comes within
+/// the safe distance of the spawn point, so no new vehicle can enter it. It
+/// stays spilled back until the queue is `QUEUE_GAP` shorter than that, so a
+/// queue creeping forward is not counted again every few steps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueMonitor {
+    /// Distance from the centre at which vehicles enter the approaches (in meters)
+    pub spawn_distance: f32,
+    /// Queues after the last step, by approach and lane index
+    pub current: HashMap<(Direction, usize), Queue>,
+    /// Longest queues so far, by approach and lane index
+    pub longest: HashMap<(Direction, usize), Queue>,
+    /// Longest queues so far over all the lanes of each approach
+    pub longest_approach: HashMap<Direction, Queue>,
+    /// Approaches spilled back after the last step
+    pub spilled: HashSet<Direction>,
+    /// Number of times each approach spilled back
+    pub spillbacks: HashMap<Direction, u32>,
+    /// Time each approach spent spilled back (in seconds)
+    pub spillback_time: HashMap<Direction, f32>,
+}
+
+impl QueueMonitor {
+    pub fn new(spawn_distance: f32) -> Self {
+        QueueMonitor {
+            spawn_distance,
+            current: HashMap::new(),
+            longest: HashMap::new(),
+            longest_approach: HashMap::new(),
+            spilled: HashSet::new(),
+            spillbacks: HashMap::new(),
+            spillback_time: HashMap::new(),
+        }
+    }
+
+    /// Measures the queues and checks every approach for spillback
+    pub fn update(&mut self, lanes: &HashMap<Direction, Vec<Vehicle>>, safe_distance: f32, delta_time: f32) {
+        let mut spilled = HashSet::new();
+        for direction in APPROACHES {
+            for lane in 0..=BIKE_LANE {
+                let queue = lane_queue(lanes, direction, lane);
+                self.current.insert((direction, lane), queue);
+                let longest = self.longest.entry((direction, lane)).or_default();
+                *longest = longest.max(queue);
+                let reach = STOP_LINE_DISTANCE + queue.length + safe_distance;
+                let threshold = if self.spilled.contains(&direction) {
+                    self.spawn_distance - QUEUE_GAP
+                } else {
+                    self.spawn_distance
+                };
+                if queue.vehicles > 0 && reach >= threshold {
+                    spilled.insert(direction);
+                }
+            }
+            let approach = self.approach(direction);
+            let longest = self.longest_approach.entry(direction).or_default();
+            *longest = longest.max(approach);
+        }
+        for direction in &spilled {
+            if !self.spilled.contains(direction) {
+                *self.spillbacks.entry(*direction).or_default() += 1;
+            }
+            *self.spillback_time.entry(*direction).or_default() += delta_time;
+        }
+        self.spilled = spilled;
+    }
+
+    /// Longest queue so far on any approach
+    pub fn longest_overall(&self) -> Queue {
+        self.longest_approach.values().fold(Queue::default(), |longest, queue| longest.max(*queue))
+    }
+
+    /// Queue on a lane after the last step
+    pub fn lane(&self, direction: Direction, lane: usize) -> Queue {
+        self.current.get(&(direction, lane)).copied().unwrap_or_default()
+    }
+
+    /// Queue on an approach after the last step: the vehicles queued on all
+    /// its lanes, as long as its longest lane queue
+    pub fn approach(&self, direction: Direction) -> Queue {
+        (0..=BIKE_LANE)
+            .map(|lane| self.lane(direction, lane))
+            .fold(Queue::default(), |total, queue| Queue {
+                vehicles: total.vehicles + queue.vehicles,
+                length: total.length.max(queue.length),
+            })
+    }
+}
+
+impl Default for QueueMonitor {
+    fn default() -> Self {
+        QueueMonitor::new(DEFAULT_SPAWN_DISTANCE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::vehicle::Route;
+
+    /// A car standing `gap` meters behind `end`
+    fn queued(id: u32, end: f32, gap: f32) -> Vehicle {
+        let mut vehicle = Vehicle::new(id, (0.0, 0.0), 0.0, Route::Straight, Direction::North, 0.0);
+        vehicle.distance_to_intersection = end + gap + vehicle.spec().length / 2.0;
+        vehicle
+    }
+
+    #[test]
+    fn test_queue_is_unbroken_line_of_slow_vehicles() {
+        let first = queued(1, STOP_LINE_DISTANCE, 0.5);
+        let back = |vehicle: &Vehicle| vehicle.distance_to_intersection + vehicle.spec().length / 2.0;
+        let second = queued(2, back(&first), 2.0);
+        // Too far behind to be part of the queue
+        let third = queued(3, back(&second), QUEUE_GAP + 1.0);
+        let mut lanes = HashMap::new();
+        lanes.insert(Direction::North, vec![third, second.clone(), first]);
+
+        let queue = lane_queue(&lanes, Direction::North, Route::Straight.lane_index());
+        assert_eq!(queue.vehicles, 2);
+        assert!((queue.length - (back(&second) - STOP_LINE_DISTANCE)).abs() < 1e-4);
+        assert_eq!(lane_queue(&lanes, Direction::North, Route::Left.lane_index()), Queue::default());
+
+        // A moving vehicle at the stop line means nobody is queued
+        lanes.get_mut(&Direction::North).unwrap()[2].velocity = 5.0;
+        assert_eq!(lane_queue(&lanes, Direction::North, Route::Straight.lane_index()).vehicles, 0);
+    }
+
+    #[test]
+    fn test_spillback_when_queue_reaches_spawn_point() {
+        let mut lane = Vec::new();
+        let mut end = STOP_LINE_DISTANCE;
+        for id in 1..=4 {
+            let vehicle = queued(id, end, 1.0);
+            end = vehicle.distance_to_intersection + vehicle.spec().length / 2.0;
+            lane.push(vehicle);
+        }
+        let mut lanes = HashMap::new();
+        lanes.insert(Direction::North, lane);
+        // The back of the fourth car is just within the safe distance of the spawn point
+        let mut monitor = QueueMonitor::new(end + 20.0);
+
+        monitor.update(&lanes, 20.0, 0.1);
+        monitor.update(&lanes, 20.0, 0.1);
+        assert_eq!(monitor.approach(Direction::North).vehicles, 4);
+        assert!(monitor.spilled.contains(&Direction::North));
+        assert_eq!(monitor.spillbacks[&Direction::North], 1);
+        assert!((monitor.spillback_time[&Direction::North] - 0.2).abs() < 1e-6);
+
+        // One car shorter is not yet enough to clear the spillback
+        lanes.get_mut(&Direction::North).unwrap().pop();
+        monitor.update(&lanes, 20.0, 0.1);
+        assert!(monitor.spilled.contains(&Direction::North));
+
+        lanes.get_mut(&Direction::North).unwrap().clear();
+        monitor.update(&lanes, 20.0, 0.1);
+        assert!(monitor.spilled.is_empty());
+        assert_eq!(monitor.approach(Direction::North), Queue::default());
+        assert_eq!(monitor.longest_approach[&Direction::North].vehicles, 4);
+        assert_eq!(monitor.spillbacks[&Direction::North], 1);
+    }
+}
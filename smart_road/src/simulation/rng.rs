@@ -0,0 +1,69 @@
+use rand::RngCore;
+
+/// Seedable random number generator whose whole state is a single `u64`
+///
+/// Uses the SplitMix64 algorithm. Keeping the state this small lets
+/// snapshots store it and resume a run with exactly the same random draws.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimRng {
+    state: u64,
+}
+
+impl SimRng {
+    /// Creates a generator from a fixed seed
+    pub fn new(seed: u64) -> Self {
+        SimRng { state: seed }
+    }
+
+    /// Creates a generator seeded from the operating system
+    pub fn from_entropy() -> Self {
+        SimRng::new(rand::rng().next_u64())
+    }
+
+    /// Current internal state, which can be passed to `new` to resume
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+}
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        rand::rand_core::impls::fill_bytes_via_next(self, dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut a = SimRng::new(42);
+        let mut b = SimRng::new(42);
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn test_resume_from_state() {
+        let mut rng = SimRng::new(7);
+        rng.next_u64();
+
+        let mut resumed = SimRng::new(rng.state());
+        assert_eq!(rng.random_range(0..1000), resumed.random_range(0..1000));
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use super::physics::CONFLICT_ZONE_HALF_SIZE;
+use super::vehicle::{Direction, Vehicle};
+
+/// How much faster than its type's top speed a speeding vehicle drives
+pub const SPEEDING_FACTOR: f32 = 1.5;
+
+/// How long a vehicle that stops suddenly stands in the conflict zone (in seconds)
+pub const STOP_DURATION: f32 = 5.0;
+
+/// How much further along its path a vehicle reporting a false position
+/// claims to be (in meters)
+pub const FALSE_POSITION_OFFSET: f32 = 20.0;
+
+/// Ways an injected vehicle misbehaves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Misbehaviour {
+    /// Drives through without waiting for a reservation or right of way
+    IgnoresReservation,
+    /// Drives `SPEEDING_FACTOR` times its type's top speed
+    Speeding,
+    /// Stops dead in the conflict zone for `STOP_DURATION`
+    SuddenStop,
+    /// Tells the manager (or, under V2V, the other vehicles) it is
+    /// `FALSE_POSITION_OFFSET` further along its path than it is
+    FalsePosition,
+}
+
+impl Misbehaviour {
+    /// All kinds of misbehaviour, in a fixed order
+    pub const ALL: [Misbehaviour; 4] = [
+        Misbehaviour::IgnoresReservation,
+        Misbehaviour::Speeding,
+        Misbehaviour::SuddenStop,
+        Misbehaviour::FalsePosition,
+    ];
+
+    /// Short name used on the command line and in snapshots
+    pub fn name(&self) -> &'static str {
+        match self {
+            Misbehaviour::IgnoresReservation => "ignore",
+            Misbehaviour::Speeding => "speeding",
+            Misbehaviour::SuddenStop => "stop",
+            Misbehaviour::FalsePosition => "spoof",
+        }
+    }
+}
+
+impl fmt::Display for Misbehaviour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Misbehaviour {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Misbehaviour::ALL
+            .into_iter()
+            .find(|kind| kind.name() == s.trim())
+            .ok_or_else(|| format!("Unknown misbehaviour: {}", s))
+    }
+}
+
+/// A vehicle injected to misbehave, for safety validation
+///
+/// Rogue vehicles are automated: they ask the manager for a reservation
+/// like everyone else, but do not do what is expected of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rogue {
+    pub kind: Misbehaviour,
+    pub stopped_for: f32,       // How long it has stood in the conflict zone so far (in seconds)
+}
+
+impl Rogue {
+    pub fn new(kind: Misbehaviour) -> Self {
+        Rogue { kind, stopped_for: 0.0 }
+    }
+
+    /// Whether the vehicle ignores reservations and right of way
+    pub fn ignores_reservation(&self) -> bool {
+        self.kind == Misbehaviour::IgnoresReservation
+    }
+
+    /// Whether the vehicle is standing in the conflict zone, or about to
+    /// stop dead there
+    pub fn stops(&self, vehicle: &Vehicle) -> bool {
+        self.kind == Misbehaviour::SuddenStop
+            && self.stopped_for < STOP_DURATION
+            && vehicle.distance_to_intersection.abs() < CONFLICT_ZONE_HALF_SIZE
+    }
+}
+
+/// Stops `vehicle` dead if it is a rogue vehicle that stops in the conflict
+/// zone, without braking first
+pub fn stop_suddenly(vehicle: &mut Vehicle, delta_time: f32) {
+    let stops = vehicle.rogue.is_some_and(|rogue| rogue.stops(vehicle));
+    if let (true, Some(rogue)) = (stops, vehicle.rogue.as_mut()) {
+        rogue.stopped_for += delta_time;
+        vehicle.stop();
+    }
+}
+
+/// Gets the vehicle as it reports itself: further along its path when it
+/// reports a false position
+///
+/// The lie never puts it past the vehicle ahead of it in its lane, as the
+/// two would then be seen to overlap; it claims to be right behind it instead.
+pub fn reported(vehicle: &Vehicle, lanes: &HashMap<Direction, Vec<Vehicle>>) -> Vehicle {
+    let mut reported = vehicle.clone();
+    if !vehicle.rogue.is_some_and(|rogue| rogue.kind == Misbehaviour::FalsePosition) {
+        return reported;
+    }
+    let room = lanes
+        .get(&vehicle.direction)
+        .into_iter()
+        .flatten()
+        .filter(|other| other.id != vehicle.id && other.active && other.lane_index() == vehicle.lane_index())
+        .filter(|other| other.distance_to_intersection < vehicle.distance_to_intersection)
+        .map(|leader| {
+            vehicle.distance_to_intersection
+                - leader.distance_to_intersection
+                - (vehicle.spec().length + leader.spec().length) / 2.0
+        })
+        .fold(f32::INFINITY, f32::min);
+    let offset = FALSE_POSITION_OFFSET.min(room.max(0.0));
+    reported.distance_to_intersection -= offset;
+    match vehicle.direction {
+        Direction::North => reported.position.1 += offset,
+        Direction::South => reported.position.1 -= offset,
+        Direction::East => reported.position.0 += offset,
+        Direction::West => reported.position.0 -= offset,
+    }
+    reported
+}
+
+/// Gets the lanes as the vehicles report them, or `None` when nobody lies
+/// about its position
+pub fn reported_lanes(lanes: &HashMap<Direction, Vec<Vehicle>>) -> Option<HashMap<Direction, Vec<Vehicle>>> {
+    let lying = lanes
+        .values()
+        .flatten()
+        .any(|vehicle| vehicle.rogue.is_some_and(|rogue| rogue.kind == Misbehaviour::FalsePosition));
+    if !lying {
+        return None;
+    }
+    Some(
+        lanes
+            .iter()
+            .map(|(direction, lane)| (*direction, lane.iter().map(|vehicle| reported(vehicle, lanes)).collect()))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::vehicle::Route;
+
+    fn vehicle(distance: f32) -> Vehicle {
+        Vehicle::new(1, (0.0, -distance), 10.0, Route::Straight, Direction::North, distance)
+    }
+
+    #[test]
+    fn test_parse_misbehaviour() {
+        for kind in Misbehaviour::ALL {
+            assert_eq!(kind.name().parse::<Misbehaviour>(), Ok(kind));
+        }
+        assert!("reckless".parse::<Misbehaviour>().is_err());
+    }
+
+    #[test]
+    fn test_false_position_is_reported_further_along() {
+        let honest = vehicle(40.0);
+        let lanes = HashMap::from([(Direction::North, vec![honest.clone()])]);
+        assert_eq!(reported(&honest, &lanes).distance_to_intersection, 40.0);
+        assert!(reported_lanes(&lanes).is_none());
+
+        let liar = vehicle(40.0).with_rogue(Misbehaviour::FalsePosition);
+        let shown = reported(&liar, &HashMap::new());
+        assert_eq!(shown.distance_to_intersection, 40.0 - FALSE_POSITION_OFFSET);
+        assert_eq!(shown.position, (0.0, -40.0 + FALSE_POSITION_OFFSET));
+    }
+
+    #[test]
+    fn test_false_position_stays_behind_the_vehicle_ahead() {
+        let mut leader = vehicle(30.0);
+        leader.id = 2;
+        let liar = vehicle(40.0).with_rogue(Misbehaviour::FalsePosition);
+        let lanes = HashMap::from([(Direction::North, vec![leader, liar.clone()])]);
+
+        // 10 m between centres leave 5.5 m between the bumpers of two cars
+        let shown = reported(&liar, &lanes);
+        assert_eq!(shown.distance_to_intersection, 34.5);
+        assert_eq!(shown.position, (0.0, -34.5));
+    }
+
+    #[test]
+    fn test_sudden_stop_only_in_conflict_zone() {
+        let mut approaching = vehicle(30.0).with_rogue(Misbehaviour::SuddenStop);
+        stop_suddenly(&mut approaching, 0.1);
+        assert_eq!(approaching.velocity, 10.0);
+
+        let mut inside = vehicle(2.0).with_rogue(Misbehaviour::SuddenStop);
+        stop_suddenly(&mut inside, 0.1);
+        assert_eq!(inside.velocity, 0.0);
+
+        // It drives on after standing there for a while
+        inside.rogue = Some(Rogue { kind: Misbehaviour::SuddenStop, stopped_for: STOP_DURATION });
+        assert!(!inside.rogue.unwrap().stops(&inside));
+    }
+
+    #[test]
+    fn test_speeding_vehicle_exceeds_top_speed() {
+        let speeding = vehicle(40.0).with_rogue(Misbehaviour::Speeding);
+        assert!(speeding.cruise_velocity > speeding.spec().max_speed);
+        assert!(speeding.driver.is_none());
+    }
+}
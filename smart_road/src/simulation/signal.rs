@@ -0,0 +1,803 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use super::conflict::{
+    claims_conflict, exit_direction, movements_conflict, uses_crosswalk, vehicle_claim, Claim, Movement,
+};
+use super::detector::LoopDetector;
+use super::manager::{Agent, IntersectionManager, REQUEST_DISTANCE, STOP_LINE_DISTANCE};
+use super::pedestrian::Pedestrian;
+use super::vehicle::{Direction, Route, Vehicle, BIKE_LANE};
+
+/// Default green time of each fixed-time phase (in seconds)
+pub const DEFAULT_GREEN: f32 = 20.0;
+
+/// Default amber time at the end of each phase (in seconds)
+pub const DEFAULT_AMBER: f32 = 3.0;
+
+/// Default all-red clearance time between phases (in seconds)
+pub const DEFAULT_ALL_RED: f32 = 2.0;
+
+/// Default shortest green of an actuated phase (in seconds)
+pub const DEFAULT_MIN_GREEN: f32 = 5.0;
+
+/// Default longest green of an actuated phase while other movements wait (in seconds)
+pub const DEFAULT_MAX_GREEN: f32 = 40.0;
+
+/// Default time without a detection after which an actuated phase gaps out (in seconds)
+pub const DEFAULT_GAP: f32 = 2.5;
+
+/// Default time between the decisions of the max-pressure controller (in seconds)
+pub const DEFAULT_DECISION_INTERVAL: f32 = 5.0;
+
+/// Length of the presence loops in front of the stop line of every lane (in meters)
+pub const DETECTOR_LENGTH: f32 = 20.0;
+
+/// Default phases: through and right-turn traffic of each axis, then the
+/// protected left turns of that axis
+pub const DEFAULT_PHASES: &str = "NS/SR,NS/L,EW/SR,EW/L";
+
+/// How a signal decides when to end a phase
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Controller {
+    /// Every phase gets its green time (`green` unless the phase sets its
+    /// own), then amber and all-red, in a fixed cycle
+    FixedTime { green: f32, amber: f32, all_red: f32 },
+    /// Phases only get green while vehicles are detected: a phase ends once
+    /// its detectors have been empty for `gap` seconds (gap-out) or after
+    /// `max_green` (max-out), but never before `min_green` and never
+    /// without a call from another phase. Phases without a call are skipped.
+    Actuated { min_green: f32, max_green: f32, gap: f32, amber: f32, all_red: f32 },
+    /// Every `interval` seconds of green the phase with the highest pressure
+    /// (see `Signal::pressure`) is chosen; the green phase is kept while no
+    /// other has a higher one
+    MaxPressure { interval: f32, amber: f32, all_red: f32 },
+}
+
+impl Controller {
+    pub fn amber(&self) -> f32 {
+        match self {
+            Controller::FixedTime { amber, .. }
+            | Controller::Actuated { amber, .. }
+            | Controller::MaxPressure { amber, .. } => *amber,
+        }
+    }
+
+    pub fn all_red(&self) -> f32 {
+        match self {
+            Controller::FixedTime { all_red, .. }
+            | Controller::Actuated { all_red, .. }
+            | Controller::MaxPressure { all_red, .. } => *all_red,
+        }
+    }
+}
+
+impl fmt::Display for Controller {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Controller::FixedTime { green, amber, all_red } => write!(f, "fixed:{}:{}:{}", green, amber, all_red),
+            Controller::Actuated { min_green, max_green, gap, amber, all_red } => {
+                write!(f, "actuated:{}:{}:{}:{}:{}", min_green, max_green, gap, amber, all_red)
+            }
+            Controller::MaxPressure { interval, amber, all_red } => {
+                write!(f, "max-pressure:{}:{}:{}", interval, amber, all_red)
+            }
+        }
+    }
+}
+
+impl FromStr for Controller {
+    type Err = String;
+
+    /// Parses `fixed`, `fixed:<green>:<amber>:<all red>`, `actuated`,
+    /// `actuated:<min green>:<max green>:<gap>:<amber>:<all red>`,
+    /// `max-pressure` or `max-pressure:<interval>:<amber>:<all red>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split(':').collect();
+        let times = |values: &[&str]| -> Result<Vec<f32>, String> {
+            values
+                .iter()
+                .map(|value| match value.parse::<f32>() {
+                    Ok(time) if time >= 0.0 => Ok(time),
+                    _ => Err(format!("Invalid signal time: {}", value)),
+                })
+                .collect()
+        };
+        match parts.as_slice() {
+            ["fixed"] => Ok(Controller::FixedTime {
+                green: DEFAULT_GREEN,
+                amber: DEFAULT_AMBER,
+                all_red: DEFAULT_ALL_RED,
+            }),
+            ["fixed", values @ ..] if values.len() == 3 => match times(values)?.as_slice() {
+                [green, amber, all_red] if *green > 0.0 => Ok(Controller::FixedTime {
+                    green: *green,
+                    amber: *amber,
+                    all_red: *all_red,
+                }),
+                _ => Err(format!("Invalid fixed-time signal: {}", s)),
+            },
+            ["actuated"] => Ok(Controller::Actuated {
+                min_green: DEFAULT_MIN_GREEN,
+                max_green: DEFAULT_MAX_GREEN,
+                gap: DEFAULT_GAP,
+                amber: DEFAULT_AMBER,
+                all_red: DEFAULT_ALL_RED,
+            }),
+            ["actuated", values @ ..] if values.len() == 5 => match times(values)?.as_slice() {
+                [min_green, max_green, gap, amber, all_red] if min_green <= max_green && *max_green > 0.0 => {
+                    Ok(Controller::Actuated {
+                        min_green: *min_green,
+                        max_green: *max_green,
+                        gap: *gap,
+                        amber: *amber,
+                        all_red: *all_red,
+                    })
+                }
+                _ => Err(format!("Invalid actuated signal: {}", s)),
+            },
+            ["max-pressure"] => Ok(Controller::MaxPressure {
+                interval: DEFAULT_DECISION_INTERVAL,
+                amber: DEFAULT_AMBER,
+                all_red: DEFAULT_ALL_RED,
+            }),
+            ["max-pressure", values @ ..] if values.len() == 3 => match times(values)?.as_slice() {
+                [interval, amber, all_red] if *interval > 0.0 => Ok(Controller::MaxPressure {
+                    interval: *interval,
+                    amber: *amber,
+                    all_red: *all_red,
+                }),
+                _ => Err(format!("Invalid max-pressure signal: {}", s)),
+            },
+            _ => Err(format!("Unknown signal controller: {}", s)),
+        }
+    }
+}
+
+/// A set of movements that get green together
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phase {
+    pub movements: Vec<Movement>,
+    /// Green time of this phase under fixed-time control, instead of the
+    /// controller's (in seconds)
+    pub green: Option<f32>,
+}
+
+impl Phase {
+    /// Whether the phase gives `movement` green
+    pub fn serves(&self, movement: Movement) -> bool {
+        self.movements.contains(&movement)
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut directions = String::new();
+        let mut routes = String::new();
+        for (direction, route) in &self.movements {
+            let direction = direction_letter(*direction);
+            let route = route_letter(*route);
+            if !directions.contains(direction) {
+                directions.push(direction);
+            }
+            if !routes.contains(route) {
+                routes.push(route);
+            }
+        }
+        write!(f, "{}/{}", directions, routes)?;
+        if let Some(green) = self.green {
+            write!(f, ":{}", green)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Phase {
+    type Err = String;
+
+    /// Parses `<directions>/<routes>[:<green>]`, e.g. `NS/SR:30`: the
+    /// directions of travel (`N`, `S`, `E`, `W`) and the routes (`R`, `S`,
+    /// `L`) of the movements the phase serves
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (movements, green) = match s.split_once(':') {
+            Some((movements, green)) => {
+                let green: f32 = green.parse().map_err(|_| format!("Invalid phase green time: {}", green))?;
+                if green <= 0.0 {
+                    return Err(format!("Invalid phase green time: {}", s));
+                }
+                (movements, Some(green))
+            }
+            None => (s, None),
+        };
+        let (directions, routes) = movements
+            .split_once('/')
+            .ok_or_else(|| format!("Expected directions/routes, found: {}", s))?;
+        let mut phase = Phase { movements: Vec::new(), green };
+        for direction in directions.chars() {
+            let direction = match direction.to_ascii_uppercase() {
+                'N' => Direction::North,
+                'S' => Direction::South,
+                'E' => Direction::East,
+                'W' => Direction::West,
+                _ => return Err(format!("Unknown direction in phase: {}", direction)),
+            };
+            for route in routes.chars() {
+                let route = match route.to_ascii_uppercase() {
+                    'R' => Route::Right,
+                    'S' => Route::Straight,
+                    'L' => Route::Left,
+                    _ => return Err(format!("Unknown route in phase: {}", route)),
+                };
+                phase.movements.push((direction, route));
+            }
+        }
+        if phase.movements.is_empty() {
+            return Err(format!("Phase without movements: {}", s));
+        }
+        Ok(phase)
+    }
+}
+
+fn direction_letter(direction: Direction) -> char {
+    match direction {
+        Direction::North => 'N',
+        Direction::South => 'S',
+        Direction::East => 'E',
+        Direction::West => 'W',
+    }
+}
+
+fn route_letter(route: Route) -> char {
+    match route {
+        Route::Right => 'R',
+        Route::Straight => 'S',
+        Route::Left => 'L',
+    }
+}
+
+/// Parses a comma-separated list of phases
+///
+/// Movements in one phase must not conflict, and every movement has to be
+/// served by some phase.
+pub fn parse_phases(s: &str) -> Result<Vec<Phase>, String> {
+    let phases: Vec<Phase> = s.split(',').map(|phase| phase.trim().parse()).collect::<Result<_, _>>()?;
+    for phase in &phases {
+        for (index, a) in phase.movements.iter().enumerate() {
+            if let Some(b) = phase.movements[index + 1..].iter().find(|b| movements_conflict(*a, **b)) {
+                return Err(format!("Conflicting movements {:?} and {:?} in phase {}", a, b, phase));
+            }
+        }
+    }
+    for direction in [Direction::North, Direction::South, Direction::East, Direction::West] {
+        for route in [Route::Right, Route::Straight, Route::Left] {
+            if !phases.iter().any(|phase| phase.serves((direction, route))) {
+                return Err(format!("No phase serves {:?} {:?}", direction, route));
+            }
+        }
+    }
+    Ok(phases)
+}
+
+/// Part of a phase the signal is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interval {
+    Green,
+    Amber,
+    /// Every movement has red while the last vehicles clear the intersection
+    AllRed,
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Interval::Green => write!(f, "green"),
+            Interval::Amber => write!(f, "amber"),
+            Interval::AllRed => write!(f, "all_red"),
+        }
+    }
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "green" => Ok(Interval::Green),
+            "amber" => Ok(Interval::Amber),
+            "all_red" => Ok(Interval::AllRed),
+            _ => Err(format!("Unknown signal interval: {}", s)),
+        }
+    }
+}
+
+/// What the signal head of a movement shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aspect {
+    Green,
+    Amber,
+    Red,
+}
+
+/// Traffic signal controlling the intersection instead of the manager
+///
+/// Vehicles with green go unless something they conflict with is still in
+/// the intersection: the tail of the previous phase, a cyclist they would
+/// cut off turning right, or a pedestrian on their crosswalk. On amber only
+/// vehicles that can no longer stop go. Pedestrians walk alongside the
+/// straight movements of the green phase, over the crosswalks those do not
+/// pass, and turning vehicles yield to them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signal {
+    pub controller: Controller,
+    pub phases: Vec<Phase>,
+    /// Index of the phase that has green, or had it last
+    pub current: usize,
+    pub interval: Interval,
+    /// Time since the current interval started (in seconds)
+    pub elapsed: f32,
+    /// Time since a vehicle was last detected on a green movement (in seconds)
+    pub since_detection: f32,
+    /// Agents allowed to cross, recomputed every step
+    pub going: HashSet<Agent>,
+    /// Number of times a phase got green after the first
+    pub phase_changes: u32,
+    /// Actuated phases that ended because their detectors were empty
+    pub gap_outs: u32,
+    /// Actuated phases that ended because they reached their longest green
+    pub max_outs: u32,
+}
+
+impl Signal {
+    pub fn new(controller: Controller, phases: Vec<Phase>) -> Self {
+        Signal {
+            controller,
+            phases,
+            current: 0,
+            interval: Interval::Green,
+            elapsed: 0.0,
+            since_detection: 0.0,
+            going: HashSet::new(),
+            phase_changes: 0,
+            gap_outs: 0,
+            max_outs: 0,
+        }
+    }
+
+    /// Length of one full cycle through every phase under fixed-time control (in seconds)
+    pub fn cycle_length(&self) -> f32 {
+        match self.controller {
+            Controller::FixedTime { green, amber, all_red } => self.phases
+                .iter()
+                .map(|phase| phase.green.unwrap_or(green) + amber + all_red)
+                .sum(),
+            Controller::Actuated { .. } | Controller::MaxPressure { .. } => 0.0,
+        }
+    }
+
+    /// What the signal head of `movement` shows
+    pub fn aspect(&self, movement: Movement) -> Aspect {
+        if !self.phases[self.current].serves(movement) {
+            return Aspect::Red;
+        }
+        match self.interval {
+            Interval::Green => Aspect::Green,
+            Interval::Amber => Aspect::Amber,
+            Interval::AllRed => Aspect::Red,
+        }
+    }
+
+    /// Whether pedestrians may start over the crosswalk on `arm`: the green
+    /// phase has straight movements and none of them passes it
+    pub fn walk(&self, arm: Direction) -> bool {
+        let mut straight = self.phases[self.current]
+            .movements
+            .iter()
+            .filter(|(_, route)| *route == Route::Straight)
+            .peekable();
+        self.interval == Interval::Green
+            && straight.peek().is_some()
+            && straight.all(|movement| !uses_crosswalk(*movement, arm))
+    }
+
+    /// Advances the signal timing and decides who may cross
+    pub fn update(
+        &mut self,
+        lanes: &HashMap<Direction, Vec<Vehicle>>,
+        pedestrians: &[Pedestrian],
+        delta_time: f32,
+    ) {
+        self.update_timing(lanes, delta_time);
+        self.update_going(lanes, pedestrians);
+    }
+
+    fn update_timing(&mut self, lanes: &HashMap<Direction, Vec<Vehicle>>, delta_time: f32) {
+        self.elapsed += delta_time;
+        match self.interval {
+            Interval::Green => {
+                if self.green_ends(lanes, delta_time) {
+                    self.interval = Interval::Amber;
+                    self.elapsed = 0.0;
+                }
+            }
+            Interval::Amber => {
+                if self.elapsed >= self.controller.amber() {
+                    self.interval = Interval::AllRed;
+                    self.elapsed = 0.0;
+                }
+            }
+            Interval::AllRed => {
+                if self.elapsed >= self.controller.all_red() {
+                    self.current = self.next_phase(lanes);
+                    self.interval = Interval::Green;
+                    self.elapsed = 0.0;
+                    self.since_detection = 0.0;
+                    self.phase_changes += 1;
+                }
+            }
+        }
+    }
+
+    /// Whether the green of the current phase is over
+    fn green_ends(&mut self, lanes: &HashMap<Direction, Vec<Vehicle>>, delta_time: f32) -> bool {
+        match self.controller {
+            Controller::FixedTime { green, .. } => self.elapsed >= self.phases[self.current].green.unwrap_or(green),
+            Controller::Actuated { min_green, max_green, gap, .. } => {
+                if self.has_call(lanes, self.current) {
+                    self.since_detection = 0.0;
+                } else {
+                    self.since_detection += delta_time;
+                }
+                let waiting = (0..self.phases.len()).any(|index| index != self.current && self.has_call(lanes, index));
+                if self.elapsed < min_green || !waiting {
+                    false
+                } else if self.since_detection >= gap {
+                    self.gap_outs += 1;
+                    true
+                } else if self.elapsed >= max_green {
+                    self.max_outs += 1;
+                    true
+                } else {
+                    false
+                }
+            }
+            Controller::MaxPressure { interval, .. } => {
+                // Decide once every interval of green
+                let decisions = (self.elapsed / interval).floor();
+                let before = ((self.elapsed - delta_time) / interval).floor();
+                let current = self.pressure(lanes, self.current);
+                decisions > before && self.strongest_other(lanes).is_some_and(|(_, pressure)| pressure > current)
+            }
+        }
+    }
+
+    /// Phase to get green next: the following one under fixed-time control,
+    /// the following one with a call under actuated control, and the other
+    /// one with the highest pressure under max-pressure control
+    fn next_phase(&self, lanes: &HashMap<Direction, Vec<Vehicle>>) -> usize {
+        let count = self.phases.len();
+        match self.controller {
+            Controller::FixedTime { .. } => (self.current + 1) % count,
+            Controller::MaxPressure { .. } => self
+                .strongest_other(lanes)
+                .map_or((self.current + 1) % count, |(index, _)| index),
+            Controller::Actuated { .. } => (1..=count)
+                .map(|offset| (self.current + offset) % count)
+                .find(|index| self.has_call(lanes, *index))
+                .unwrap_or((self.current + 1) % count),
+        }
+    }
+
+    /// Pressure of a phase: for each of its movements, the vehicles queued
+    /// upstream of the stop line less those occupying the road it leads to
+    pub fn pressure(&self, lanes: &HashMap<Direction, Vec<Vehicle>>, phase: usize) -> i32 {
+        self.phases[phase]
+            .movements
+            .iter()
+            .map(|movement| {
+                let downstream = downstream_occupancy(lanes, exit_direction(*movement));
+                queue_length(lanes, *movement) as i32 - downstream as i32
+            })
+            .sum()
+    }
+
+    /// Phase other than the current one with the highest pressure, the
+    /// first in order on ties
+    fn strongest_other(&self, lanes: &HashMap<Direction, Vec<Vehicle>>) -> Option<(usize, i32)> {
+        let count = self.phases.len();
+        (1..count)
+            .map(|offset| (self.current + offset) % count)
+            .map(|index| (index, self.pressure(lanes, index)))
+            .fold(None, |best: Option<(usize, i32)>, (index, pressure)| match best {
+                Some((_, highest)) if highest >= pressure => best,
+                _ => Some((index, pressure)),
+            })
+    }
+
+    /// Whether a stop-bar loop of any movement in the phase is occupied
+    fn has_call(&self, lanes: &HashMap<Direction, Vec<Vehicle>>, phase: usize) -> bool {
+        self.phases[phase]
+            .movements
+            .iter()
+            .flat_map(|movement| stop_bar_loops(*movement))
+            .any(|detector| detector.occupied(lanes))
+    }
+
+    /// Lets through whoever the signal and the agents still in the
+    /// intersection allow
+    fn update_going(&mut self, lanes: &HashMap<Direction, Vec<Vehicle>>, pedestrians: &[Pedestrian]) {
+        let mut going = HashSet::new();
+        let mut claims: Vec<Claim> = Vec::new();
+        let mut vehicles: Vec<&Vehicle> = lanes
+            .values()
+            .flatten()
+            .filter(|vehicle| vehicle.active && vehicle.distance_to_intersection <= REQUEST_DISTANCE)
+            .filter(|vehicle| !IntersectionManager::has_cleared(vehicle))
+            .collect();
+        vehicles.sort_by(|a, b| {
+            a.distance_to_intersection
+                .total_cmp(&b.distance_to_intersection)
+                .then(a.id.cmp(&b.id))
+        });
+
+        // Whoever is crossing, or can no longer stop, keeps going
+        for pedestrian in pedestrians.iter().filter(|pedestrian| pedestrian.active && pedestrian.crossing) {
+            going.insert(Agent::Pedestrian(pedestrian.id));
+            claims.push(Claim::Crosswalk(pedestrian.arm));
+        }
+        let mut caught = Vec::new();
+        let mut deciding = Vec::new();
+        for vehicle in vehicles {
+            let agent = Agent::Vehicle(vehicle.id);
+            let aspect = self.aspect((vehicle.direction, vehicle.route));
+            let stoppable = IntersectionManager::can_stop(vehicle);
+            if IntersectionManager::has_entered(vehicle) || (!stoppable && self.going.contains(&agent)) {
+                going.insert(agent);
+                claims.push(vehicle_claim(vehicle));
+            } else if !stoppable && aspect == Aspect::Amber {
+                caught.push(vehicle);
+            } else {
+                deciding.push((vehicle, aspect));
+            }
+        }
+
+        // Vehicles the amber caught too close to stop go as well, unless
+        // that would take them into the path of someone already committed:
+        // then they brake as hard as they can instead
+        for vehicle in caught {
+            let claim = vehicle_claim(vehicle);
+            if !claims.iter().any(|other| claims_conflict(*other, claim)) {
+                going.insert(Agent::Vehicle(vehicle.id));
+                claims.push(claim);
+            }
+        }
+
+        for pedestrian in pedestrians.iter().filter(|pedestrian| pedestrian.active && !pedestrian.crossing) {
+            let claim = Claim::Crosswalk(pedestrian.arm);
+            if self.walk(pedestrian.arm) && !claims.iter().any(|other| claims_conflict(*other, claim)) {
+                going.insert(Agent::Pedestrian(pedestrian.id));
+                claims.push(claim);
+            }
+        }
+
+        // The nearest vehicles with green go first
+        for (vehicle, aspect) in deciding {
+            let claim = vehicle_claim(vehicle);
+            if aspect == Aspect::Green && !claims.iter().any(|other| claims_conflict(*other, claim)) {
+                going.insert(Agent::Vehicle(vehicle.id));
+                claims.push(claim);
+            }
+        }
+        self.going = going;
+    }
+
+    /// Whether the vehicle has to stop at the stop line
+    pub fn must_wait(&self, vehicle: &Vehicle) -> bool {
+        !IntersectionManager::has_cleared(vehicle) && !self.going.contains(&Agent::Vehicle(vehicle.id))
+    }
+
+    /// Whether the pedestrian may step onto (or stay on) the crosswalk
+    pub fn may_cross(&self, pedestrian_id: u32) -> bool {
+        self.going.contains(&Agent::Pedestrian(pedestrian_id))
+    }
+}
+
+/// Presence loops in front of the stop line of `movement`: one over its
+/// lane and one over the bike lane, called by the cyclists taking it
+pub fn stop_bar_loops(movement: Movement) -> [LoopDetector; 2] {
+    let (direction, route) = movement;
+    let distance = STOP_LINE_DISTANCE + DETECTOR_LENGTH;
+    [
+        LoopDetector::new(direction, route.lane_index(), distance, DETECTOR_LENGTH),
+        LoopDetector::new(direction, BIKE_LANE, distance, DETECTOR_LENGTH).for_route(route),
+    ]
+}
+
+/// Number of vehicles on the approach lane of `movement`, and of cyclists
+/// taking it from the bike lane, that have not driven into the
+/// intersection yet
+pub fn queue_length(lanes: &HashMap<Direction, Vec<Vehicle>>, movement: Movement) -> usize {
+    lanes.get(&movement.0).map_or(0, |lane| {
+        lane.iter()
+            .filter(|vehicle| vehicle.active && vehicle.route == movement.1)
+            .filter(|vehicle| !IntersectionManager::has_entered(vehicle) && !IntersectionManager::has_cleared(vehicle))
+            .count()
+    })
+}
+
+/// Number of vehicles that left the intersection towards `arm` and are
+/// still on the road there
+pub fn downstream_occupancy(lanes: &HashMap<Direction, Vec<Vehicle>>, arm: Direction) -> usize {
+    lanes
+        .values()
+        .flatten()
+        .filter(|vehicle| vehicle.active && IntersectionManager::has_cleared(vehicle))
+        .filter(|vehicle| exit_direction((vehicle.direction, vehicle.route)) == arm)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::vehicle_type::VehicleType;
+    use crate::simulation::test_support::{lanes, stopped};
+
+    const TICK: f32 = 0.1;
+
+    fn signal(controller: &str) -> Signal {
+        Signal::new(controller.parse().unwrap(), parse_phases(DEFAULT_PHASES).unwrap())
+    }
+
+    #[test]
+    fn test_parse_controller_and_phases() {
+        let controller: Controller = "fixed:30:4:1".parse().unwrap();
+        assert_eq!(controller, Controller::FixedTime { green: 30.0, amber: 4.0, all_red: 1.0 });
+        assert_eq!(controller.to_string().parse(), Ok(controller));
+        let controller: Controller = "actuated".parse().unwrap();
+        assert_eq!(controller.to_string().parse(), Ok(controller));
+        let controller: Controller = "max-pressure:4:3:2".parse().unwrap();
+        assert_eq!(controller, Controller::MaxPressure { interval: 4.0, amber: 3.0, all_red: 2.0 });
+        assert_eq!(controller.to_string().parse(), Ok(controller));
+        assert!("fixed:30".parse::<Controller>().is_err());
+        assert!("actuated:40:5:2:3:2".parse::<Controller>().is_err());
+
+        let phases = parse_phases("NS/SR:30,NS/L,EW/SR,EW/L").unwrap();
+        assert_eq!(phases.len(), 4);
+        assert_eq!(phases[0].green, Some(30.0));
+        assert!(phases[0].serves((Direction::South, Route::Right)));
+        assert_eq!(phases[0].to_string().parse(), Ok(phases[0].clone()));
+        // Left turns against oncoming straight traffic
+        assert!(parse_phases("NS/SRL,EW/SRL").is_err());
+        // Nothing serves the east and west approaches
+        assert!(parse_phases("NS/SR,NS/L").is_err());
+    }
+
+    #[test]
+    fn test_fixed_time_cycles_through_phases() {
+        let mut signal = signal("fixed:10:3:2");
+        let lanes = lanes(Vec::new());
+        assert_eq!(signal.cycle_length(), 60.0);
+        assert_eq!(signal.aspect((Direction::North, Route::Straight)), Aspect::Green);
+        assert_eq!(signal.aspect((Direction::East, Route::Straight)), Aspect::Red);
+
+        let steps = |seconds: f32| (seconds / TICK).round() as usize;
+        for _ in 0..steps(10.0) + 1 {
+            signal.update(&lanes, &[], TICK);
+        }
+        assert_eq!(signal.aspect((Direction::North, Route::Straight)), Aspect::Amber);
+        for _ in 0..steps(5.0) + 1 {
+            signal.update(&lanes, &[], TICK);
+        }
+        assert_eq!(signal.current, 1);
+        assert_eq!(signal.aspect((Direction::North, Route::Left)), Aspect::Green);
+        assert_eq!(signal.phase_changes, 1);
+    }
+
+    #[test]
+    fn test_only_green_movements_go() {
+        let mut signal = signal("fixed");
+        let north = stopped(1, Direction::North, Route::Straight);
+        let east = stopped(2, Direction::East, Route::Straight);
+        let pedestrian = Pedestrian::new(1, Direction::East, true, 1.4);
+        signal.update(&lanes(vec![north.clone(), east.clone()]), &[pedestrian], TICK);
+        assert!(!signal.must_wait(&north));
+        assert!(signal.must_wait(&east));
+        // The east crosswalk is parallel to the northbound traffic
+        assert!(signal.may_cross(1));
+
+        // A right turn yields to the pedestrian walking over its exit crosswalk
+        let mut walking = Pedestrian::new(1, Direction::East, true, 1.4);
+        walking.crossing = true;
+        let right = stopped(3, Direction::North, Route::Right);
+        signal.update(&lanes(vec![right.clone()]), &[walking], TICK);
+        assert!(signal.must_wait(&right));
+    }
+
+    #[test]
+    fn test_actuated_skips_empty_phases_and_gaps_out() {
+        let mut signal = signal("actuated:5:40:2:3:2");
+        let east = stopped(1, Direction::East, Route::Straight);
+        let lanes = lanes(vec![east.clone()]);
+
+        // No call on the first phase: it gaps out after its shortest green
+        let steps = |seconds: f32| (seconds / TICK).round() as usize;
+        for _ in 0..steps(5.0) + 1 {
+            signal.update(&lanes, &[], TICK);
+        }
+        assert_eq!(signal.interval, Interval::Amber);
+        assert_eq!(signal.gap_outs, 1);
+        for _ in 0..steps(5.0) + 1 {
+            signal.update(&lanes, &[], TICK);
+        }
+        // The left-turn phase has no call and is skipped
+        assert_eq!(signal.current, 2);
+        assert!(!signal.must_wait(&east));
+
+        // With no call anywhere else, the green rests on the east phase
+        for _ in 0..steps(60.0) {
+            signal.update(&lanes, &[], TICK);
+        }
+        assert_eq!(signal.interval, Interval::Green);
+        assert_eq!(signal.max_outs, 0);
+    }
+
+    #[test]
+    fn test_max_pressure_serves_the_heaviest_phase() {
+        let mut signal = signal("max-pressure:5:3:2");
+        // Two waiting left turns from the west against one through vehicle
+        // from the north, whose exit road is empty
+        let mut vehicles = vec![
+            stopped(1, Direction::South, Route::Straight),
+            stopped(2, Direction::East, Route::Left),
+            stopped(3, Direction::West, Route::Left),
+        ];
+        let mut behind = stopped(4, Direction::East, Route::Left);
+        behind.distance_to_intersection += 10.0;
+        vehicles.push(behind);
+        let queued = lanes(vehicles);
+        assert_eq!(signal.pressure(&queued, 0), 1);
+        assert_eq!(signal.pressure(&queued, 3), 3);
+
+        let steps = |seconds: f32| (seconds / TICK).round() as usize;
+        for _ in 0..steps(5.0) + 1 {
+            signal.update(&queued, &[], TICK);
+        }
+        assert_eq!(signal.interval, Interval::Amber);
+        for _ in 0..steps(5.0) + 1 {
+            signal.update(&queued, &[], TICK);
+        }
+        // Straight to the east-west left turns, skipping the phases in between
+        assert_eq!(signal.current, 3);
+
+        // Vehicles that left towards the west weigh against the phase
+        let departed = Vehicle::new(5, (0.0, 0.0), 10.0, Route::Straight, Direction::West, -40.0);
+        let busy = lanes(vec![stopped(6, Direction::West, Route::Straight), departed]);
+        assert_eq!(downstream_occupancy(&busy, Direction::West), 1);
+        assert_eq!(signal.pressure(&busy, 2), 0);
+    }
+
+    #[test]
+    fn test_phase_switch_keeps_cyclist_out_of_right_turn() {
+        let mut signal = signal("max-pressure:5:3:2");
+        // A cyclist in the bike lane adds to the pressure of its movement
+        let mut cyclist = Vehicle::new(1, (0.0, 0.0), 10.0, Route::Straight, Direction::South, 0.0)
+            .with_type(VehicleType::Bicycle);
+        cyclist.distance_to_intersection = STOP_LINE_DISTANCE + cyclist.spec().length / 2.0 + 0.5;
+        let waiting = lanes(vec![cyclist.clone(), stopped(2, Direction::South, Route::Right)]);
+        assert_eq!(signal.pressure(&waiting, 0), 2);
+
+        // The right-turner took the green; the cyclist arrives too fast to
+        // stop in front of the line just as the phase switches to amber
+        let mut turning = stopped(2, Direction::South, Route::Right);
+        turning.distance_to_intersection = 5.0;
+        turning.velocity = 5.0;
+        let approaching = lanes(vec![cyclist, turning]);
+        assert!(!IntersectionManager::can_stop(&approaching[&Direction::South][0]));
+        signal.update(&approaching, &[], TICK);
+        signal.interval = Interval::Amber;
+        signal.elapsed = 0.0;
+        signal.update(&approaching, &[], TICK);
+
+        assert!(signal.going.contains(&Agent::Vehicle(2)));
+        assert!(!signal.going.contains(&Agent::Vehicle(1)));
+    }
+}
@@ -0,0 +1,26 @@
+//! Fixtures shared by the simulation tests
+use std::collections::HashMap;
+use super::manager::STOP_LINE_DISTANCE;
+use super::vehicle::{Direction, Route, Vehicle};
+
+/// Lanes holding the given vehicles, each on its own approach
+pub fn lanes(vehicles: Vec<Vehicle>) -> HashMap<Direction, Vec<Vehicle>> {
+    let mut lanes: HashMap<Direction, Vec<Vehicle>> = HashMap::new();
+    for vehicle in vehicles {
+        lanes.entry(vehicle.direction).or_default().push(vehicle);
+    }
+    lanes
+}
+
+/// A car driving at 10 m/s, `distance` meters from the centre
+pub fn vehicle(id: u32, direction: Direction, route: Route, distance: f32) -> Vehicle {
+    Vehicle::new(id, (0.0, 0.0), 10.0, route, direction, distance)
+}
+
+/// A vehicle standing with its front on the stop line
+pub fn stopped(id: u32, direction: Direction, route: Route) -> Vehicle {
+    let mut vehicle = Vehicle::new(id, (0.0, 0.0), 10.0, route, direction, 0.0);
+    vehicle.distance_to_intersection = STOP_LINE_DISTANCE + vehicle.spec().length / 2.0 + 0.5;
+    vehicle.velocity = 0.0;
+    vehicle
+}
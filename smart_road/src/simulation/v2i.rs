@@ -0,0 +1,368 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use rand::Rng;
+use super::auction::AuctionOutcome;
+use super::manager::{IntersectionManager, REQUEST_DISTANCE};
+use super::pedestrian::Pedestrian;
+use super::rng::SimRng;
+use super::vehicle::{Direction, Vehicle};
+
+/// Time without a position update after which the manager forgets a
+/// vehicle, in case its cancel message was lost (in seconds)
+pub const VIEW_TIMEOUT: f32 = 2.0;
+
+/// Time a restarted manager listens to position updates before answering,
+/// so it does not grant anything before it has heard of the vehicles
+/// already inside the intersection (in seconds)
+pub const RESTART_LISTEN_TIME: f32 = 0.5;
+
+/// Delay and loss of the messages sent over the channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelConfig {
+    pub latency: f32,       // Mean delivery delay (in seconds)
+    pub jitter: f32,        // Largest deviation from the mean delay (in seconds)
+    pub drop_rate: f32,     // Share of messages that are lost (0–1)
+}
+
+impl FromStr for ChannelConfig {
+    type Err = String;
+
+    /// Parses `<latency>:<jitter>:<drop rate>`, e.g. `0.1:0.05:0.02`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(':')
+            .map(|value| value.trim().parse::<f32>().map_err(|_| format!("Invalid number: {}", value)))
+            .collect::<Result<Vec<f32>, String>>()?;
+        match values.as_slice() {
+            [latency, jitter, drop_rate]
+                if *latency >= 0.0 && *jitter >= 0.0 && (0.0..=1.0).contains(drop_rate) =>
+            {
+                Ok(ChannelConfig { latency: *latency, jitter: *jitter, drop_rate: *drop_rate })
+            }
+            _ => Err(format!("Expected latency:jitter:drop rate, found: {}", s)),
+        }
+    }
+}
+
+/// A message between a vehicle and the intersection manager
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// First contact, carrying the state of the vehicle asking to cross
+    Request(Vehicle),
+    /// Latest state of a vehicle that already asked to cross
+    PositionUpdate(Vehicle),
+    /// The vehicle has crossed and no longer needs a reservation
+    Cancel(u32),
+    /// The vehicle holds a reservation and may enter
+    Grant(u32),
+    /// The vehicle has no reservation (any more) and has to wait
+    Reject(u32),
+}
+
+/// A message on its way, delivered once the channel time reaches `delivery`
+#[derive(Debug, Clone)]
+pub struct InFlight {
+    pub delivery: f32,
+    pub message: Message,
+}
+
+/// Vehicle-to-infrastructure communication between the vehicles and the
+/// intersection manager
+///
+/// Automated vehicles within `REQUEST_DISTANCE` send a request and then a
+/// position update every step, and cancel once they have crossed. The
+/// manager only knows the vehicles from these messages, and answers every
+/// request and update with a grant or a reject; vehicles go on the last
+/// answer they received. Messages in both directions are delayed by the
+/// latency plus or minus the jitter, and a share of them is lost.
+///
+/// Human drivers and pedestrians do not communicate; the roadside unit
+/// senses them directly, as it does without the channel.
+#[derive(Debug, Clone)]
+pub struct V2iLink {
+    pub config: ChannelConfig,
+    pub rng: SimRng,
+    /// Time since the link was set up (in seconds)
+    pub time: f32,
+    /// Messages from the vehicles to the manager
+    pub uplink: Vec<InFlight>,
+    /// Messages from the manager to the vehicles
+    pub downlink: Vec<InFlight>,
+    /// Latest state of each vehicle the manager heard of, and when it heard it
+    pub view: HashMap<u32, (Vehicle, f32)>,
+    /// Vehicles that sent their request and have not cancelled yet
+    pub requested: HashSet<u32>,
+    /// Vehicles whose last answer from the manager was a grant
+    pub granted: HashSet<u32>,
+    /// Vehicles that have entered the intersection
+    pub entered: HashSet<u32>,
+    /// Time the restarted manager still listens before answering (in seconds)
+    pub listening: f32,
+    /// Whether an answer from the manager reached a vehicle this step
+    pub answered: bool,
+    pub sent: u64,
+    pub dropped: u64,
+    /// Vehicles that entered without the manager granting them
+    pub unauthorized_entries: u32,
+}
+
+impl V2iLink {
+    pub fn new(config: ChannelConfig, seed: u64) -> Self {
+        V2iLink {
+            config,
+            rng: SimRng::new(seed),
+            time: 0.0,
+            uplink: Vec::new(),
+            downlink: Vec::new(),
+            view: HashMap::new(),
+            requested: HashSet::new(),
+            granted: HashSet::new(),
+            entered: HashSet::new(),
+            listening: 0.0,
+            answered: false,
+            sent: 0,
+            dropped: 0,
+            unauthorized_entries: 0,
+        }
+    }
+
+    /// Exchanges this step's messages and lets the manager decide on what
+    /// it knows
+    ///
+    /// While the manager is down, what reaches it is lost and nothing is
+    /// answered. Returns the outcomes of the vehicles that won an auction
+    /// this step.
+    pub fn update(
+        &mut self,
+        lanes: &HashMap<Direction, Vec<Vehicle>>,
+        pedestrians: &[Pedestrian],
+        manager: Option<&mut IntersectionManager>,
+        delta_time: f32,
+    ) -> Vec<AuctionOutcome> {
+        self.time += delta_time;
+        self.send_vehicle_messages(lanes);
+
+        let delivered = Self::deliver(&mut self.uplink, self.time);
+        let mut outcomes = Vec::new();
+        if let Some(manager) = manager {
+            // The manager updates its view from what arrived
+            let mut answer = Vec::new();
+            for message in delivered {
+                match message {
+                    Message::Request(vehicle) | Message::PositionUpdate(vehicle) => {
+                        answer.push(vehicle.id);
+                        self.view.insert(vehicle.id, (vehicle, self.time));
+                    }
+                    Message::Cancel(id) => {
+                        self.view.remove(&id);
+                    }
+                    Message::Grant(_) | Message::Reject(_) => {}
+                }
+            }
+            let time = self.time;
+            self.view.retain(|_, (_, heard)| time - *heard <= VIEW_TIMEOUT);
+            if self.listening > 0.0 {
+                self.listening -= delta_time;
+                answer.clear();
+            }
+
+            let mut known: HashMap<Direction, Vec<Vehicle>> = HashMap::new();
+            let sensed = lanes.values().flatten().filter(|vehicle| vehicle.is_human());
+            for vehicle in self.view.values().map(|(vehicle, _)| vehicle).chain(sensed) {
+                known.entry(vehicle.direction).or_default().push(vehicle.clone());
+            }
+            if self.listening <= 0.0 {
+                outcomes = manager.update(&known, pedestrians, delta_time);
+            }
+
+            for id in answer {
+                let message = if manager.is_granted(id) { Message::Grant(id) } else { Message::Reject(id) };
+                self.send_to_vehicle(message);
+            }
+        }
+
+        self.answered = false;
+        for message in Self::deliver(&mut self.downlink, self.time) {
+            match message {
+                Message::Grant(id) => {
+                    self.granted.insert(id);
+                }
+                Message::Reject(id) => {
+                    self.granted.remove(&id);
+                }
+                _ => continue,
+            }
+            self.answered = true;
+        }
+        outcomes
+    }
+
+    /// Forgets what the manager knew, after it failed, and lets it listen
+    /// before it answers again
+    pub fn restart(&mut self) {
+        self.view.clear();
+        self.listening = RESTART_LISTEN_TIME;
+    }
+
+    /// Vehicles in range send their request or position; those that crossed cancel
+    fn send_vehicle_messages(&mut self, lanes: &HashMap<Direction, Vec<Vehicle>>) {
+        let mut automated: Vec<&Vehicle> = lanes
+            .values()
+            .flatten()
+            .filter(|vehicle| vehicle.active && !vehicle.is_human())
+            .collect();
+        // In id order, so the channel draws do not depend on lane iteration order
+        automated.sort_by_key(|vehicle| vehicle.id);
+        for vehicle in &automated {
+            let in_range = vehicle.distance_to_intersection <= REQUEST_DISTANCE;
+            let requested = self.requested.contains(&vehicle.id);
+            if IntersectionManager::has_cleared(vehicle) {
+                if requested {
+                    self.requested.remove(&vehicle.id);
+                    self.send_to_manager(Message::Cancel(vehicle.id));
+                }
+            } else if requested {
+                self.send_to_manager(Message::PositionUpdate((*vehicle).clone()));
+            } else if in_range {
+                self.requested.insert(vehicle.id);
+                self.send_to_manager(Message::Request((*vehicle).clone()));
+            }
+        }
+
+        // Vehicles that left forget their answers
+        let present: HashSet<u32> = automated.iter().map(|vehicle| vehicle.id).collect();
+        self.requested.retain(|id| present.contains(id));
+        self.granted.retain(|id| present.contains(id));
+        self.entered.retain(|id| present.contains(id));
+    }
+
+    /// Counts vehicles that entered the intersection since the last step
+    /// without a reservation from the manager
+    pub fn check_entries(&mut self, lanes: &HashMap<Direction, Vec<Vehicle>>, manager: &IntersectionManager) {
+        for vehicle in lanes.values().flatten().filter(|vehicle| vehicle.active && !vehicle.is_human()) {
+            if IntersectionManager::has_entered(vehicle) && self.entered.insert(vehicle.id) && !manager.is_granted(vehicle.id) {
+                self.unauthorized_entries += 1;
+            }
+        }
+    }
+
+    fn send_to_manager(&mut self, message: Message) {
+        if let Some(delivery) = self.transmit() {
+            self.uplink.push(InFlight { delivery, message });
+        }
+    }
+
+    fn send_to_vehicle(&mut self, message: Message) {
+        if let Some(delivery) = self.transmit() {
+            self.downlink.push(InFlight { delivery, message });
+        }
+    }
+
+    /// Delivery time of a message sent now, or `None` if it is lost
+    fn transmit(&mut self) -> Option<f32> {
+        self.sent += 1;
+        if self.config.drop_rate > 0.0 && self.rng.random_bool(self.config.drop_rate as f64) {
+            self.dropped += 1;
+            return None;
+        }
+        let jitter = if self.config.jitter > 0.0 {
+            self.rng.random_range(-self.config.jitter..=self.config.jitter)
+        } else {
+            0.0
+        };
+        Some(self.time + (self.config.latency + jitter).max(0.0))
+    }
+
+    /// Takes the messages due by `time` off the channel, in delivery order
+    fn deliver(channel: &mut Vec<InFlight>, time: f32) -> Vec<Message> {
+        let mut due: Vec<InFlight> = Vec::new();
+        channel.retain(|in_flight| {
+            if in_flight.delivery <= time {
+                due.push(in_flight.clone());
+                false
+            } else {
+                true
+            }
+        });
+        due.sort_by(|a, b| a.delivery.total_cmp(&b.delivery));
+        due.into_iter().map(|in_flight| in_flight.message).collect()
+    }
+
+    /// Whether the vehicle has to stop at the stop line, going by the last
+    /// answer it received
+    pub fn must_wait(&self, vehicle: &Vehicle) -> bool {
+        !IntersectionManager::has_cleared(vehicle) && !self.granted.contains(&vehicle.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::vehicle::Route;
+    use crate::simulation::test_support::lanes;
+
+    const TICK: f32 = 0.1;
+
+    fn config(latency: f32, drop_rate: f32) -> ChannelConfig {
+        ChannelConfig { latency, jitter: 0.0, drop_rate }
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config: ChannelConfig = "0.1:0.05:0.02".parse().unwrap();
+        assert_eq!(config, ChannelConfig { latency: 0.1, jitter: 0.05, drop_rate: 0.02 });
+        assert!("0.1:0.05".parse::<ChannelConfig>().is_err());
+        assert!("0.1:0.05:2".parse::<ChannelConfig>().is_err());
+    }
+
+    #[test]
+    fn test_grant_arrives_after_round_trip() {
+        let mut link = V2iLink::new(config(0.25, 0.0), 1);
+        let mut manager = IntersectionManager::new();
+        let vehicle = Vehicle::new(1, (0.0, 0.0), 10.0, Route::Straight, Direction::North, 40.0);
+        let lanes = lanes(vec![vehicle.clone()]);
+
+        for _ in 0..4 {
+            link.update(&lanes, &[], Some(&mut manager), TICK);
+        }
+        // The manager has heard of it, but the grant is still on its way
+        assert!(manager.is_granted(1));
+        assert!(link.must_wait(&vehicle));
+
+        for _ in 0..3 {
+            link.update(&lanes, &[], Some(&mut manager), TICK);
+        }
+        assert!(!link.must_wait(&vehicle));
+    }
+
+    #[test]
+    fn test_lost_messages_are_counted() {
+        let mut link = V2iLink::new(config(0.0, 1.0), 1);
+        let mut manager = IntersectionManager::new();
+        let vehicle = Vehicle::new(1, (0.0, 0.0), 10.0, Route::Straight, Direction::North, 40.0);
+        for _ in 0..10 {
+            link.update(&lanes(vec![vehicle.clone()]), &[], Some(&mut manager), TICK);
+        }
+        assert!(manager.requests.is_empty());
+        assert!(link.must_wait(&vehicle));
+        assert_eq!(link.sent, 10);
+        assert_eq!(link.dropped, 10);
+    }
+
+    #[test]
+    fn test_forgets_silent_vehicles() {
+        let mut link = V2iLink::new(config(0.0, 0.0), 1);
+        let mut manager = IntersectionManager::new();
+        let vehicle = Vehicle::new(1, (0.0, 0.0), 10.0, Route::Straight, Direction::North, 40.0);
+        link.update(&lanes(vec![vehicle]), &[], Some(&mut manager), TICK);
+        assert_eq!(link.view.len(), 1);
+
+        // It vanished without its cancel getting through
+        let steps = (VIEW_TIMEOUT / TICK) as usize + 2;
+        for _ in 0..steps {
+            link.update(&HashMap::new(), &[], Some(&mut manager), TICK);
+        }
+        assert!(link.view.is_empty());
+        assert!(manager.requests.is_empty());
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::{HashMap, HashSet};
+use super::conflict::{claims_conflict, vehicle_claim, Claim};
+use super::driver::time_to_zone_when_going;
+use super::manager::{Agent, IntersectionManager, REQUEST_DISTANCE};
+use super::pedestrian::Pedestrian;
+use super::vehicle::{Direction, Vehicle};
+
+/// Default distance over which vehicles hear each other (in meters)
+///
+/// Twice `REQUEST_DISTANCE`, so every announcing agent hears every other.
+pub const DEFAULT_COMMUNICATION_RANGE: f32 = 2.0 * REQUEST_DISTANCE;
+
+/// Smallest time between the planned arrival of a vehicle and the vehicle
+/// ahead of it in its lane (in seconds)
+const FOLLOWING_ARRIVAL_GAP: f32 = 0.1;
+
+/// What an agent broadcasts about its crossing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Intention {
+    pub agent: Agent,
+    pub claim: Claim,
+    pub location: (f32, f32),   // Where the agent is, to tell who is in range
+    pub arrival: f32,           // Planned arrival at the conflict zone (simulation time, in seconds)
+    pub priority: bool,         // Emergency vehicles and human drivers who have decided to go
+    pub committed: bool,        // Already on its way through and unable to yield
+}
+
+/// Decentralized right-of-way negotiation between vehicles, without a
+/// central manager
+///
+/// Every automated vehicle within `REQUEST_DISTANCE` of the centre, and
+/// every pedestrian at a curb, broadcasts its intention: its movement, when
+/// it plans to arrive at the conflict zone and whether it is already going.
+/// The planned arrival is announced once and never changes, and is never
+/// earlier than that of the vehicle ahead in the lane. Each agent applies
+/// the same rule to what it hears within the communication range: it takes
+/// right of way once nobody it conflicts with is going or comes before it,
+/// where emergency vehicles come first, then agents already on their way
+/// and then the earliest planned arrival, ties going to the lower id.
+///
+/// Human drivers do not negotiate. Once they decide to go they are heard
+/// like emergency vehicles, which take right of way back from conflicting
+/// agents that can still stop; vehicles queued behind a human who has not
+/// gone yet stay silent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Negotiation {
+    /// Distance over which intentions are heard (in meters)
+    pub range: f32,
+    /// Time since the negotiation started (in seconds)
+    pub time: f32,
+    /// Planned arrival each agent announced
+    pub arrivals: HashMap<Agent, f32>,
+    /// Agents that took right of way and hold it until they have crossed
+    pub going: HashSet<Agent>,
+    /// Intentions heard by agents deciding whether to go, in total
+    pub messages: u64,
+}
+
+impl Negotiation {
+    pub fn new(range: f32) -> Self {
+        Negotiation {
+            range,
+            time: 0.0,
+            arrivals: HashMap::new(),
+            going: HashSet::new(),
+            messages: 0,
+        }
+    }
+
+    /// Collects the intentions broadcast this step and lets the agents that
+    /// may go take right of way
+    pub fn update(
+        &mut self,
+        lanes: &HashMap<Direction, Vec<Vehicle>>,
+        pedestrians: &[Pedestrian],
+        delta_time: f32,
+    ) {
+        self.time += delta_time;
+        let intentions = self.broadcast(lanes, pedestrians);
+
+        // Forget agents that crossed or left
+        let present: HashSet<Agent> = intentions.iter().map(|intention| intention.agent).collect();
+        self.arrivals.retain(|agent, _| present.contains(agent));
+        self.going.retain(|agent| present.contains(agent));
+
+        // Humans who decided, and emergency vehicles that can no longer stop,
+        // go regardless; they take right of way back from conflicting agents
+        // that can still stop, as do emergency vehicles still approaching
+        for intention in intentions.iter().filter(|intention| intention.priority && intention.committed) {
+            self.going.insert(intention.agent);
+        }
+        for intention in intentions.iter().filter(|intention| !intention.priority && !intention.committed) {
+            let yields = intentions.iter().any(|other| {
+                other.priority && self.in_range(intention, other) && claims_conflict(other.claim, intention.claim)
+            });
+            if yields {
+                self.going.remove(&intention.agent);
+            }
+        }
+
+        let mut order: Vec<&Intention> = intentions.iter().collect();
+        order.sort_by(|a, b| precedence(a, &self.going).partial_cmp(&precedence(b, &self.going)).unwrap());
+        for index in 0..order.len() {
+            let intention = order[index];
+            if self.going.contains(&intention.agent) {
+                continue;
+            }
+            let heard: Vec<&Intention> = order
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index)
+                .map(|(_, other)| *other)
+                .filter(|other| self.in_range(intention, other))
+                .collect();
+            self.messages += heard.len() as u64;
+
+            let blocked = heard.iter().any(|other| {
+                let before = precedence(other, &self.going) < precedence(intention, &self.going);
+                claims_conflict(other.claim, intention.claim)
+                    && (self.going.contains(&other.agent) || before)
+            });
+            if !blocked {
+                self.going.insert(intention.agent);
+            }
+        }
+    }
+
+    /// Intentions of every agent taking part in the negotiation, announcing
+    /// the planned arrival of newcomers
+    fn broadcast(
+        &mut self,
+        lanes: &HashMap<Direction, Vec<Vehicle>>,
+        pedestrians: &[Pedestrian],
+    ) -> Vec<Intention> {
+        let mut vehicles: Vec<&Vehicle> = lanes
+            .values()
+            .flatten()
+            .filter(|vehicle| vehicle.active && vehicle.distance_to_intersection <= REQUEST_DISTANCE)
+            .filter(|vehicle| !IntersectionManager::has_cleared(vehicle))
+            .filter(|vehicle| vehicle.driver.is_none_or(|driver| driver.decided))
+            .filter(|vehicle| !IntersectionManager::behind_undecided_human(lanes, vehicle))
+            .collect();
+        // Front to back, so everyone knows the arrival of the vehicle ahead
+        vehicles.sort_by(|a, b| {
+            a.distance_to_intersection
+                .total_cmp(&b.distance_to_intersection)
+                .then(a.id.cmp(&b.id))
+        });
+
+        let mut intentions = Vec::new();
+        let mut lane_arrivals: HashMap<(Direction, usize), f32> = HashMap::new();
+        for vehicle in vehicles {
+            let agent = Agent::Vehicle(vehicle.id);
+            let lane = (vehicle.direction, vehicle.lane_index());
+            let arrival = match self.arrivals.get(&agent) {
+                Some(&arrival) => arrival,
+                None => {
+                    let ahead = lane_arrivals.get(&lane).map_or(f32::MIN, |arrival| arrival + FOLLOWING_ARRIVAL_GAP);
+                    (self.time + time_to_zone_when_going(vehicle)).max(ahead)
+                }
+            };
+            self.arrivals.insert(agent, arrival);
+            lane_arrivals.insert(lane, arrival);
+            intentions.push(Intention {
+                agent,
+                claim: vehicle_claim(vehicle),
+                location: approach_location(vehicle),
+                arrival,
+                priority: vehicle.priority || vehicle.is_human(),
+                committed: vehicle.is_human() || !IntersectionManager::can_stop(vehicle),
+            });
+        }
+
+        for pedestrian in pedestrians.iter().filter(|pedestrian| pedestrian.active) {
+            let agent = Agent::Pedestrian(pedestrian.id);
+            let arrival = *self.arrivals.entry(agent).or_insert(self.time);
+            intentions.push(Intention {
+                agent,
+                claim: Claim::Crosswalk(pedestrian.arm),
+                location: pedestrian.world_position(),
+                arrival,
+                priority: false,
+                committed: pedestrian.crossing,
+            });
+        }
+        intentions
+    }
+
+    /// Whether `a` and `b` are close enough to hear each other
+    fn in_range(&self, a: &Intention, b: &Intention) -> bool {
+        let (dx, dy) = (a.location.0 - b.location.0, a.location.1 - b.location.1);
+        (dx * dx + dy * dy).sqrt() <= self.range
+    }
+
+    /// Whether the vehicle has to stop at the stop line
+    pub fn must_wait(&self, vehicle: &Vehicle) -> bool {
+        !IntersectionManager::has_cleared(vehicle) && !self.going.contains(&Agent::Vehicle(vehicle.id))
+    }
+
+    /// Whether the pedestrian may step onto (or stay on) the crosswalk
+    pub fn may_cross(&self, pedestrian_id: u32) -> bool {
+        self.going.contains(&Agent::Pedestrian(pedestrian_id))
+    }
+}
+
+/// Sort key of an intention: lower keys come first
+fn precedence(intention: &Intention, going: &HashSet<Agent>) -> (bool, bool, f32, u8, u32) {
+    let (kind, id) = match intention.agent {
+        Agent::Vehicle(id) => (0, id),
+        Agent::Pedestrian(id) => (1, id),
+    };
+    let on_its_way = intention.committed || going.contains(&intention.agent);
+    (!intention.priority, !on_its_way, intention.arrival, kind, id)
+}
+
+/// Point on the approach axis where the vehicle is, centred on the intersection
+fn approach_location(vehicle: &Vehicle) -> (f32, f32) {
+    let distance = vehicle.distance_to_intersection;
+    match vehicle.direction {
+        Direction::North => (0.0, -distance),
+        Direction::South => (0.0, distance),
+        Direction::East => (-distance, 0.0),
+        Direction::West => (distance, 0.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::vehicle::Route;
+    use crate::simulation::test_support::{lanes, vehicle};
+
+    const TICK: f32 = 0.1;
+
+    #[test]
+    fn test_earliest_arrival_goes_first() {
+        let mut negotiation = Negotiation::new(DEFAULT_COMMUNICATION_RANGE);
+        let near = vehicle(1, Direction::North, Route::Straight, 30.0);
+        let far = vehicle(2, Direction::East, Route::Straight, 45.0);
+        let opposing = vehicle(3, Direction::South, Route::Straight, 48.0);
+        let vehicles = vec![near.clone(), far.clone(), opposing.clone()];
+        negotiation.update(&lanes(vehicles), &[], TICK);
+
+        assert!(!negotiation.must_wait(&near));
+        assert!(negotiation.must_wait(&far));
+        // Compatible with the vehicle that is going, but not with the one
+        // that arrives before it
+        assert!(negotiation.must_wait(&opposing));
+    }
+
+    #[test]
+    fn test_follower_never_plans_to_arrive_before_leader() {
+        let mut negotiation = Negotiation::new(DEFAULT_COMMUNICATION_RANGE);
+        let mut leader = vehicle(1, Direction::North, Route::Straight, 30.0);
+        leader.velocity = 0.0;
+        let follower = vehicle(2, Direction::North, Route::Straight, 40.0);
+        negotiation.update(&lanes(vec![leader, follower]), &[], TICK);
+
+        let arrivals = &negotiation.arrivals;
+        assert!(arrivals[&Agent::Vehicle(2)] > arrivals[&Agent::Vehicle(1)]);
+    }
+
+    #[test]
+    fn test_out_of_range_vehicles_do_not_hear_each_other() {
+        let mut negotiation = Negotiation::new(20.0);
+        let first = vehicle(1, Direction::North, Route::Straight, 40.0);
+        let second = vehicle(2, Direction::East, Route::Straight, 45.0);
+        negotiation.update(&lanes(vec![first.clone(), second.clone()]), &[], TICK);
+        assert!(!negotiation.must_wait(&first));
+        assert!(!negotiation.must_wait(&second));
+        assert!(negotiation.messages == 0);
+    }
+
+    #[test]
+    fn test_emergency_vehicle_takes_right_of_way() {
+        let mut negotiation = Negotiation::new(DEFAULT_COMMUNICATION_RANGE);
+        let crossing = vehicle(1, Direction::North, Route::Straight, 40.0);
+        negotiation.update(&lanes(vec![crossing.clone()]), &[], TICK);
+        assert!(!negotiation.must_wait(&crossing));
+
+        let mut ambulance = vehicle(2, Direction::East, Route::Straight, 48.0);
+        ambulance.priority = true;
+        negotiation.update(&lanes(vec![crossing.clone(), ambulance.clone()]), &[], TICK);
+        assert!(negotiation.must_wait(&crossing));
+        assert!(!negotiation.must_wait(&ambulance));
+    }
+}
@@ -1,3 +1,11 @@
+use std::fmt;
+use std::str::FromStr;
+use super::behaviour::Behaviour;
+use super::driver::HumanDriver;
+use super::physics::CONFLICT_ZONE_HALF_SIZE;
+use super::rogue::{Misbehaviour, Rogue, SPEEDING_FACTOR};
+use super::vehicle_type::{VehicleSpec, VehicleType};
+
 /// Represents the four cardinal directions a vehicle can come from
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Direction {
@@ -7,6 +15,43 @@ pub enum Direction {
     West,
 }
 
+impl Direction {
+    /// Compass heading of a vehicle travelling in this direction, in degrees
+    /// (0 = north, clockwise)
+    pub fn heading(&self) -> f32 {
+        match self {
+            Direction::North => 0.0,
+            Direction::East => 90.0,
+            Direction::South => 180.0,
+            Direction::West => 270.0,
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "North" => Ok(Direction::North),
+            "South" => Ok(Direction::South),
+            "East" => Ok(Direction::East),
+            "West" => Ok(Direction::West),
+            _ => Err(format!("Unknown direction: {}", s)),
+        }
+    }
+}
+
+/// Index of the bike lane along the curb of each approach, numbered after
+/// the three vehicle lanes
+pub const BIKE_LANE: usize = 3;
+
 /// Represents the route a vehicle will take at the intersection
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Route {
@@ -15,6 +60,61 @@ pub enum Route {
     Left,
 }
 
+impl Route {
+    /// Index of the lane used by this route, counted from the rightmost lane
+    pub fn lane_index(&self) -> usize {
+        match self {
+            Route::Right => 0,
+            Route::Straight => 1,
+            Route::Left => 2,
+        }
+    }
+
+    /// Route served by the lane at `index` (see `lane_index`), `None` for the
+    /// bike lane and unknown indices
+    pub fn from_lane_index(index: usize) -> Option<Route> {
+        match index {
+            0 => Some(Route::Right),
+            1 => Some(Route::Straight),
+            2 => Some(Route::Left),
+            _ => None,
+        }
+    }
+}
+
+/// Coarse state of a vehicle relative to the intersection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VehicleState {
+    /// Standing still
+    Stopped,
+    /// Driving towards the conflict zone
+    Approaching,
+    /// Inside the conflict zone
+    Crossing,
+    /// Past the conflict zone, heading for the exit
+    Departing,
+}
+
+impl fmt::Display for VehicleState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for VehicleState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Stopped" => Ok(VehicleState::Stopped),
+            "Approaching" => Ok(VehicleState::Approaching),
+            "Crossing" => Ok(VehicleState::Crossing),
+            "Departing" => Ok(VehicleState::Departing),
+            _ => Err(format!("Unknown vehicle state: {}", s)),
+        }
+    }
+}
+
 /// Represents a vehicle in the traffic simulation
 /// 
 /// Each vehicle has a unique ID, position, velocity, and planned route.
@@ -30,6 +130,15 @@ pub struct Vehicle {
     pub distance_to_intersection: f32,     // Distance remaining to the intersection in meters
     pub time_elapsed: f32,                 // in seconds
     pub active: bool,                       // Whether this vehicle is currently active in the simulation
+    pub vehicle_type: VehicleType,
+    pub cruise_velocity: f32,              // Speed the vehicle drives at when nothing is in its way
+    pub acceleration: f32,                 // Acceleration applied in the last update (in m/s²)
+    pub priority: bool,                    // Emergency vehicles get priority at the intersection
+    pub distance_travelled: f32,           // Distance driven since spawning (in meters)
+    pub driver: Option<HumanDriver>,       // Human driver ignoring the manager, None when automated
+    pub behaviour: Behaviour,              // Headway, and how a human driver reacts and judges gaps
+    pub platoon: Option<u32>,              // Id of the first vehicle of its platoon, None when driving alone
+    pub rogue: Option<Rogue>,              // How the vehicle misbehaves, None when it behaves
 }
 
 impl Vehicle {
@@ -50,7 +159,80 @@ impl Vehicle {
             distance_to_intersection,
             time_elapsed: 0.0,
             active: true,
+            vehicle_type: VehicleType::Car,
+            cruise_velocity: velocity,
+            acceleration: 0.0,
+            priority: false,
+            distance_travelled: 0.0,
+            driver: None,
+            behaviour: Behaviour::default(),
+            platoon: None,
+            rogue: None,
+        }
+    }
+
+    /// Sets the vehicle type, capping the speed at the type's maximum
+    ///
+    /// Emergency vehicle types are given priority.
+    pub fn with_type(mut self, vehicle_type: VehicleType) -> Self {
+        let max_speed = vehicle_type.spec().max_speed;
+        self.vehicle_type = vehicle_type;
+        self.priority = vehicle_type.is_emergency();
+        self.velocity = self.velocity.min(max_speed);
+        self.cruise_velocity = self.cruise_velocity.min(max_speed);
+        self
+    }
+
+    /// Hands the vehicle to a human driver
+    pub fn with_driver(mut self, driver: HumanDriver) -> Self {
+        self.driver = Some(driver);
+        self
+    }
+
+    /// Makes the vehicle misbehave; rogue vehicles are automated
+    ///
+    /// Speeding vehicles cruise faster than their type allows.
+    pub fn with_rogue(mut self, kind: Misbehaviour) -> Self {
+        self.driver = None;
+        self.rogue = Some(Rogue::new(kind));
+        if kind == Misbehaviour::Speeding {
+            self.cruise_velocity = self.spec().max_speed * SPEEDING_FACTOR;
         }
+        self
+    }
+
+    /// Sets the behaviour parameters of the vehicle
+    pub fn with_behaviour(mut self, behaviour: Behaviour) -> Self {
+        self.behaviour = behaviour;
+        self
+    }
+
+    /// Whether the vehicle is driven by a human instead of automated
+    pub fn is_human(&self) -> bool {
+        self.driver.is_some()
+    }
+
+    /// Gets the dimensions and dynamic limits of this vehicle's type
+    pub fn spec(&self) -> VehicleSpec {
+        self.vehicle_type.spec()
+    }
+
+    /// Changes velocity towards `target_velocity` within the type's limits
+    ///
+    /// # Arguments
+    /// * `target_velocity` - Desired velocity in m/s
+    /// * `delta_time` - Time over which the change happens (in seconds)
+    pub fn accelerate_towards(&mut self, target_velocity: f32, delta_time: f32) {
+        if delta_time <= 0.0 {
+            return;
+        }
+        let spec = self.spec();
+        let change = (target_velocity - self.velocity).clamp(
+            -spec.max_deceleration * delta_time,
+            spec.max_acceleration * delta_time,
+        );
+        self.velocity = (self.velocity + change).max(0.0);
+        self.acceleration = change / delta_time;
     }
 
     /// Updates the vehicle's position based on its velocity and the time elapsed
@@ -69,6 +251,7 @@ impl Vehicle {
         
         // Update distance to intersection
         self.distance_to_intersection -= distance_traveled;
+        self.distance_travelled += distance_traveled;
         
         // Update position based on direction
         match self.direction {
@@ -97,6 +280,14 @@ impl Vehicle {
         }
     }
 
+    /// Time lost compared to driving the same distance at cruise velocity (in seconds)
+    pub fn delay(&self) -> f32 {
+        if self.cruise_velocity <= 0.0 {
+            return 0.0;
+        }
+        (self.time_elapsed - self.distance_travelled / self.cruise_velocity).max(0.0)
+    }
+
     /// Checks if this vehicle is too close to another vehicle
     /// 
     /// Calculates the Euclidean distance between two vehicles and compares
@@ -138,6 +329,34 @@ impl Vehicle {
     pub fn is_stopped(&self) -> bool {
         self.velocity == 0.0
     }
+
+    /// Index of the lane this vehicle drives in: the route's lane, or
+    /// `BIKE_LANE` for bicycles whatever their route
+    pub fn lane_index(&self) -> usize {
+        if self.vehicle_type.is_bicycle() {
+            BIKE_LANE
+        } else {
+            self.route.lane_index()
+        }
+    }
+
+    /// Identifier of the lane this vehicle drives in, e.g. `North_1`
+    pub fn lane_id(&self) -> String {
+        format!("{}_{}", self.direction, self.lane_index())
+    }
+
+    /// Gets the current state of the vehicle relative to the conflict zone
+    pub fn state(&self) -> VehicleState {
+        if self.is_stopped() {
+            VehicleState::Stopped
+        } else if self.distance_to_intersection > CONFLICT_ZONE_HALF_SIZE {
+            VehicleState::Approaching
+        } else if self.distance_to_intersection > -CONFLICT_ZONE_HALF_SIZE {
+            VehicleState::Crossing
+        } else {
+            VehicleState::Departing
+        }
+    }
 }
 
 #[cfg(test)]
@@ -342,4 +561,66 @@ mod tests {
         assert_eq!(straight.route, Route::Straight);
         assert_eq!(left.route, Route::Left);
     }
+
+    #[test]
+    fn test_with_type_caps_speed() {
+        let vehicle = Vehicle::new(1, (0.0, 0.0), 15.0, Route::Straight, Direction::North, 100.0)
+            .with_type(VehicleType::Bus);
+
+        assert_eq!(vehicle.vehicle_type, VehicleType::Bus);
+        assert_eq!(vehicle.velocity, VehicleType::Bus.spec().max_speed);
+        assert_eq!(vehicle.cruise_velocity, VehicleType::Bus.spec().max_speed);
+    }
+
+    #[test]
+    fn test_accelerate_towards_respects_limits() {
+        let mut truck = Vehicle::new(1, (0.0, 0.0), 10.0, Route::Straight, Direction::North, 100.0)
+            .with_type(VehicleType::Truck);
+        let spec = truck.spec();
+
+        truck.accelerate_towards(0.0, 1.0);
+        assert_eq!(truck.velocity, 10.0 - spec.max_deceleration);
+        assert_eq!(truck.acceleration, -spec.max_deceleration);
+
+        truck.accelerate_towards(12.0, 0.5);
+        assert_eq!(truck.velocity, 10.0 - spec.max_deceleration + spec.max_acceleration * 0.5);
+
+        // Small changes are applied exactly and velocity never goes negative
+        truck.accelerate_towards(6.5, 1.0);
+        assert_eq!(truck.velocity, 6.5);
+        truck.accelerate_towards(-5.0, 10.0);
+        assert_eq!(truck.velocity, 0.0);
+    }
+
+    #[test]
+    fn test_bicycles_use_bike_lane() {
+        let bicycle = Vehicle::new(1, (0.0, 0.0), 5.0, Route::Straight, Direction::West, 50.0)
+            .with_type(VehicleType::Bicycle);
+        assert_eq!(bicycle.lane_index(), BIKE_LANE);
+        assert_eq!(bicycle.lane_id(), "West_3");
+        assert_eq!(bicycle.velocity, 5.0);
+    }
+
+    #[test]
+    fn test_lane_id() {
+        let vehicle = Vehicle::new(1, (0.0, 0.0), 10.0, Route::Left, Direction::East, 100.0);
+        assert_eq!(vehicle.lane_id(), "East_2");
+        assert_eq!(Route::from_lane_index(2), Some(Route::Left));
+        assert_eq!(Route::from_lane_index(3), None);
+    }
+
+    #[test]
+    fn test_vehicle_state() {
+        let mut vehicle = Vehicle::new(1, (0.0, 0.0), 10.0, Route::Straight, Direction::North, 100.0);
+        assert_eq!(vehicle.state(), VehicleState::Approaching);
+
+        vehicle.distance_to_intersection = 0.0;
+        assert_eq!(vehicle.state(), VehicleState::Crossing);
+
+        vehicle.distance_to_intersection = -20.0;
+        assert_eq!(vehicle.state(), VehicleState::Departing);
+
+        vehicle.stop();
+        assert_eq!(vehicle.state(), VehicleState::Stopped);
+    }
 }
\ No newline at end of file
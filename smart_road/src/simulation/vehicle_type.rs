@@ -0,0 +1,262 @@
+use std::fmt;
+use std::str::FromStr;
+use rand::Rng;
+
+/// Kinds of vehicles that can be spawned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VehicleType {
+    Car,
+    Van,
+    Truck,
+    Bus,
+    Motorcycle,
+    Ambulance,
+    FireTruck,
+    Bicycle,
+}
+
+/// Physical dimensions and dynamic limits of a vehicle type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VehicleSpec {
+    pub length: f32,            // in meters
+    pub width: f32,             // in meters
+    pub max_speed: f32,         // in m/s
+    pub max_acceleration: f32,  // in m/s²
+    pub max_deceleration: f32,  // Comfortable braking limit in m/s² (positive)
+}
+
+impl VehicleType {
+    /// All vehicle types, in a fixed order
+    pub const ALL: [VehicleType; 8] = [
+        VehicleType::Car,
+        VehicleType::Van,
+        VehicleType::Truck,
+        VehicleType::Bus,
+        VehicleType::Motorcycle,
+        VehicleType::Ambulance,
+        VehicleType::FireTruck,
+        VehicleType::Bicycle,
+    ];
+
+    /// Gets the dimensions and dynamic limits of this vehicle type
+    pub fn spec(&self) -> VehicleSpec {
+        match self {
+            VehicleType::Car => VehicleSpec {
+                length: 4.5,
+                width: 1.8,
+                max_speed: 15.0,
+                max_acceleration: 3.0,
+                max_deceleration: 6.0,
+            },
+            VehicleType::Van => VehicleSpec {
+                length: 5.5,
+                width: 2.0,
+                max_speed: 14.0,
+                max_acceleration: 2.2,
+                max_deceleration: 5.0,
+            },
+            VehicleType::Truck => VehicleSpec {
+                length: 10.0,
+                width: 2.5,
+                max_speed: 12.0,
+                max_acceleration: 1.2,
+                max_deceleration: 4.0,
+            },
+            VehicleType::Bus => VehicleSpec {
+                length: 12.0,
+                width: 2.55,
+                max_speed: 12.0,
+                max_acceleration: 1.2,
+                max_deceleration: 4.0,
+            },
+            VehicleType::Motorcycle => VehicleSpec {
+                length: 2.2,
+                width: 0.8,
+                max_speed: 15.0,
+                max_acceleration: 4.0,
+                max_deceleration: 7.0,
+            },
+            VehicleType::Ambulance => VehicleSpec {
+                length: 6.5,
+                width: 2.2,
+                max_speed: 15.0,
+                max_acceleration: 2.5,
+                max_deceleration: 5.5,
+            },
+            VehicleType::FireTruck => VehicleSpec {
+                length: 10.0,
+                width: 2.5,
+                max_speed: 14.0,
+                max_acceleration: 1.5,
+                max_deceleration: 4.5,
+            },
+            VehicleType::Bicycle => VehicleSpec {
+                length: 1.8,
+                width: 0.6,
+                max_speed: 5.5,
+                max_acceleration: 1.0,
+                max_deceleration: 3.0,
+            },
+        }
+    }
+
+    /// Whether vehicles of this type have priority at the intersection
+    pub fn is_emergency(&self) -> bool {
+        matches!(self, VehicleType::Ambulance | VehicleType::FireTruck)
+    }
+
+    /// Whether this is a bicycle, which rides in the bike lane
+    pub fn is_bicycle(&self) -> bool {
+        *self == VehicleType::Bicycle
+    }
+
+    /// Lowercase name, used for sprite folders and exported files
+    pub fn name(&self) -> &'static str {
+        match self {
+            VehicleType::Car => "car",
+            VehicleType::Van => "van",
+            VehicleType::Truck => "truck",
+            VehicleType::Bus => "bus",
+            VehicleType::Motorcycle => "motorcycle",
+            VehicleType::Ambulance => "ambulance",
+            VehicleType::FireTruck => "fire_truck",
+            VehicleType::Bicycle => "bicycle",
+        }
+    }
+}
+
+impl fmt::Display for VehicleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for VehicleType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        VehicleType::ALL
+            .iter()
+            .find(|vehicle_type| vehicle_type.name() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown vehicle type: {}", s))
+    }
+}
+
+/// Relative share of each vehicle type among generated vehicles
+#[derive(Debug, Clone, PartialEq)]
+pub struct VehicleMix {
+    weights: Vec<(VehicleType, f32)>,
+}
+
+impl VehicleMix {
+    /// Creates a mix from (type, weight) pairs; weights need not sum to 1
+    pub fn new(weights: Vec<(VehicleType, f32)>) -> Result<Self, String> {
+        if weights.iter().any(|(_, weight)| !weight.is_finite() || *weight < 0.0) {
+            return Err("Vehicle mix weights must be finite and not negative".to_string());
+        }
+        if weights.iter().map(|(_, weight)| weight).sum::<f32>() <= 0.0 {
+            return Err("Vehicle mix needs at least one positive weight".to_string());
+        }
+        Ok(VehicleMix { weights })
+    }
+
+    /// Draws a vehicle type according to the weights
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> VehicleType {
+        let total: f32 = self.weights.iter().map(|(_, weight)| weight).sum();
+        let mut pick = rng.random_range(0.0..total);
+        for (vehicle_type, weight) in &self.weights {
+            if pick < *weight {
+                return *vehicle_type;
+            }
+            pick -= weight;
+        }
+        // Only reachable through rounding at the upper end
+        self.weights.last().map_or(VehicleType::Car, |(vehicle_type, _)| *vehicle_type)
+    }
+}
+
+impl Default for VehicleMix {
+    fn default() -> Self {
+        VehicleMix {
+            weights: vec![
+                (VehicleType::Car, 0.55),
+                (VehicleType::Van, 0.15),
+                (VehicleType::Truck, 0.1),
+                (VehicleType::Bus, 0.05),
+                (VehicleType::Motorcycle, 0.1),
+                (VehicleType::Bicycle, 0.05),
+            ],
+        }
+    }
+}
+
+impl FromStr for VehicleMix {
+    type Err = String;
+
+    /// Parses a mix such as `car=60,truck=20,bus=20`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let weights = s
+            .split(',')
+            .map(|entry| {
+                let (name, weight) = entry
+                    .split_once('=')
+                    .ok_or_else(|| format!("Expected type=weight, found: {}", entry))?;
+                let weight: f32 = weight
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid weight: {}", weight))?;
+                Ok((name.trim().parse()?, weight))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        VehicleMix::new(weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::rng::SimRng;
+
+    #[test]
+    fn test_name_round_trip() {
+        for vehicle_type in VehicleType::ALL {
+            assert_eq!(vehicle_type.name().parse::<VehicleType>(), Ok(vehicle_type));
+        }
+        assert!("tram".parse::<VehicleType>().is_err());
+    }
+
+    #[test]
+    fn test_heavy_vehicles_are_slower_to_react() {
+        let car = VehicleType::Car.spec();
+        let truck = VehicleType::Truck.spec();
+        assert!(truck.length > car.length);
+        assert!(truck.max_acceleration < car.max_acceleration);
+        assert!(truck.max_deceleration < car.max_deceleration);
+    }
+
+    #[test]
+    fn test_parse_mix() {
+        let mix: VehicleMix = "car=3, bus=1".parse().unwrap();
+        assert_eq!(mix, VehicleMix::new(vec![(VehicleType::Car, 3.0), (VehicleType::Bus, 1.0)]).unwrap());
+
+        assert!("car".parse::<VehicleMix>().is_err());
+        assert!("car=0".parse::<VehicleMix>().is_err());
+        assert!("car=-1,bus=2".parse::<VehicleMix>().is_err());
+        assert!("car=NaN".parse::<VehicleMix>().is_err());
+        assert!("car=inf".parse::<VehicleMix>().is_err());
+    }
+
+    #[test]
+    fn test_sample_follows_weights() {
+        let mix = VehicleMix::new(vec![(VehicleType::Truck, 0.0), (VehicleType::Bus, 1.0)]).unwrap();
+        let mut rng = SimRng::new(5);
+        for _ in 0..100 {
+            assert_eq!(mix.sample(&mut rng), VehicleType::Bus);
+        }
+
+        let mix = VehicleMix::new(vec![(VehicleType::Car, 1.0), (VehicleType::Van, 1.0)]).unwrap();
+        let vans = (0..1000).filter(|_| mix.sample(&mut rng) == VehicleType::Van).count();
+        assert!((400..600).contains(&vans));
+    }
+}
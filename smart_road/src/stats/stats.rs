@@ -5,13 +5,74 @@ use sdl2::video::Window;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::ttf::Font;
+use crate::simulation::Vehicle;
+use crate::simulation::auction::AuctionOutcome;
 
-#[derive(Debug)]
+/// Delay accumulated by vehicles that have left the intersection
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DelayStats {
+    pub count: u32,
+    pub total: f32,   // in seconds
+    pub max: f32,     // in seconds
+    pub sum_squares: f32,   // Sum of squared delays, for the fairness index
+}
+
+impl DelayStats {
+    /// Adds the delay of one exited vehicle
+    pub fn record(&mut self, delay: f32) {
+        self.count += 1;
+        self.total += delay;
+        self.max = self.max.max(delay);
+        self.sum_squares += delay * delay;
+    }
+
+    /// Average delay per vehicle (0 when no vehicle has exited)
+    pub fn average(&self) -> f32 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.total / self.count as f32
+        }
+    }
+
+    /// Jain's fairness index of the delays, from 1/count when one vehicle
+    /// took all the delay to 1 when everyone was delayed equally (1 when
+    /// nobody was delayed)
+    pub fn fairness(&self) -> f32 {
+        if self.sum_squares == 0.0 {
+            1.0
+        } else {
+            self.total * self.total / (self.count as f32 * self.sum_squares)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct StatisticsManager {
     pub num_cars: i32,
     pub num_close: i32,
     pub max_velo: f32,
     pub min_velo: f32,
+    /// Delay of regular vehicles, reported apart from emergency vehicles
+    pub regular_delay: DelayStats,
+    pub emergency_delay: DelayStats,
+    pub cyclist_delay: DelayStats,
+    /// Time pedestrians waited at the curb before crossing
+    pub pedestrian_wait: DelayStats,
+    /// Pedestrians that had a vehicle on their crosswalk while crossing
+    pub pedestrian_close_calls: i32,
+    /// Pairs of vehicles with conflicting movements that were inside the
+    /// intersection at the same time
+    pub zone_conflicts: i32,
+    /// Pairs of vehicles that ran into each other
+    pub collisions: i32,
+    /// Times a vehicle had to brake harder than it comfortably can
+    pub emergency_brakings: i32,
+    /// Sizes of the platoons that crossed (count, total and largest size)
+    pub platoon_size: DelayStats,
+    /// Bids and outcomes of the vehicles that won or withdrew from an
+    /// auction, in order
+    pub auction_log: Vec<AuctionOutcome>,
 }
 
 impl StatisticsManager {
@@ -21,6 +82,16 @@ impl StatisticsManager {
             num_close: 0,
             max_velo: f32::MIN,
             min_velo: f32::MAX,
+            regular_delay: DelayStats::default(),
+            emergency_delay: DelayStats::default(),
+            cyclist_delay: DelayStats::default(),
+            pedestrian_wait: DelayStats::default(),
+            pedestrian_close_calls: 0,
+            zone_conflicts: 0,
+            collisions: 0,
+            emergency_brakings: 0,
+            platoon_size: DelayStats::default(),
+            auction_log: Vec::new(),
         }
     }
     
@@ -47,15 +118,15 @@ impl StatisticsManager {
         
         // Stats panel background
         canvas.set_draw_color(Color::RGB(50, 50, 50));
-        canvas.fill_rect(Rect::new(150, 200, 400, 400))?;
+        canvas.fill_rect(Rect::new(150, 200, 400, 420))?;
         
         // Car count text
         let cars_text = format!("Total Cars: {}", self.num_cars);
-        self.render_text(canvas, font, texture_creator, &cars_text, 200, 250, Color::RGB(255, 255, 255))?;
+        self.render_text(canvas, font, texture_creator, &cars_text, 200, 220, Color::RGB(255, 255, 255))?;
         
         // Close calls text
         let close_text = format!("Close Calls: {}", self.num_close);
-        self.render_text(canvas, font, texture_creator, &close_text, 200, 310, Color::RGB(255, 165, 0))?;
+        self.render_text(canvas, font, texture_creator, &close_text, 200, 270, Color::RGB(255, 165, 0))?;
         
         // Max velocity text
         let max_velo_text = if self.num_cars == 0 || self.max_velo == f32::MIN {
@@ -63,7 +134,7 @@ impl StatisticsManager {
         } else {
             format!("Max Velocity: {:.2} m/s", self.max_velo)
         };
-        self.render_text(canvas, font, texture_creator, &max_velo_text, 200, 370, Color::RGB(0, 255, 0))?;
+        self.render_text(canvas, font, texture_creator, &max_velo_text, 200, 320, Color::RGB(0, 255, 0))?;
         
         // Min velocity text
         let min_velo_text = if self.num_cars == 0 || self.min_velo == f32::MAX {
@@ -71,7 +142,33 @@ impl StatisticsManager {
         } else {
             format!("Min Velocity: {:.2} m/s", self.min_velo)
         };
-        self.render_text(canvas, font, texture_creator, &min_velo_text, 200, 430, Color::RGB(0, 200, 255))?;
+        self.render_text(canvas, font, texture_creator, &min_velo_text, 200, 370, Color::RGB(0, 200, 255))?;
+
+        // Average delay, emergency vehicles reported separately
+        let regular_text = format!("Avg Delay: {:.2} s", self.regular_delay.average());
+        self.render_text(canvas, font, texture_creator, &regular_text, 200, 420, Color::RGB(255, 255, 0))?;
+
+        let emergency_text = if self.emergency_delay.count == 0 {
+            "Emergency Delay: N/A".to_string()
+        } else {
+            format!("Emergency Delay: {:.2} s", self.emergency_delay.average())
+        };
+        self.render_text(canvas, font, texture_creator, &emergency_text, 200, 470, Color::RGB(255, 80, 80))?;
+
+        // Pedestrian waiting time and close calls with vehicles
+        let pedestrian_text = format!(
+            "Ped Wait: {:.2} s  Close: {}",
+            self.pedestrian_wait.average(),
+            self.pedestrian_close_calls,
+        );
+        self.render_text(canvas, font, texture_creator, &pedestrian_text, 200, 520, Color::RGB(200, 150, 255))?;
+
+        let cyclist_text = if self.cyclist_delay.count == 0 {
+            "Cyclist Delay: N/A".to_string()
+        } else {
+            format!("Cyclist Delay: {:.2} s", self.cyclist_delay.average())
+        };
+        self.render_text(canvas, font, texture_creator, &cyclist_text, 200, 570, Color::RGB(120, 220, 220))?;
         
         Ok(())
     }
@@ -110,4 +207,46 @@ impl StatisticsManager {
         self.max_velo = self.max_velo.max(velocity);
         self.min_velo = self.min_velo.min(velocity); 
     }
+
+    /// Records how long a pedestrian waited before crossing
+    pub fn record_pedestrian_wait(&mut self, waiting_time: f32) {
+        self.pedestrian_wait.record(waiting_time);
+    }
+
+    pub fn record_pedestrian_close_call(&mut self) {
+        self.pedestrian_close_calls += 1;
+    }
+
+    pub fn record_zone_conflict(&mut self) {
+        self.zone_conflicts += 1;
+    }
+
+    pub fn record_collision(&mut self) {
+        self.collisions += 1;
+    }
+
+    pub fn record_emergency_braking(&mut self) {
+        self.emergency_brakings += 1;
+    }
+
+    /// Records the size of a platoon whose head left the intersection
+    pub fn record_platoon(&mut self, size: usize) {
+        self.platoon_size.record(size as f32);
+    }
+
+    /// Records the outcome of a vehicle that won or withdrew from an auction
+    pub fn record_auction(&mut self, outcome: AuctionOutcome) {
+        self.auction_log.push(outcome);
+    }
+
+    /// Records the delay of a vehicle leaving the intersection
+    pub fn record_exit(&mut self, vehicle: &Vehicle) {
+        if vehicle.priority {
+            self.emergency_delay.record(vehicle.delay());
+        } else if vehicle.vehicle_type.is_bicycle() {
+            self.cyclist_delay.record(vehicle.delay());
+        } else {
+            self.regular_delay.record(vehicle.delay());
+        }
+    }
 }
\ No newline at end of file